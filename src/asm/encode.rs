@@ -0,0 +1,101 @@
+//! Turns a mnemonic and its operands into an [`Instruction`].
+
+use super::parse::{self, Operand};
+use crate::instruction::Instruction;
+
+/// The mnemonics [`encode`] understands, used to tell instructions from directives.
+pub const MNEMONICS : &[&str] = &[
+    "cls", "ret", "scd", "scu", "scr", "scl", "exit", "low", "high", "jp", "call", "se", "sne",
+    "save", "load", "ld", "add", "or", "and", "xor", "sub", "shr", "subn", "shl", "rnd", "drw",
+    "skp", "sknp", "plane", "audio", "pitch",
+];
+
+/// Size in bytes of an instruction, which is known before its operands can be evaluated.
+pub fn size(mnemonic: &str, operands: &[Operand]) -> u16
+{
+    match (mnemonic, operands)
+    {
+        ("ld", [Operand::Index, Operand::Long(_)]) => 4,
+        _ => 2,
+    }
+}
+
+/// Encodes one instruction. `evaluate` resolves expressions to numbers.
+pub fn encode(mnemonic: &str, operands: &[Operand], evaluate: &dyn Fn(&parse::Expr) -> Result<i64, String>)
+    -> Result<Instruction, String>
+{
+    use Operand::*;
+
+    let address = |expr: &parse::Expr| ranged(evaluate(expr)?, 0, 0xFFF, "address").map(|value| value as u16);
+    let long_address = |expr: &parse::Expr| ranged(evaluate(expr)?, 0, 0xFFFF, "address").map(|value| value as u16);
+    // Bytes may be given as signed values, -1 is the same as 0xFF.
+    let byte = |expr: &parse::Expr| ranged(evaluate(expr)?, -128, 0xFF, "byte").map(|value| value as u8);
+    let nibble = |expr: &parse::Expr| ranged(evaluate(expr)?, 0, 0xF, "value").map(|value| value as u8);
+
+    let instruction = match (mnemonic, operands)
+    {
+        ("cls", []) => Instruction::ClearScreen,
+        ("ret", []) => Instruction::Return,
+        ("scd", [Expr(n)]) => Instruction::ScrollDown{ n: nibble(n)? },
+        ("scu", [Expr(n)]) => Instruction::ScrollUp{ n: nibble(n)? },
+        ("scr", []) => Instruction::ScrollRight,
+        ("scl", []) => Instruction::ScrollLeft,
+        ("exit", []) => Instruction::Exit,
+        ("low", []) => Instruction::LowRes,
+        ("high", []) => Instruction::HighRes,
+        ("jp", [Expr(nnn)]) => Instruction::Jump{ nnn: address(nnn)? },
+        ("jp", [Register(0), Expr(nnn)]) => Instruction::JumpOffset{ nnn: address(nnn)? },
+        ("call", [Expr(nnn)]) => Instruction::Call{ nnn: address(nnn)? },
+        ("se", [Register(x), Expr(nn)]) => Instruction::SkipIfEqual{ x: *x, nn: byte(nn)? },
+        ("se", [Register(x), Register(y)]) => Instruction::SkipIfRegistersEqual{ x: *x, y: *y },
+        ("sne", [Register(x), Expr(nn)]) => Instruction::SkipIfNotEqual{ x: *x, nn: byte(nn)? },
+        ("sne", [Register(x), Register(y)]) => Instruction::SkipIfRegistersNotEqual{ x: *x, y: *y },
+        ("save", [Register(x), Register(y)]) => Instruction::SaveRange{ x: *x, y: *y },
+        ("load", [Register(x), Register(y)]) => Instruction::LoadRange{ x: *x, y: *y },
+        ("ld", [Register(x), Expr(nn)]) => Instruction::Assign{ x: *x, nn: byte(nn)? },
+        ("ld", [Register(x), Register(y)]) => Instruction::Copy{ x: *x, y: *y },
+        ("ld", [Index, Long(nnnn)]) => Instruction::LongIndex{ nnnn: long_address(nnnn)? },
+        ("ld", [Index, Expr(nnn)]) => Instruction::SetIndex{ nnn: address(nnn)? },
+        ("ld", [Register(x), DelayTimer]) => Instruction::GetDelayTimer{ x: *x },
+        ("ld", [Register(x), Key]) => Instruction::WaitForKey{ x: *x },
+        ("ld", [DelayTimer, Register(x)]) => Instruction::SetDelayTimer{ x: *x },
+        ("ld", [SoundTimer, Register(x)]) => Instruction::SetSoundTimer{ x: *x },
+        ("ld", [Font, Register(x)]) => Instruction::Font{ x: *x },
+        ("ld", [BigFont, Register(x)]) => Instruction::BigFont{ x: *x },
+        ("ld", [Bcd, Register(x)]) => Instruction::BinaryCodedDecimal{ x: *x },
+        ("ld", [IndexedMemory, Register(x)]) => Instruction::Store{ x: *x },
+        ("ld", [Register(x), IndexedMemory]) => Instruction::Load{ x: *x },
+        ("ld", [Flags, Register(x)]) => Instruction::SaveFlags{ x: *x },
+        ("ld", [Register(x), Flags]) => Instruction::LoadFlags{ x: *x },
+        ("add", [Register(x), Expr(nn)]) => Instruction::AddImmediate{ x: *x, nn: byte(nn)? },
+        ("add", [Register(x), Register(y)]) => Instruction::Add{ x: *x, y: *y },
+        ("add", [Index, Register(x)]) => Instruction::AddToIndex{ x: *x },
+        ("or", [Register(x), Register(y)]) => Instruction::Or{ x: *x, y: *y },
+        ("and", [Register(x), Register(y)]) => Instruction::And{ x: *x, y: *y },
+        ("xor", [Register(x), Register(y)]) => Instruction::Xor{ x: *x, y: *y },
+        ("sub", [Register(x), Register(y)]) => Instruction::Subtract{ x: *x, y: *y },
+        ("subn", [Register(x), Register(y)]) => Instruction::SubtractReverse{ x: *x, y: *y },
+        ("shr", [Register(x)]) => Instruction::ShiftRight{ x: *x, y: *x },
+        ("shr", [Register(x), Register(y)]) => Instruction::ShiftRight{ x: *x, y: *y },
+        ("shl", [Register(x)]) => Instruction::ShiftLeft{ x: *x, y: *x },
+        ("shl", [Register(x), Register(y)]) => Instruction::ShiftLeft{ x: *x, y: *y },
+        ("rnd", [Register(x), Expr(nn)]) => Instruction::Random{ x: *x, nn: byte(nn)? },
+        ("drw", [Register(x), Register(y), Expr(n)]) => Instruction::Draw{ x: *x, y: *y, n: nibble(n)? },
+        ("skp", [Register(x)]) => Instruction::SkipIfKey{ x: *x },
+        ("sknp", [Register(x)]) => Instruction::SkipIfNotKey{ x: *x },
+        ("plane", [Expr(n)]) => Instruction::SelectPlanes{ n: ranged(evaluate(n)?, 0, 3, "plane mask")? as u8 },
+        ("audio", []) => Instruction::LoadAudio,
+        ("pitch", [Register(x)]) => Instruction::Pitch{ x: *x },
+        _ => return Err(format!("invalid operands for {}", mnemonic.to_ascii_uppercase())),
+    };
+    Ok(instruction)
+}
+
+fn ranged(value: i64, min: i64, max: i64, what: &str) -> Result<i64, String>
+{
+    if value < min || value > max
+    {
+        return Err(format!("{} {} is out of range ({} to 0x{:X})", what, value, min, max));
+    }
+    Ok(value & max)
+}
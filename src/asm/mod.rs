@@ -0,0 +1,414 @@
+//! An assembler for CHIP-8 programs.
+//!
+//! Instructions are written the way the [`disasm`](crate::disasm) module prints them. One
+//! statement goes on each line, optionally preceded by a label, and everything after a `;`
+//! is a comment:
+//!
+//! ```text
+//! digit   equ 7               ; constants must be defined before they are used
+//!
+//! start:  cls
+//!         ld v0, digit
+//!         ld f, v0
+//!         ld v1, 10
+//!         drw v1, v1, 5
+//! loop:   jp loop
+//!
+//!         include "sprites.asm"
+//! pattern: db 0b10101010, 0x55, "text"
+//! table:   dw start, loop + 2
+//! ```
+//!
+//! Mnemonics, register names and the `I`, `[I]`, `DT`, `ST`, `K`, `F`, `HF`, `B` and `R`
+//! operands are case insensitive, labels and constants are not. Numbers may be decimal,
+//! hex with a `0x`, `$` or `#` prefix, binary with `0b`, or a character in single quotes.
+//! Expressions combine them with `+ - * / & |` and parentheses, and `$` on its own is the
+//! address of the current statement.
+//!
+//! Besides the instructions there are the directives `db` and `dw` for data, `equ` for
+//! constants, `org` to continue at a higher address and `include` to assemble another file
+//! in place. Programs start at [`PROGRAM_START`], where [`Chip::load_rom`](crate::Chip::load_rom)
+//! puts them.
+
+mod encode;
+mod parse;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::chip::PROGRAM_START;
+use crate::instruction::Instruction;
+use crate::variant::Variant;
+use parse::{Operand, Statement};
+
+/// An error in the source, with the file and line it was found on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError
+{
+    pub file : String,
+    pub line : usize,
+    pub message : String,
+}
+
+impl fmt::Display for AsmError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// The bytes one source line assembled to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingLine
+{
+    pub address : u16,
+    pub bytes : Vec<u8>,
+    pub file : String,
+    pub line : usize,
+    pub source : String,
+}
+
+/// The result of assembling a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly
+{
+    /// The program, to be loaded at [`PROGRAM_START`].
+    pub bytes : Vec<u8>,
+    /// Every source line in order, including the ones that produced no bytes.
+    pub listing : Vec<ListingLine>,
+    /// The value of every label and constant.
+    pub symbols : BTreeMap<String, i64>,
+}
+
+impl Assembly
+{
+    /// Renders the listing: address, up to four bytes of machine code, line number and source
+    /// for every line, followed by the symbol table.
+    pub fn format_listing(&self) -> String
+    {
+        let mut out = String::new();
+        let mut file = None;
+        for line in &self.listing
+        {
+            // Name the file whenever the listing enters a different one, e.g. after an include.
+            if file != Some(&line.file)
+            {
+                writeln!(out, "; {}", line.file).unwrap();
+                file = Some(&line.file);
+            }
+            let mut chunks = line.bytes.chunks(4);
+            let first : String = chunks.next().unwrap_or(&[]).iter().map(|byte| format!("{:02X}", byte)).collect();
+            let address = if line.bytes.is_empty() {"    ".to_string()} else {format!("{:04X}", line.address)};
+            writeln!(out, "{}  {:<8}  {:>5}  {}", address, first, line.line, line.source).unwrap();
+
+            for (index, chunk) in chunks.enumerate()
+            {
+                let hex : String = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+                writeln!(out, "{:04X}  {}", line.address as usize + (index + 1) * 4, hex).unwrap();
+            }
+        }
+
+        writeln!(out, "\nsymbols:").unwrap();
+        for (name, value) in &self.symbols
+        {
+            writeln!(out, "    {:<24} 0x{:04X}", name, value).unwrap();
+        }
+        out
+    }
+}
+
+/// One line of source after includes were expanded.
+struct SourceLine
+{
+    file : String,
+    line : usize,
+    text : String,
+    statement : Statement,
+    /// Address of the line, filled in by the first pass. It is only narrowed to `u16` in the
+    /// listing, since a line after the last byte of memory is one past the largest address.
+    address : usize,
+}
+
+impl SourceLine
+{
+    fn error(&self, message: String) -> AsmError
+    {
+        AsmError{ file: self.file.clone(), line: self.line, message }
+    }
+}
+
+/// Assembles source files for one instruction set.
+#[derive(Debug, Clone, Copy)]
+pub struct Assembler
+{
+    variant : Variant,
+}
+
+impl Assembler
+{
+    /// Creates an assembler that accepts the instructions of `variant`.
+    pub fn new(variant: Variant) -> Assembler
+    {
+        Assembler{ variant }
+    }
+
+    /// Assembles the file at `path`. Includes are looked up relative to the including file.
+    pub fn assemble_file<P: AsRef<Path>>(&self, path: P) -> Result<Assembly, AsmError>
+    {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let text = fs::read_to_string(path)
+            .map_err(|error| AsmError{ file: name.clone(), line: 0, message: error.to_string() })?;
+        let mut lines = Vec::new();
+        let mut include_stack = vec![path.to_path_buf()];
+        self.read_lines(&name, &text, path.parent().unwrap_or(Path::new("")), &mut include_stack, &mut lines)?;
+        self.assemble_lines(lines)
+    }
+
+    /// Assembles `source`, reporting errors against the file name `name`. Includes are looked
+    /// up relative to the working directory.
+    pub fn assemble_source(&self, name: &str, source: &str) -> Result<Assembly, AsmError>
+    {
+        let mut lines = Vec::new();
+        self.read_lines(name, source, Path::new(""), &mut Vec::new(), &mut lines)?;
+        self.assemble_lines(lines)
+    }
+
+    /// Parses every line of `text`, replacing `include` directives by the included lines.
+    fn read_lines(&self, name: &str, text: &str, directory: &Path, include_stack: &mut Vec<PathBuf>,
+        lines: &mut Vec<SourceLine>) -> Result<(), AsmError>
+    {
+        for (index, raw_line) in text.lines().enumerate()
+        {
+            let error = |message: String| AsmError{ file: name.to_string(), line: index + 1, message };
+            let statement = parse::parse_line(raw_line).map_err(error)?;
+
+            if statement.mnemonic.as_deref() == Some("include")
+            {
+                let file = match statement.operands.as_slice()
+                {
+                    [Operand::Text(file)] => file,
+                    _ => return Err(error("include expects a quoted file name".to_string())),
+                };
+                let path = directory.join(file);
+                if include_stack.contains(&path)
+                {
+                    return Err(error(format!("{} includes itself", path.display())));
+                }
+                let included = fs::read_to_string(&path)
+                    .map_err(|io_error| error(format!("cannot include {}: {}", path.display(), io_error)))?;
+
+                include_stack.push(path.clone());
+                let included_name = path.display().to_string();
+                self.read_lines(&included_name, &included, path.parent().unwrap_or(Path::new("")), include_stack, lines)?;
+                include_stack.pop();
+                continue;
+            }
+
+            lines.push(SourceLine{
+                file: name.to_string(),
+                line: index + 1,
+                text: raw_line.trim_end().to_string(),
+                statement,
+                address: 0,
+            });
+        }
+        Ok(())
+    }
+
+    fn assemble_lines(&self, mut lines: Vec<SourceLine>) -> Result<Assembly, AsmError>
+    {
+        let symbols = self.assign_addresses(&mut lines)?;
+        let memory_size = self.variant.memory_size();
+
+        let mut bytes : Vec<u8> = Vec::new();
+        let mut listing = Vec::new();
+        for line in &lines
+        {
+            let statement = &line.statement;
+            let evaluate = |expr: &parse::Expr| expr.evaluate(&symbols, line.address);
+
+            let emitted = match statement.mnemonic.as_deref()
+            {
+                None | Some("equ") | Some("org") => Vec::new(),
+                Some("db") => self.data(line, 1, &evaluate)?,
+                Some("dw") => self.data(line, 2, &evaluate)?,
+                Some(mnemonic) =>
+                {
+                    let instruction = encode::encode(mnemonic, &statement.operands, &evaluate)
+                        .map_err(|message| line.error(message))?;
+                    self.check_variant(instruction).map_err(|message| line.error(message))?;
+                    instruction.to_bytes()
+                },
+            };
+
+            // `org` leaves a gap that is filled with zeros.
+            let offset = line.address - PROGRAM_START as usize;
+            if offset > bytes.len() && !emitted.is_empty()
+            {
+                bytes.resize(offset, 0);
+            }
+            bytes.extend_from_slice(&emitted);
+            if PROGRAM_START as usize + bytes.len() > memory_size
+            {
+                return Err(line.error(format!("the program does not fit into {} bytes of memory", memory_size)));
+            }
+
+            listing.push(ListingLine{
+                address: line.address as u16,
+                bytes: emitted,
+                file: line.file.clone(),
+                line: line.line,
+                source: line.text.clone(),
+            });
+        }
+
+        Ok(Assembly{
+            bytes,
+            listing,
+            symbols: symbols.into_iter().collect(),
+        })
+    }
+
+    /// The first pass: works out the address of every line and defines labels and constants.
+    fn assign_addresses(&self, lines: &mut [SourceLine]) -> Result<HashMap<String, i64>, AsmError>
+    {
+        let mut symbols : HashMap<String, i64> = HashMap::new();
+        let mut address = PROGRAM_START as usize;
+
+        for line in lines.iter_mut()
+        {
+            let statement = &line.statement;
+            let mut line_address = address;
+            let error = |message: String| AsmError{ file: line.file.clone(), line: line.line, message };
+
+            let mut define = |name: &str, value: i64| -> Result<(), AsmError>
+            {
+                if symbols.insert(name.to_string(), value).is_some()
+                {
+                    return Err(error(format!("{} is defined twice", name)));
+                }
+                Ok(())
+            };
+            if let Some(label) = &statement.label
+            {
+                define(label, address as i64)?;
+            }
+
+            let operand_value = |symbols: &HashMap<String, i64>| match statement.operands.as_slice()
+            {
+                [Operand::Expr(expr)] => expr.evaluate(symbols, address).map_err(&error),
+                _ => Err(error(format!("{} expects a single value", statement.mnemonic.as_deref().unwrap_or("")))),
+            };
+
+            let size = match statement.mnemonic.as_deref()
+            {
+                None => 0,
+                Some("equ") =>
+                {
+                    let name = statement.constant.as_deref().ok_or_else(|| error("equ expects a name".to_string()))?;
+                    let value = operand_value(&symbols)?;
+                    if symbols.insert(name.to_string(), value).is_some()
+                    {
+                        return Err(error(format!("{} is defined twice", name)));
+                    }
+                    0
+                },
+                Some("org") =>
+                {
+                    let target = operand_value(&symbols)?;
+                    if target < address as i64
+                    {
+                        return Err(error(format!("org 0x{:X} is below the current address 0x{:X}", target, address)));
+                    }
+                    if target >= self.variant.memory_size() as i64
+                    {
+                        return Err(error(format!("org 0x{:X} is beyond the end of memory at 0x{:X}", target, self.variant.memory_size())));
+                    }
+                    address = target as usize;
+                    line_address = address;
+                    0
+                },
+                Some("db") => statement.operands.iter().map(|operand| match operand
+                {
+                    Operand::Text(text) => text.len(),
+                    _ => 1,
+                }).sum(),
+                Some("dw") => statement.operands.len() * 2,
+                Some(mnemonic) if encode::MNEMONICS.contains(&mnemonic) =>
+                    encode::size(mnemonic, &statement.operands) as usize,
+                Some(mnemonic) => return Err(error(format!("unknown instruction {}", mnemonic.to_ascii_uppercase()))),
+            };
+            if statement.constant.is_some() && statement.mnemonic.as_deref() != Some("equ")
+            {
+                return Err(error("unexpected text before the instruction".to_string()));
+            }
+
+            address += size;
+            if address > self.variant.memory_size()
+            {
+                return Err(error(format!("the program does not fit into {} bytes of memory", self.variant.memory_size())));
+            }
+            line.address = line_address;
+        }
+        Ok(symbols)
+    }
+
+    /// Encodes the operands of a `db` (`width` 1) or `dw` (`width` 2) directive.
+    fn data(&self, line: &SourceLine, width: usize, evaluate: &dyn Fn(&parse::Expr) -> Result<i64, String>)
+        -> Result<Vec<u8>, AsmError>
+    {
+        let (min, max) = if width == 1 {(-0x80, 0xFF)} else {(-0x8000, 0xFFFF)};
+        let mut bytes = Vec::new();
+        if line.statement.operands.is_empty()
+        {
+            return Err(line.error("missing data".to_string()));
+        }
+        for operand in &line.statement.operands
+        {
+            match operand
+            {
+                Operand::Text(text) if width == 1 => bytes.extend_from_slice(text.as_bytes()),
+                Operand::Expr(expr) =>
+                {
+                    let value = evaluate(expr).map_err(|message| line.error(message))?;
+                    if value < min || value > max
+                    {
+                        return Err(line.error(format!("{} does not fit into {} byte(s)", value, width)));
+                    }
+                    let value = (value & max) as u16;
+                    if width == 1
+                    {
+                        bytes.push(value as u8);
+                    }
+                    else
+                    {
+                        bytes.extend_from_slice(&value.to_be_bytes());
+                    }
+                },
+                _ => return Err(line.error("data must be numbers or, for db, strings".to_string())),
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Makes sure the selected variant knows the instruction, by decoding it again.
+    fn check_variant(&self, instruction: Instruction) -> Result<(), String>
+    {
+        let next = match instruction
+        {
+            Instruction::LongIndex{ nnnn } => nnnn,
+            _ => 0,
+        };
+        if Instruction::decode(instruction.opcode(), next, self.variant) != Some(instruction)
+        {
+            return Err(format!("{} is not available in {} mode", instruction, self.variant));
+        }
+        Ok(())
+    }
+}
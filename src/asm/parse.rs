@@ -0,0 +1,344 @@
+//! Splits source lines into labels, mnemonics and operands, and parses operand expressions.
+
+use std::collections::HashMap;
+
+use crate::literal;
+
+/// One source line with the comment removed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Statement
+{
+    pub label : Option<String>,
+    /// The mnemonic or directive, lowercased.
+    pub mnemonic : Option<String>,
+    pub operands : Vec<Operand>,
+    /// The name before an `equ` directive, which is a constant rather than a label.
+    pub constant : Option<String>,
+}
+
+/// A single operand of an instruction or directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand
+{
+    /// One of the registers V0 to VF.
+    Register(u8),
+    /// `I`
+    Index,
+    /// `[I]`
+    IndexedMemory,
+    /// `DT`
+    DelayTimer,
+    /// `ST`
+    SoundTimer,
+    /// `K`
+    Key,
+    /// `F`
+    Font,
+    /// `HF`
+    BigFont,
+    /// `B`
+    Bcd,
+    /// `R`
+    Flags,
+    /// `LONG <expr>`
+    Long(Expr),
+    /// A quoted string, only valid in `db` and `include`.
+    Text(String),
+    Expr(Expr),
+}
+
+/// An arithmetic expression over numbers and symbols.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr
+{
+    Number(i64),
+    Symbol(String),
+    /// `$`, the address of the current statement.
+    Here,
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+}
+
+impl Expr
+{
+    /// Evaluates the expression. `here` is the address of the statement it appears in.
+    pub fn evaluate(&self, symbols: &HashMap<String, i64>, here: usize) -> Result<i64, String>
+    {
+        match self
+        {
+            Expr::Number(value) => Ok(*value),
+            Expr::Symbol(name) => symbols.get(name).copied().ok_or_else(|| format!("undefined symbol {}", name)),
+            Expr::Here => Ok(here as i64),
+            Expr::Negate(inner) => Ok(inner.evaluate(symbols, here)?.wrapping_neg()),
+            Expr::Binary(lhs, op, rhs) =>
+            {
+                let (lhs, rhs) = (lhs.evaluate(symbols, here)?, rhs.evaluate(symbols, here)?);
+                match op
+                {
+                    '+' => Ok(lhs.wrapping_add(rhs)),
+                    '-' => Ok(lhs.wrapping_sub(rhs)),
+                    '*' => Ok(lhs.wrapping_mul(rhs)),
+                    '/' if rhs == 0 => Err("division by zero".to_string()),
+                    '/' => lhs.checked_div(rhs).ok_or_else(|| format!("{} / {} overflows", lhs, rhs)),
+                    '&' => Ok(lhs & rhs),
+                    '|' => Ok(lhs | rhs),
+                    _ => unreachable!("operator {} is not parsed", op),
+                }
+            },
+        }
+    }
+}
+
+/// Parses one line of source.
+pub fn parse_line(line: &str) -> Result<Statement, String>
+{
+    let mut rest = strip_comment(line).trim();
+    let mut statement = Statement::default();
+
+    // A label is an identifier followed by a colon at the start of the line.
+    if let Some(colon) = rest.find(':')
+    {
+        let name = rest[..colon].trim();
+        if is_identifier(name)
+        {
+            statement.label = Some(name.to_string());
+            rest = rest[colon + 1..].trim();
+        }
+    }
+    if rest.is_empty()
+    {
+        return Ok(statement);
+    }
+
+    let (mut word, mut tail) = split_word(rest);
+
+    // `NAME equ value`
+    let (second, second_tail) = split_word(tail);
+    if second.eq_ignore_ascii_case("equ")
+    {
+        if !is_identifier(word)
+        {
+            return Err(format!("invalid constant name {:?}", word));
+        }
+        statement.constant = Some(word.to_string());
+        word = second;
+        tail = second_tail;
+    }
+
+    statement.mnemonic = Some(word.to_ascii_lowercase());
+    statement.operands = split_operands(tail)?
+        .iter()
+        .map(|operand| parse_operand(operand))
+        .collect::<Result<_, _>>()?;
+    Ok(statement)
+}
+
+/// Removes everything after a `;` that is not inside a string or character literal.
+fn strip_comment(line: &str) -> &str
+{
+    let mut quote = None;
+    for (index, c) in line.char_indices()
+    {
+        match (quote, c)
+        {
+            (None, ';') => return &line[..index],
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            _ => {},
+        }
+    }
+    line
+}
+
+/// Splits off the first whitespace separated word.
+fn split_word(text: &str) -> (&str, &str)
+{
+    let text = text.trim_start();
+    match text.find(char::is_whitespace)
+    {
+        Some(end) => (&text[..end], text[end..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+/// Splits an operand list at the commas that are not inside a string.
+fn split_operands(text: &str) -> Result<Vec<String>, String>
+{
+    if text.trim().is_empty()
+    {
+        return Ok(Vec::new());
+    }
+    let mut operands = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for c in text.chars()
+    {
+        match (quote, c)
+        {
+            (None, ',') =>
+            {
+                operands.push(current.trim().to_string());
+                current.clear();
+                continue;
+            },
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            _ => {},
+        }
+        current.push(c);
+    }
+    if quote.is_some()
+    {
+        return Err("unterminated string".to_string());
+    }
+    operands.push(current.trim().to_string());
+    if operands.iter().any(|operand| operand.is_empty())
+    {
+        return Err("empty operand".to_string());
+    }
+    Ok(operands)
+}
+
+fn parse_operand(text: &str) -> Result<Operand, String>
+{
+    let upper = text.to_ascii_uppercase();
+    let operand = match upper.as_str()
+    {
+        "I" => Operand::Index,
+        "[I]" => Operand::IndexedMemory,
+        "DT" => Operand::DelayTimer,
+        "ST" => Operand::SoundTimer,
+        "K" => Operand::Key,
+        "F" => Operand::Font,
+        "HF" => Operand::BigFont,
+        "B" => Operand::Bcd,
+        "R" => Operand::Flags,
+        _ if upper.len() == 2 && upper.starts_with('V') && upper.as_bytes()[1].is_ascii_hexdigit() =>
+            Operand::Register(u8::from_str_radix(&upper[1..], 16).unwrap()),
+        _ if upper.starts_with("LONG ") => Operand::Long(parse_expr(&text[5..])?),
+        _ if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') =>
+            Operand::Text(text[1..text.len() - 1].to_string()),
+        _ => Operand::Expr(parse_expr(text)?),
+    };
+    Ok(operand)
+}
+
+fn is_identifier(text: &str) -> bool
+{
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Parses an expression: numbers, symbols and `$` combined with `+ - * / & |` and
+/// parentheses. `*` and `/` bind tighter than the other operators.
+pub fn parse_expr(text: &str) -> Result<Expr, String>
+{
+    let tokens = tokenize(text)?;
+    let mut position = 0;
+    let expr = parse_sum(&tokens, &mut position)?;
+    if position != tokens.len()
+    {
+        return Err(format!("unexpected {:?} in expression {:?}", tokens[position], text.trim()));
+    }
+    Ok(expr)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token
+{
+    Number(i64),
+    Symbol(String),
+    Here,
+    Operator(char),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String>
+{
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(c) = rest.chars().next()
+    {
+        let length = if let Some(number) = literal::read_number(rest)
+        {
+            let (value, length) = number?;
+            tokens.push(Token::Number(value));
+            length
+        }
+        else
+        {
+            let token = match c
+            {
+                '+' | '-' | '*' | '/' | '&' | '|' => Token::Operator(c),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                '$' => Token::Here,
+                _ if c.is_ascii_alphabetic() || c == '_' || c == '.' =>
+                {
+                    let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+                    Token::Symbol(rest[..end].to_string())
+                },
+                _ => return Err(format!("unexpected character {:?} in {:?}", c, text.trim())),
+            };
+            let length = if let Token::Symbol(name) = &token {name.len()} else {1};
+            tokens.push(token);
+            length
+        };
+        rest = rest[length..].trim_start();
+    }
+    if tokens.is_empty()
+    {
+        return Err("missing expression".to_string());
+    }
+    Ok(tokens)
+}
+
+fn parse_sum(tokens: &[Token], position: &mut usize) -> Result<Expr, String>
+{
+    let mut lhs = parse_product(tokens, position)?;
+    while let Some(Token::Operator(op @ ('+' | '-' | '&' | '|'))) = tokens.get(*position)
+    {
+        *position += 1;
+        let rhs = parse_product(tokens, position)?;
+        lhs = Expr::Binary(Box::new(lhs), *op, Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_product(tokens: &[Token], position: &mut usize) -> Result<Expr, String>
+{
+    let mut lhs = parse_term(tokens, position)?;
+    while let Some(Token::Operator(op @ ('*' | '/'))) = tokens.get(*position)
+    {
+        *position += 1;
+        let rhs = parse_term(tokens, position)?;
+        lhs = Expr::Binary(Box::new(lhs), *op, Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_term(tokens: &[Token], position: &mut usize) -> Result<Expr, String>
+{
+    let token = tokens.get(*position).ok_or("incomplete expression")?;
+    *position += 1;
+    match token
+    {
+        Token::Number(value) => Ok(Expr::Number(*value)),
+        Token::Symbol(name) => Ok(Expr::Symbol(name.clone())),
+        Token::Here => Ok(Expr::Here),
+        Token::Operator('-') => Ok(Expr::Negate(Box::new(parse_term(tokens, position)?))),
+        Token::Open =>
+        {
+            let inner = parse_sum(tokens, position)?;
+            if tokens.get(*position) != Some(&Token::Close)
+            {
+                return Err("missing )".to_string());
+            }
+            *position += 1;
+            Ok(inner)
+        },
+        _ => Err(format!("unexpected {:?} in expression", token)),
+    }
+}
@@ -0,0 +1,53 @@
+use std::io;
+
+use rodio::buffer::SamplesBuffer;
+use rodio::{OutputStream, OutputStreamHandle, Sink};
+
+use super::AudioSink;
+
+/// Frames of audio allowed to queue up before further samples are dropped. Keeps the
+/// latency bounded when the emulator runs faster than real time.
+const MAX_QUEUED_BUFFERS : usize = 8;
+
+/// Plays samples on the host's default output device.
+pub struct HostSink
+{
+    // The stream has to stay alive for as long as the sink plays.
+    _stream : OutputStream,
+    _handle : OutputStreamHandle,
+    sink : Sink,
+    sample_rate : u32,
+}
+
+impl HostSink
+{
+    pub fn new(sample_rate: u32) -> io::Result<HostSink>
+    {
+        let (stream, handle) = OutputStream::try_default().map_err(|error| io::Error::other(error.to_string()))?;
+        let sink = Sink::try_new(&handle).map_err(|error| io::Error::other(error.to_string()))?;
+        Ok(HostSink{
+            _stream: stream,
+            _handle: handle,
+            sink,
+            sample_rate,
+        })
+    }
+}
+
+impl AudioSink for HostSink
+{
+    fn write(&mut self, samples: &[f32]) -> io::Result<()>
+    {
+        if self.sink.len() < MAX_QUEUED_BUFFERS
+        {
+            self.sink.append(SamplesBuffer::new(1, self.sample_rate, samples.to_vec()));
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()>
+    {
+        self.sink.stop();
+        Ok(())
+    }
+}
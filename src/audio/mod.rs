@@ -0,0 +1,132 @@
+//! Sound output. A [`ToneGenerator`] turns the state of the sound timer into samples,
+//! which are handed to an [`AudioSink`] for playback or recording.
+
+#[cfg(feature = "host-audio")]
+mod host;
+mod wav;
+
+use std::io;
+
+use crate::chip::Chip;
+use crate::scheduler::TIMER_FREQUENCY;
+
+#[cfg(feature = "host-audio")]
+pub use host::HostSink;
+pub use wav::WavSink;
+
+/// Sample rate used when none is specified, in Hz.
+pub const DEFAULT_SAMPLE_RATE : u32 = 44_100;
+
+/// Highest supported sample rate, in Hz.
+pub const MAX_SAMPLE_RATE : u32 = 192_000;
+
+/// Receives mono samples in the range -1.0 to 1.0.
+pub trait AudioSink
+{
+    fn write(&mut self, samples: &[f32]) -> io::Result<()>;
+
+    /// Flushes anything still buffered. Called once when the emulator stops.
+    fn finish(&mut self) -> io::Result<()>
+    {
+        Ok(())
+    }
+}
+
+/// Discards all samples.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NullSink;
+
+impl AudioSink for NullSink
+{
+    fn write(&mut self, _samples: &[f32]) -> io::Result<()>
+    {
+        Ok(())
+    }
+}
+
+/// Generates a square wave for every frame in which the sound timer is active.
+/// If an XO-CHIP program loaded an audio pattern, the pattern is played instead at the
+/// rate given by its pitch register.
+#[derive(Debug, Clone)]
+pub struct ToneGenerator
+{
+    sample_rate : u32,
+    frequency : f32,
+    volume : f32,
+    /// Position within the current wave period, from 0.0 to 1.0.
+    phase : f32,
+    /// Position within the 128 bit audio pattern, from 0.0 to 128.0.
+    pattern_position : f32,
+    /// Samples owed from previous frames when the sample rate is not a multiple of 60.
+    sample_remainder : u32,
+}
+
+impl ToneGenerator
+{
+    /// Creates a generator for a 440 Hz tone at a quarter of full volume.
+    pub fn new(sample_rate: u32) -> ToneGenerator
+    {
+        ToneGenerator{
+            sample_rate,
+            frequency: 440.0,
+            volume: 0.25,
+            phase: 0.0,
+            pattern_position: 0.0,
+            sample_remainder: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32
+    {
+        self.sample_rate
+    }
+
+    pub fn set_frequency(&mut self, frequency: f32)
+    {
+        self.frequency = frequency;
+    }
+
+    /// Sets the amplitude of the wave, from 0.0 to 1.0.
+    pub fn set_volume(&mut self, volume: f32)
+    {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    /// Appends the samples for the 60 Hz frame `chip` just completed to `out`. The tone sounds
+    /// for the whole frame if the sound timer was running and the frame is silent otherwise.
+    pub fn generate_frame(&mut self, chip: &Chip, out: &mut Vec<f32>)
+    {
+        let total = self.sample_rate + self.sample_remainder;
+        let count = total / TIMER_FREQUENCY;
+        self.sample_remainder = total % TIMER_FREQUENCY;
+
+        if !chip.is_sound_active()
+        {
+            // Restart the wave from the beginning next time so every beep sounds the same.
+            self.phase = 0.0;
+            self.pattern_position = 0.0;
+            out.extend(std::iter::repeat_n(0.0, count as usize));
+            return;
+        }
+
+        if let Some(pattern) = chip.audio_pattern()
+        {
+            let step = chip.audio_playback_rate() / self.sample_rate as f32;
+            for _ in 0..count
+            {
+                let bit = self.pattern_position as usize;
+                let high = pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+                out.push(if high {self.volume} else {-self.volume});
+                self.pattern_position = (self.pattern_position + step) % 128.0;
+            }
+            return;
+        }
+
+        let step = self.frequency / self.sample_rate as f32;
+        for _ in 0..count
+        {
+            out.push(if self.phase < 0.5 {self.volume} else {-self.volume});
+            self.phase = (self.phase + step).fract();
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::{AudioSink, MAX_SAMPLE_RATE};
+
+/// Size of the RIFF and format headers that precede the samples.
+const HEADER_SIZE : u32 = 44;
+
+/// Records samples into a 16 bit mono PCM WAV file.
+///
+/// The sizes in the header are filled in by [`AudioSink::finish`], which also runs when the
+/// sink is dropped.
+pub struct WavSink<W: Write + Seek>
+{
+    out : W,
+    sample_rate : u32,
+    samples_written : u32,
+    finished : bool,
+}
+
+impl WavSink<BufWriter<File>>
+{
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32) -> io::Result<WavSink<BufWriter<File>>>
+    {
+        WavSink::new(BufWriter::new(File::create(path)?), sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavSink<W>
+{
+    /// Writes a provisional header to `out`. Fails if `sample_rate` is above
+    /// [`MAX_SAMPLE_RATE`].
+    pub fn new(out: W, sample_rate: u32) -> io::Result<WavSink<W>>
+    {
+        if sample_rate > MAX_SAMPLE_RATE
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("sample rate {} Hz is too high", sample_rate)));
+        }
+        let mut sink = WavSink{
+            out,
+            sample_rate,
+            samples_written: 0,
+            finished: false,
+        };
+        sink.write_header()?;
+        Ok(sink)
+    }
+
+    fn write_header(&mut self) -> io::Result<()>
+    {
+        let data_size = self.samples_written * 2;
+        let out = &mut self.out;
+        out.write_all(b"RIFF")?;
+        out.write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?; // format chunk size
+        out.write_all(&1u16.to_le_bytes())?; // PCM
+        out.write_all(&1u16.to_le_bytes())?; // channels
+        out.write_all(&self.sample_rate.to_le_bytes())?;
+        out.write_all(&(self.sample_rate * 2).to_le_bytes())?; // byte rate
+        out.write_all(&2u16.to_le_bytes())?; // block align
+        out.write_all(&16u16.to_le_bytes())?; // bits per sample
+        out.write_all(b"data")?;
+        out.write_all(&data_size.to_le_bytes())
+    }
+}
+
+impl<W: Write + Seek> AudioSink for WavSink<W>
+{
+    fn write(&mut self, samples: &[f32]) -> io::Result<()>
+    {
+        for sample in samples
+        {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.out.write_all(&value.to_le_bytes())?;
+        }
+        self.samples_written += samples.len() as u32;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()>
+    {
+        if self.finished
+        {
+            return Ok(());
+        }
+        self.out.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Drop for WavSink<W>
+{
+    fn drop(&mut self)
+    {
+        let _ = self.finish();
+    }
+}
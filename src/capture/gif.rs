@@ -0,0 +1,169 @@
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use ::gif::{Encoder, EncodingError, Frame, Repeat};
+
+use super::{Canvas, FrameSink};
+use crate::chip::Chip;
+use crate::palette::Palette;
+use crate::scheduler::TIMER_FREQUENCY;
+
+/// The shortest delay to give a GIF frame, in hundredths of a second. Many viewers play
+/// frames with shorter delays at a tenth of a second instead.
+const MIN_DELAY : u64 = 2;
+
+/// Encodes the display as a looping animated GIF with the four colors of a palette.
+///
+/// Frames that look like the one before only lengthen its delay. A frame that changed but
+/// would be shown for less than [`MIN_DELAY`] is replaced by the next one, so animations
+/// that flicker every frame keep their speed at the cost of some of their frames.
+///
+/// The last frame and the trailer are written by [`FrameSink::finish`], which also runs
+/// when the sink is dropped.
+pub struct GifSink<W: Write>
+{
+    encoder : Option<Encoder<W>>,
+    canvas : Canvas,
+    /// The size of the canvas, which the format stores in 16 bits.
+    width : u16,
+    height : u16,
+    /// The frame waiting for a different one, and for how many 60 Hz frames it stayed.
+    pending : Option<(Vec<u8>, u64)>,
+    /// Number of 60 Hz frames before the pending one.
+    frames_written : u64,
+}
+
+impl GifSink<BufWriter<File>>
+{
+    pub fn create<P: AsRef<Path>>(path: P, chip: &Chip, scale: usize, palette: Palette) -> io::Result<GifSink<BufWriter<File>>>
+    {
+        GifSink::new(BufWriter::new(File::create(path)?), chip, scale, palette)
+    }
+}
+
+impl<W: Write> GifSink<W>
+{
+    /// Starts a GIF sized for `chip`, with every pixel a `scale` by `scale` square. Fails if
+    /// the scaled display is wider or taller than a GIF can be.
+    pub fn new(out: W, chip: &Chip, scale: usize, palette: Palette) -> io::Result<GifSink<W>>
+    {
+        let canvas = Canvas::new(chip, scale);
+        let (width, height) = match (u16::try_from(canvas.width), u16::try_from(canvas.height))
+        {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("a GIF cannot be {}x{} pixels", canvas.width, canvas.height))),
+        };
+        let colors : Vec<u8> = (0..4)
+            .flat_map(|pixel| {
+                let color = palette.color(pixel);
+                [color.0, color.1, color.2]
+            })
+            .collect();
+        let mut encoder = Encoder::new(out, width, height, &colors).map_err(io_error)?;
+        encoder.set_repeat(Repeat::Infinite).map_err(io_error)?;
+        Ok(GifSink{
+            encoder: Some(encoder),
+            canvas,
+            width,
+            height,
+            pending: None,
+            frames_written: 0,
+        })
+    }
+
+    /// Finishes the GIF and returns the writer.
+    pub fn into_inner(mut self) -> io::Result<W>
+    {
+        self.write_pending()?;
+        self.encoder.take().unwrap().into_inner()
+    }
+
+    /// Writes the pending frame with the time it was shown for.
+    fn write_pending(&mut self) -> io::Result<()>
+    {
+        let (encoder, (pixels, count)) = match (self.encoder.as_mut(), self.pending.take())
+        {
+            (Some(encoder), Some(pending)) => (encoder, pending),
+            _ => return Ok(()),
+        };
+        let mut delay = delay(self.frames_written, count);
+        self.frames_written += count;
+
+        let mut frame = Frame{
+            width: self.width,
+            height: self.height,
+            buffer: pixels.into(),
+            ..Frame::default()
+        };
+        // Delays longer than the format allows are split over copies of the frame.
+        while delay > 0
+        {
+            let part = delay.min(u16::MAX as u64);
+            frame.delay = part as u16;
+            encoder.write_frame(&frame).map_err(io_error)?;
+            delay -= part;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> FrameSink for GifSink<W>
+{
+    fn frame(&mut self, chip: &Chip) -> io::Result<()>
+    {
+        let pixels = self.canvas.pixels(chip);
+        match &mut self.pending
+        {
+            Some((pending, count)) if *pending == pixels => *count += 1,
+            Some((pending, count)) if delay(self.frames_written, *count) < MIN_DELAY =>
+            {
+                *pending = pixels;
+                *count += 1;
+            },
+            _ =>
+            {
+                self.write_pending()?;
+                self.pending = Some((pixels, 1));
+            },
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()>
+    {
+        self.write_pending()?;
+        match self.encoder.take()
+        {
+            Some(encoder) => encoder.into_inner()?.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Drop for GifSink<W>
+{
+    fn drop(&mut self)
+    {
+        let _ = self.finish();
+    }
+}
+
+/// The delay in hundredths of a second of a frame shown from 60 Hz frame `start` for `count`
+/// frames. Rounding the start and end times instead of the length keeps the total exact.
+fn delay(start: u64, count: u64) -> u64
+{
+    let centiseconds = |frame: u64| (frame * 100 + TIMER_FREQUENCY as u64 / 2) / TIMER_FREQUENCY as u64;
+    centiseconds(start + count) - centiseconds(start)
+}
+
+fn io_error(error: EncodingError) -> io::Error
+{
+    match error
+    {
+        EncodingError::Io(error) => error,
+        EncodingError::Format(error) => io::Error::new(io::ErrorKind::InvalidInput, error),
+    }
+}
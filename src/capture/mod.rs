@@ -0,0 +1,83 @@
+//! Video capture. Every completed 60 Hz frame the display is handed to a [`FrameSink`],
+//! which encodes it as an animated GIF ([`GifSink`]) or streams it uncompressed for an
+//! external encoder ([`RawSink`], [`Y4mSink`]).
+//!
+//! A video has the same size throughout, so programs that can switch to the high resolution
+//! of SUPER-CHIP are captured at 128x64, with low resolution frames scaled up by 2 like on
+//! the HP 48. On top of that every pixel is a `scale` by `scale` square.
+//!
+//! ```
+//! use chip_8::capture::{FrameSink, Y4mSink};
+//! use chip_8::{Chip, Palette};
+//!
+//! let chip = Chip::new();
+//! let mut sink = Y4mSink::new(Vec::new(), &chip, 1, Palette::default()).unwrap();
+//! sink.frame(&chip).unwrap();
+//! sink.frame(&chip).unwrap();
+//! let video = sink.into_inner();
+//! assert!(video.starts_with(b"YUV4MPEG2 W64 H32 F60:1"));
+//! ```
+
+mod gif;
+mod raw;
+
+use std::io;
+
+use crate::chip::Chip;
+use crate::chip::{HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub use self::gif::GifSink;
+pub use raw::{RawSink, Y4mSink};
+
+/// Receives the display once per frame.
+pub trait FrameSink
+{
+    fn frame(&mut self, chip: &Chip) -> io::Result<()>;
+
+    /// Writes anything still buffered. Called once when the emulator stops.
+    fn finish(&mut self) -> io::Result<()>
+    {
+        Ok(())
+    }
+}
+
+/// The fixed size of a video and how the display is scaled onto it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Canvas
+{
+    width : usize,
+    height : usize,
+}
+
+impl Canvas
+{
+    fn new(chip: &Chip, scale: usize) -> Canvas
+    {
+        let scale = scale.max(1);
+        let (width, height) = if chip.variant().has_superchip()
+        {
+            (HIRES_SCREEN_WIDTH as usize, HIRES_SCREEN_HEIGHT as usize)
+        }
+        else
+        {
+            (SCREEN_WIDTH as usize, SCREEN_HEIGHT as usize)
+        };
+        Canvas{ width: width * scale, height: height * scale }
+    }
+
+    /// The pixels of the display scaled onto the canvas, row by row, with the values of
+    /// [`Chip::texture`]. A display that does not fit, after loading a state of another
+    /// variant, is stretched.
+    fn pixels(&self, chip: &Chip) -> Vec<u8>
+    {
+        let texture = chip.texture();
+        let (width, height) = (chip.screen_width(), chip.screen_height());
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height
+        {
+            let row = &texture[y * height / self.height * width..][..width];
+            pixels.extend((0..self.width).map(|x| row[x * width / self.width] & 0x3));
+        }
+        pixels
+    }
+}
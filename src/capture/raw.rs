@@ -0,0 +1,126 @@
+use std::io::{self, Write};
+
+use super::{Canvas, FrameSink};
+use crate::chip::Chip;
+use crate::palette::{Palette, Rgb};
+
+/// Streams the display as uncompressed 24 bit RGB frames, one after the other without any
+/// header, for instance into `ffmpeg -f rawvideo -pixel_format rgb24 -video_size WxH
+/// -framerate 60 -i -`. [`width`](RawSink::width) and [`height`](RawSink::height) give
+/// the size.
+pub struct RawSink<W: Write>
+{
+    out : W,
+    canvas : Canvas,
+    palette : Palette,
+}
+
+impl<W: Write> RawSink<W>
+{
+    /// Starts a stream sized for `chip`, with every pixel a `scale` by `scale` square.
+    pub fn new(out: W, chip: &Chip, scale: usize, palette: Palette) -> RawSink<W>
+    {
+        RawSink{
+            out,
+            canvas: Canvas::new(chip, scale),
+            palette,
+        }
+    }
+
+    pub fn width(&self) -> usize
+    {
+        self.canvas.width
+    }
+
+    pub fn height(&self) -> usize
+    {
+        self.canvas.height
+    }
+
+    pub fn into_inner(self) -> W
+    {
+        self.out
+    }
+}
+
+impl<W: Write> FrameSink for RawSink<W>
+{
+    fn frame(&mut self, chip: &Chip) -> io::Result<()>
+    {
+        let colors = (0..4).map(|pixel| self.palette.color(pixel)).collect::<Vec<Rgb>>();
+        let data : Vec<u8> = self.canvas.pixels(chip).iter()
+            .flat_map(|pixel| {
+                let Rgb(r, g, b) = colors[*pixel as usize];
+                [r, g, b]
+            })
+            .collect();
+        self.out.write_all(&data)
+    }
+
+    fn finish(&mut self) -> io::Result<()>
+    {
+        self.out.flush()
+    }
+}
+
+/// Streams the display as a YUV4MPEG2 video at 60 frames per second, with full resolution
+/// color (4:4:4). Most video tools read it directly.
+pub struct Y4mSink<W: Write>
+{
+    out : W,
+    canvas : Canvas,
+    /// The Y, Cb and Cr values of the four pixel values.
+    colors : [[u8; 3]; 4],
+}
+
+impl<W: Write> Y4mSink<W>
+{
+    /// Writes the header of a video sized for `chip`, with every pixel a `scale` by `scale`
+    /// square.
+    pub fn new(mut out: W, chip: &Chip, scale: usize, palette: Palette) -> io::Result<Y4mSink<W>>
+    {
+        let canvas = Canvas::new(chip, scale);
+        writeln!(out, "YUV4MPEG2 W{} H{} F60:1 Ip A1:1 C444", canvas.width, canvas.height)?;
+        let mut colors = [[0; 3]; 4];
+        for (pixel, color) in colors.iter_mut().enumerate()
+        {
+            *color = ycbcr(palette.color(pixel as u8));
+        }
+        Ok(Y4mSink{ out, canvas, colors })
+    }
+
+    pub fn into_inner(self) -> W
+    {
+        self.out
+    }
+}
+
+impl<W: Write> FrameSink for Y4mSink<W>
+{
+    fn frame(&mut self, chip: &Chip) -> io::Result<()>
+    {
+        let pixels = self.canvas.pixels(chip);
+        self.out.write_all(b"FRAME\n")?;
+        for plane in 0..3
+        {
+            let data : Vec<u8> = pixels.iter().map(|pixel| self.colors[*pixel as usize][plane]).collect();
+            self.out.write_all(&data)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()>
+    {
+        self.out.flush()
+    }
+}
+
+/// Converts a color to studio range Y'CbCr as in ITU-R BT.601.
+fn ycbcr(Rgb(r, g, b): Rgb) -> [u8; 3]
+{
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let cb = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let cr = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    [y as u8, cb as u8, cr as u8]
+}
@@ -0,0 +1,1453 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::Chip8Error;
+use crate::font::{BIG_FONT_SET, FONT_SET};
+use crate::instruction::Instruction;
+use crate::opcode::OppCodeData;
+use crate::profile::Profiler;
+use crate::quirks::Quirks;
+use crate::random::{Random, RandomKind};
+use crate::savestate::{StateReader, StateWriter};
+use crate::trace::Tracer;
+use crate::variant::Variant;
+
+/// Width of the display in pixels.
+pub const SCREEN_WIDTH :u8 = 64;
+/// Height of the display in pixels.
+pub const SCREEN_HEIGHT :u8 = 32;
+/// Number of pixels in the framebuffer returned by [`Chip::texture`].
+pub const TEXTURE_SIZE :usize = 32*64;
+/// Width of the SUPER-CHIP high resolution display in pixels.
+pub const HIRES_SCREEN_WIDTH :u8 = 128;
+/// Height of the SUPER-CHIP high resolution display in pixels.
+pub const HIRES_SCREEN_HEIGHT :u8 = 64;
+/// Number of pixels in the framebuffer in high resolution mode.
+pub const HIRES_TEXTURE_SIZE :usize = 64*128;
+/// The XO-CHIP pitch at which an audio pattern plays back at 4000 samples per second.
+pub const DEFAULT_PITCH :u8 = 64;
+/// Address of the 4x5 font in memory.
+pub const FONT_ADDRESS :u16 = 0x000;
+/// Address of the SUPER-CHIP 8x10 font in memory, directly after the small font.
+pub const BIG_FONT_ADDRESS :u16 = 0x050;
+/// Address at which ROMs are loaded and execution starts.
+pub const PROGRAM_START :u16 = 0x200;
+/// Size of main memory in bytes.
+pub const MEMORY_SIZE :usize = 4096;
+/// Size of main memory in bytes in XO-CHIP mode.
+pub const XO_MEMORY_SIZE :usize = 65536;
+
+/// The complete state of a CHIP-8 machine.
+pub struct Chip
+{
+    current_opcode : u16,
+    /// Main memory, [`MEMORY_SIZE`] or [`XO_MEMORY_SIZE`] bytes depending on the variant.
+    memory : Vec<u8>,
+    registers : [u8;16],
+    index_register : u16,
+    program_counter : u16,
+    /// Row-major pixels of the current resolution. Only the first `width * height` entries are used.
+    /// Each pixel holds one bit per bit-plane.
+    texture : [u8; HIRES_TEXTURE_SIZE],
+    /// The bit-planes affected by drawing, clearing and scrolling, selected with 0xFN01.
+    plane_mask : u8,
+    hires : bool,
+    delay_timer : u8,
+    sound_timer : u8,
+    /// Whether the sound timer was running during the last frame.
+    sound_active : bool,
+    stack : [u16;16],
+    stack_pointer : u16,
+    keys : [u8; 16],
+    /// Keys that went down since the last time 0xFX0A started waiting, one bit per key.
+    pressed_keys_edges : u16,
+    key_wait : KeyWait,
+    /// Set after a draw when the display wait quirk is on; execution stalls until the next frame.
+    waiting_for_vblank : bool,
+    quirks : Quirks,
+    variant : Variant,
+    /// Set by 0x00FD; a halted machine no longer executes instructions.
+    halted : bool,
+    /// The SUPER-CHIP RPL user flags of 0xFX75 and 0xFX85.
+    rpl_flags : [u8; 16],
+    /// The XO-CHIP audio pattern loaded by 0xF002, 128 one-bit samples.
+    audio_pattern : Option<[u8; 16]>,
+    /// The XO-CHIP playback pitch set by 0xFX3A.
+    pitch : u8,
+    /// The source of 0xCXNN's random numbers.
+    random : Random,
+    /// Memory read or written by the last instruction, for watchpoints.
+    memory_accesses : Vec<MemoryAccess>,
+    /// Logs executed instructions, if tracing.
+    tracer : Option<Tracer>,
+    /// Counts executed instructions, if profiling.
+    profiler : Option<Profiler>,
+    oppcode_data: OppCodeData,
+}
+
+/// Whether an instruction read or wrote memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind
+{
+    Read,
+    Write,
+}
+
+/// A range of memory an instruction read or wrote, see [`Chip::memory_accesses`].
+/// Fetching the instruction itself is not counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess
+{
+    pub kind : AccessKind,
+    pub start : u16,
+    pub length : usize,
+}
+
+impl MemoryAccess
+{
+    /// Whether the access touched any byte in `start..start + length`.
+    pub fn overlaps(&self, start: u16, length: usize) -> bool
+    {
+        let (start, end) = (start as usize, start as usize + length);
+        (self.start as usize) < end && start < self.start as usize + self.length
+    }
+}
+
+/// Progress of a 0xFX0A instruction. Like the original interpreter it completes only once
+/// a key has been pressed and released again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyWait
+{
+    Idle,
+    WaitingForPress,
+    WaitingForRelease(u8),
+}
+
+impl Default for Chip
+{
+    fn default() -> Chip
+    {
+        Chip::new()
+    }
+}
+
+impl Chip
+{
+    /// Creates a machine with cleared memory and registers, the font loaded at address 0
+    /// and the program counter pointing at [`PROGRAM_START`].
+    pub fn new() -> Chip
+    {
+        let mut chip = Chip{
+            current_opcode: 0,
+            memory: vec![0;MEMORY_SIZE],
+            registers: [0;16],
+            index_register: 0,
+            program_counter: PROGRAM_START,
+            texture: [0;HIRES_TEXTURE_SIZE],
+            hires: false,
+            plane_mask: 1,
+            delay_timer: 0,
+            sound_timer: 0,
+            sound_active: false,
+            stack: [0;16],
+            stack_pointer: 0,
+            keys : [0;16],
+            pressed_keys_edges: 0,
+            key_wait: KeyWait::Idle,
+            waiting_for_vblank: false,
+            quirks: Quirks::default(),
+            variant: Variant::default(),
+            halted: false,
+            rpl_flags: [0;16],
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            random: Random::from_entropy(RandomKind::default()),
+            memory_accesses: Vec::new(),
+            tracer: None,
+            profiler: None,
+            oppcode_data: OppCodeData::new(0x0000),
+        };
+        chip.load_font(&FONT_SET);
+        chip.load_big_font(&BIG_FONT_SET);
+        chip
+    }
+
+    /// Reads the ROM at `path` and loads it into memory at [`PROGRAM_START`].
+    pub fn load_rom<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Chip8Error>
+    {
+        let path : &Path = path.as_ref();
+        let io_error = |source| Chip8Error::RomIo{ path: path.to_path_buf(), source };
+
+        let mut file = File::open(path).map_err(io_error)?;
+        let mut buffer: Vec<u8> = Vec::new();
+        file.read_to_end(&mut buffer).map_err(io_error)?;
+
+        self.load_rom_bytes(&buffer)
+    }
+
+    /// Loads a ROM image that is already in memory at [`PROGRAM_START`].
+    /// Fails without touching memory if the ROM does not fit between 0x200 and the end of
+    /// memory, so select the variant first.
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error>
+    {
+        let start = PROGRAM_START as usize;
+        let max_size = self.memory.len() - start;
+        if rom.len() > max_size
+        {
+            return Err(Chip8Error::RomTooLarge{ size: rom.len(), max_size });
+        }
+
+        self.memory[start..start + rom.len()].copy_from_slice(rom);
+        Ok(())
+    }
+
+    /// Serializes the complete machine state, including variant and quirks, into a
+    /// versioned and checksummed save state. See [`savestate`](crate::savestate) for the format.
+    pub fn save_state(&self) -> Vec<u8>
+    {
+        let mut writer = StateWriter::new();
+        writer.chunk(b"CONF", |chunk| {
+            chunk.blob(self.variant.to_string().as_bytes());
+            chunk.u8(self.quirks.to_bits());
+        });
+        writer.chunk(b"CPU ", |chunk| {
+            chunk.bytes(&self.registers);
+            chunk.u16(self.index_register);
+            chunk.u16(self.program_counter);
+            chunk.u16(self.stack_pointer);
+            self.stack.iter().for_each(|address| chunk.u16(*address));
+            chunk.u8(self.delay_timer);
+            chunk.u8(self.sound_timer);
+            chunk.bool(self.sound_active);
+            chunk.u16(self.current_opcode);
+            chunk.bool(self.halted);
+            chunk.bool(self.waiting_for_vblank);
+            match self.key_wait
+            {
+                KeyWait::Idle => chunk.bytes(&[0, 0]),
+                KeyWait::WaitingForPress => chunk.bytes(&[1, 0]),
+                KeyWait::WaitingForRelease(key) => chunk.bytes(&[2, key]),
+            }
+        });
+        writer.chunk(b"MEM ", |chunk| chunk.blob(&self.memory));
+        writer.chunk(b"DISP", |chunk| {
+            chunk.bool(self.hires);
+            chunk.u8(self.plane_mask);
+            chunk.blob(&self.texture);
+        });
+        writer.chunk(b"KEYS", |chunk| {
+            chunk.bytes(&self.keys);
+            chunk.u16(self.pressed_keys_edges);
+        });
+        writer.chunk(b"XOCH", |chunk| {
+            chunk.bytes(&self.rpl_flags);
+            chunk.u8(self.pitch);
+            match &self.audio_pattern
+            {
+                Some(pattern) =>
+                {
+                    chunk.bool(true);
+                    chunk.bytes(pattern);
+                },
+                None => chunk.bool(false),
+            }
+        });
+        writer.chunk(b"RNG ", |chunk| {
+            chunk.u8(match self.random.kind()
+            {
+                RandomKind::Xorshift => 0,
+                RandomKind::CosmacVip => 1,
+            });
+            chunk.u64(self.random.state());
+        });
+        writer.finish()
+    }
+
+    /// Restores a state written by [`save_state`](Chip::save_state), replacing everything
+    /// including variant and quirks. States from newer versions load as long as they do not
+    /// require a newer reader. On error the machine is left unchanged. A [tracer](Chip::set_tracer)
+    /// and a [profiler](Chip::set_profiler) stay attached; the profiler attributes calls that
+    /// were in progress in the state to the top level.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), Chip8Error>
+    {
+        let mut chip = Chip::from_state(bytes).map_err(|reason| Chip8Error::InvalidSaveState{ reason })?;
+        chip.tracer = self.tracer.take();
+        chip.profiler = self.profiler.take();
+        if let Some(profiler) = &mut chip.profiler
+        {
+            profiler.reset_stack();
+        }
+        *self = chip;
+        Ok(())
+    }
+
+    fn from_state(bytes: &[u8]) -> Result<Chip, String>
+    {
+        let invalid = |reason: &str| reason.to_string();
+        let state = StateReader::parse(bytes)?;
+        let mut chip = Chip::new();
+
+        let mut conf = state.require(b"CONF")?;
+        let variant = std::str::from_utf8(conf.blob()?).ok().and_then(|name| name.parse().ok())
+            .ok_or_else(|| invalid("unknown variant"))?;
+        chip.set_variant(variant);
+        chip.quirks = Quirks::from_bits(conf.u8()?);
+
+        let mut cpu = state.require(b"CPU ")?;
+        cpu.fill(&mut chip.registers)?;
+        chip.index_register = cpu.u16()?;
+        chip.program_counter = cpu.u16()?;
+        chip.stack_pointer = cpu.u16()?;
+        if chip.stack_pointer as usize > chip.stack.len()
+        {
+            return Err(invalid("stack pointer out of range"));
+        }
+        for entry in chip.stack.iter_mut()
+        {
+            *entry = cpu.u16()?;
+        }
+        chip.delay_timer = cpu.u8()?;
+        chip.sound_timer = cpu.u8()?;
+        chip.sound_active = cpu.bool()?;
+        chip.current_opcode = cpu.u16()?;
+        chip.oppcode_data.init(chip.current_opcode);
+        chip.halted = cpu.bool()?;
+        chip.waiting_for_vblank = cpu.bool()?;
+        chip.key_wait = match (cpu.u8()?, cpu.u8()?)
+        {
+            (0, _) => KeyWait::Idle,
+            (1, _) => KeyWait::WaitingForPress,
+            (2, key) if key < 16 => KeyWait::WaitingForRelease(key),
+            _ => return Err(invalid("unknown key wait state")),
+        };
+
+        let memory = state.require(b"MEM ")?.blob()?;
+        if memory.len() != chip.memory.len()
+        {
+            return Err(invalid("memory size does not match the variant"));
+        }
+        chip.memory.copy_from_slice(memory);
+
+        let mut display = state.require(b"DISP")?;
+        chip.hires = display.bool()?;
+        chip.plane_mask = display.u8()?;
+        let texture = display.blob()?;
+        if texture.len() != chip.texture.len()
+        {
+            return Err(invalid("framebuffer size does not match"));
+        }
+        chip.texture.copy_from_slice(texture);
+
+        let mut keys = state.require(b"KEYS")?;
+        keys.fill(&mut chip.keys)?;
+        chip.pressed_keys_edges = keys.u16()?;
+
+        if let Some(mut xochip) = state.chunk(b"XOCH")
+        {
+            xochip.fill(&mut chip.rpl_flags)?;
+            chip.pitch = xochip.u8()?;
+            if xochip.bool()?
+            {
+                let mut pattern = [0; 16];
+                xochip.fill(&mut pattern)?;
+                chip.audio_pattern = Some(pattern);
+            }
+        }
+
+        if let Some(mut random) = state.chunk(b"RNG ")
+        {
+            let kind = match random.u8()?
+            {
+                0 => RandomKind::Xorshift,
+                1 => RandomKind::CosmacVip,
+                _ => return Err(invalid("unknown random number generator")),
+            };
+            chip.random = Random::from_state(kind, random.u64()?);
+        }
+        Ok(chip)
+    }
+
+    /// Fetches, decodes and executes the instruction at the program counter.
+    /// While the display wait quirk holds execution until the next frame, or after the program
+    /// exited with 0x00FD, this does nothing.
+    ///
+    /// The timers are not touched; they count down at 60 Hz independently of the instruction
+    /// rate through [`Chip::tick_timers`], usually called by a [`Scheduler`](crate::scheduler::Scheduler).
+    ///
+    /// If the instruction fails the error is returned and the program counter is left pointing
+    /// at the offending instruction.
+    pub fn emulate_cycle(&mut self) -> Result<(), Chip8Error>
+    {
+        if self.waiting_for_vblank || self.halted
+        {
+            return Ok(());
+        }
+
+        // Fetch opcode
+        let opcode_bytes = self.read_memory(self.program_counter, self.program_counter as usize, 2)?;
+        let opcode_lhs : u16 = (opcode_bytes[0] as u16) << 8;
+        let opcode_rhs : u16 = opcode_bytes[1] as u16;
+        self.current_opcode = opcode_lhs | opcode_rhs;
+        self.oppcode_data.init(self.current_opcode);
+        self.memory_accesses.clear();
+
+        // Advance program counter before executing so jumps and calls land on their target
+        let address = self.program_counter;
+        self.program_counter = address.wrapping_add(2);
+
+        // Decode and execute opcode
+        let before = self.tracer.is_some().then_some((self.registers, self.index_register));
+        if let Some(profiler) = &mut self.profiler
+        {
+            profiler.execute(address);
+        }
+        if let Err(error) = self.execute()
+        {
+            self.program_counter = address;
+            if let Some(profiler) = &mut self.profiler
+            {
+                profiler.retract(address);
+            }
+            return Err(error);
+        }
+
+        if let (Some(mut tracer), Some((registers, index_register))) = (self.tracer.take(), before)
+        {
+            tracer.record(self, address, &registers, index_register);
+            self.tracer = Some(tracer);
+        }
+        Ok(())
+    }
+
+    /// Counts the delay and sound timers down by one. Call this at 60 Hz.
+    /// The sound itself is produced by the frontend, see [`audio`](crate::audio).
+    pub fn tick_timers(&mut self)
+    {
+        self.waiting_for_vblank = false;
+        if self.delay_timer > 0
+        {
+            self.delay_timer -= 1;
+        }
+        self.sound_active = self.sound_timer > 0;
+        if self.sound_timer > 0
+        {
+            self.sound_timer -= 1;
+        }
+    }
+
+    /// Executes the instruction in `current_opcode`.
+    fn execute(&mut self) -> Result<(), Chip8Error>
+    {
+        let next = self.program_counter as usize;
+        let next_word = match self.memory.get(next..next + 2)
+        {
+            Some(bytes) => (bytes[0] as u16) << 8 | bytes[1] as u16,
+            None => 0,
+        };
+        let instruction = Instruction::decode(self.current_opcode, next_word, self.variant)
+            .ok_or_else(|| self.unknown_opcode())?;
+
+        match instruction
+        {
+            Instruction::ScrollDown{ .. } => self.scroll_down(),
+            Instruction::ScrollUp{ .. } => self.scroll_up(),
+            Instruction::ClearScreen => self.clear_screen(),
+            Instruction::Return => self.return_from_subroutine()?,
+            Instruction::ScrollRight => self.scroll_right(),
+            Instruction::ScrollLeft => self.scroll_left(),
+            Instruction::Exit => self.exit(),
+            Instruction::LowRes => self.set_hires(false),
+            Instruction::HighRes => self.set_hires(true),
+            Instruction::Jump{ .. } => self.jump_to_address(),
+            Instruction::Call{ .. } => self.call_subroutine()?,
+            Instruction::SkipIfEqual{ .. } => self.skip_if_x_equal(),
+            Instruction::SkipIfNotEqual{ .. } => self.skip_if_x_not_equal(),
+            Instruction::SkipIfRegistersEqual{ .. } => self.skip_if_x_y_equal(),
+            Instruction::SaveRange{ .. } => self.save_register_range()?,
+            Instruction::LoadRange{ .. } => self.load_register_range()?,
+            Instruction::Assign{ .. } => self.assign_nn(),
+            Instruction::AddImmediate{ .. } => self.add_nnn(),
+            Instruction::Copy{ .. } => self.assign(),
+            Instruction::Or{ .. } => self.or(),
+            Instruction::And{ .. } => self.and(),
+            Instruction::Xor{ .. } => self.xor(),
+            Instruction::Add{ .. } => self.add(),
+            Instruction::Subtract{ .. } => self.subtract_y_from_x(),
+            Instruction::ShiftRight{ .. } => self.shift_x_right(),
+            Instruction::SubtractReverse{ .. } => self.subtract_x_from_y(),
+            Instruction::ShiftLeft{ .. } => self.shift_x_left(),
+            Instruction::SkipIfRegistersNotEqual{ .. } => self.skip_if_x_y_not_equal(),
+            Instruction::SetIndex{ .. } => self.set_index_register(),
+            Instruction::JumpOffset{ .. } => self.jump_to_address_plus_register_0(),
+            Instruction::Random{ .. } => self.set_x_to_random_and(),
+            Instruction::Draw{ .. } => self.draw_sprite()?,
+            Instruction::SkipIfKey{ .. } => self.skip_if_key_is_pressed(),
+            Instruction::SkipIfNotKey{ .. } => self.skip_if_key_is_not_pressed(),
+            Instruction::LongIndex{ nnnn } => self.load_long_index(nnnn)?,
+            Instruction::SelectPlanes{ .. } => self.select_planes(),
+            Instruction::LoadAudio => self.load_audio_pattern()?,
+            Instruction::GetDelayTimer{ .. } => self.get_delay_timer(),
+            Instruction::WaitForKey{ .. } => self.wait_for_key_press(),
+            Instruction::SetDelayTimer{ .. } => self.set_delay_timer(),
+            Instruction::SetSoundTimer{ .. } => self.set_sound_timer(),
+            Instruction::AddToIndex{ .. } => self.add_to_index(),
+            Instruction::Font{ .. } => self.set_sprite_address(),
+            Instruction::BigFont{ .. } => self.set_big_sprite_address(),
+            Instruction::BinaryCodedDecimal{ .. } => self.binary_coded_decimal()?,
+            Instruction::Pitch{ .. } => self.set_pitch(),
+            Instruction::Store{ .. } => self.register_dump()?,
+            Instruction::Load{ .. } => self.register_load()?,
+            Instruction::SaveFlags{ .. } => self.save_flags(),
+            Instruction::LoadFlags{ .. } => self.load_flags(),
+        }
+        Ok(())
+    }
+
+    pub fn quirks(&self) -> Quirks
+    {
+        self.quirks
+    }
+
+    /// Selects the interpreter behaviors to emulate. Takes effect with the next instruction.
+    pub fn set_quirks(&mut self, quirks: Quirks)
+    {
+        self.quirks = quirks;
+    }
+
+    pub fn variant(&self) -> Variant
+    {
+        self.variant
+    }
+
+    /// Selects the instruction set. Memory grows or shrinks to the size the variant has,
+    /// and switching back to CHIP-8 also leaves high resolution mode.
+    pub fn set_variant(&mut self, variant: Variant)
+    {
+        self.variant = variant;
+        self.memory.resize(variant.memory_size(), 0);
+        if !variant.has_superchip() && self.hires
+        {
+            self.set_hires(false);
+        }
+        if !variant.has_xochip()
+        {
+            self.plane_mask = 1;
+            self.texture.iter_mut().for_each(|pixel| *pixel &= 1);
+        }
+    }
+
+    /// Returns whether the program stopped itself with 0x00FD.
+    pub fn is_halted(&self) -> bool
+    {
+        self.halted
+    }
+
+    /// Returns whether the SUPER-CHIP 128x64 high resolution mode is active.
+    pub fn is_hires(&self) -> bool
+    {
+        self.hires
+    }
+
+    /// Width of the display in the current resolution.
+    pub fn screen_width(&self) -> usize
+    {
+        if self.hires {HIRES_SCREEN_WIDTH as usize} else {SCREEN_WIDTH as usize}
+    }
+
+    /// Height of the display in the current resolution.
+    pub fn screen_height(&self) -> usize
+    {
+        if self.hires {HIRES_SCREEN_HEIGHT as usize} else {SCREEN_HEIGHT as usize}
+    }
+
+    /// The framebuffer, one byte per pixel in row-major order. A pixel is lit if its byte is non-zero.
+    /// In XO-CHIP mode bit 0 of a pixel belongs to the first and bit 1 to the second bit-plane.
+    /// Holds [`screen_width`](Self::screen_width) times [`screen_height`](Self::screen_height) pixels.
+    pub fn texture(&self) -> &[u8]
+    {
+        &self.texture[..self.screen_width() * self.screen_height()]
+    }
+
+    /// Starts logging every executed instruction to `tracer`, or stops with `None`.
+    /// See [`trace`](crate::trace).
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>)
+    {
+        self.tracer = tracer;
+    }
+
+    pub fn tracer(&self) -> Option<&Tracer>
+    {
+        self.tracer.as_ref()
+    }
+
+    /// Stops tracing and returns the tracer, so it can be [finished](Tracer::finish).
+    pub fn take_tracer(&mut self) -> Option<Tracer>
+    {
+        self.tracer.take()
+    }
+
+    /// Starts counting executed instructions with `profiler`, or stops with `None`.
+    /// See [`profile`](crate::profile).
+    pub fn set_profiler(&mut self, profiler: Option<Profiler>)
+    {
+        self.profiler = profiler;
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler>
+    {
+        self.profiler.as_ref()
+    }
+
+    /// Stops profiling and returns the profiler with its counts.
+    pub fn take_profiler(&mut self) -> Option<Profiler>
+    {
+        self.profiler.take()
+    }
+
+    /// Returns whether the pixel at `(x, y)` is lit.
+    pub fn pixel(&self, x: usize, y: usize) -> bool
+    {
+        self.texture[x + y * self.screen_width()] != 0
+    }
+
+    /// The bit-planes selected with 0xFN01.
+    pub fn plane_mask(&self) -> u8
+    {
+        self.plane_mask
+    }
+
+    /// The XO-CHIP audio pattern, if the program loaded one with 0xF002.
+    pub fn audio_pattern(&self) -> Option<&[u8; 16]>
+    {
+        self.audio_pattern.as_ref()
+    }
+
+    /// The XO-CHIP pitch register set by 0xFX3A.
+    pub fn pitch(&self) -> u8
+    {
+        self.pitch
+    }
+
+    /// The rate at which the audio pattern plays, in samples per second.
+    /// A pitch of [`DEFAULT_PITCH`] gives 4000 Hz; every 48 steps double or halve the rate.
+    pub fn audio_playback_rate(&self) -> f32
+    {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+
+    /// The generator behind 0xCXNN.
+    pub fn random(&self) -> &Random
+    {
+        &self.random
+    }
+
+    /// Replaces the generator behind 0xCXNN, e.g. with a seeded one for reproducible runs.
+    /// New machines start with a [`RandomKind::Xorshift`] generator seeded from the system.
+    pub fn set_random(&mut self, random: Random)
+    {
+        self.random = random;
+    }
+
+    /// The SUPER-CHIP RPL user flags.
+    pub fn rpl_flags(&self) -> &[u8]
+    {
+        &self.rpl_flags[..self.variant.rpl_flag_count()]
+    }
+
+    /// The general purpose registers V0-VF.
+    pub fn registers(&self) -> &[u8;16]
+    {
+        &self.registers
+    }
+
+    /// The index register (I).
+    pub fn index_register(&self) -> u16
+    {
+        self.index_register
+    }
+
+    /// The address of the next instruction to be executed.
+    pub fn program_counter(&self) -> u16
+    {
+        self.program_counter
+    }
+
+    /// The opcode executed by the most recent call to [`Chip::emulate_cycle`].
+    pub fn current_opcode(&self) -> u16
+    {
+        self.current_opcode
+    }
+
+    /// The return addresses currently on the call stack, oldest first.
+    pub fn stack(&self) -> &[u16]
+    {
+        &self.stack[..self.stack_pointer as usize]
+    }
+
+    /// The number of return addresses currently on the call stack.
+    pub fn stack_pointer(&self) -> u16
+    {
+        self.stack_pointer
+    }
+
+    /// The delay timer, counting down towards 0.
+    pub fn delay_timer(&self) -> u8
+    {
+        self.delay_timer
+    }
+
+    /// The sound timer. A tone plays while it is non-zero.
+    pub fn sound_timer(&self) -> u8
+    {
+        self.sound_timer
+    }
+
+    /// Whether the tone sounded during the frame ended by the last [`Chip::tick_timers`].
+    /// A sound timer set to N keeps this true for N frames.
+    pub fn is_sound_active(&self) -> bool
+    {
+        self.sound_active
+    }
+
+    /// All of main memory, including the fonts and the loaded ROM. This is 4 KiB, or 64 KiB
+    /// in XO-CHIP mode.
+    pub fn memory(&self) -> &[u8]
+    {
+        &self.memory
+    }
+
+    /// The state of the 16 keys of the hex keypad. A key is held down if its byte is non-zero.
+    pub fn keys(&self) -> &[u8;16]
+    {
+        &self.keys
+    }
+
+    /// Returns whether key `key` (0x0-0xF) of the hex keypad is held down.
+    pub fn is_key_pressed(&self, key: u8) -> bool
+    {
+        self.keys[(key & 0xF) as usize] != 0
+    }
+
+    /// Puts key `key` (0x0-0xF) of the hex keypad down. Pressing a key that is already
+    /// down has no effect.
+    pub fn press_key(&mut self, key: u8)
+    {
+        let key = key & 0xF;
+        if self.keys[key as usize] == 0
+        {
+            self.keys[key as usize] = 1;
+            self.pressed_keys_edges |= 1 << key;
+        }
+    }
+
+    /// Lets go of key `key` (0x0-0xF) of the hex keypad.
+    pub fn release_key(&mut self, key: u8)
+    {
+        self.keys[(key & 0xF) as usize] = 0;
+    }
+
+    /// Presses or releases key `key` (0x0-0xF) of the hex keypad.
+    pub fn set_key(&mut self, key: u8, pressed: bool)
+    {
+        if pressed
+        {
+            self.press_key(key);
+        }
+        else
+        {
+            self.release_key(key);
+        }
+    }
+
+    /// The keypad as a bit mask, bit `n` set if key `n` is down.
+    pub fn key_mask(&self) -> u16
+    {
+        (0..16).filter(|key| self.keys[*key] != 0).fold(0, |mask, key| mask | 1 << key)
+    }
+
+    /// Presses the keys whose bits are set in `mask` and releases all others, exactly as
+    /// the same calls of [`set_key`](Chip::set_key) would.
+    pub fn set_key_mask(&mut self, mask: u16)
+    {
+        for key in 0..16
+        {
+            self.set_key(key, mask & 1 << key != 0);
+        }
+    }
+
+    /// Returns whether the program is blocked in 0xFX0A waiting for a key.
+    pub fn is_waiting_for_key(&self) -> bool
+    {
+        self.key_wait != KeyWait::Idle
+    }
+
+    /// Returns whether the program is blocked until the next 60 Hz frame, see
+    /// [`Quirks::display_wait`].
+    pub fn is_waiting_for_vblank(&self) -> bool
+    {
+        self.waiting_for_vblank
+    }
+
+    /// The memory ranges the last executed instruction read or wrote: sprite data, the
+    /// registers stored or loaded at I and the digits of 0xFX33.
+    pub fn memory_accesses(&self) -> &[MemoryAccess]
+    {
+        &self.memory_accesses
+    }
+
+    fn log_access(&mut self, kind: AccessKind, start: usize, length: usize)
+    {
+        self.memory_accesses.push(MemoryAccess{ kind, start: start as u16, length });
+    }
+
+    fn load_font(&mut self, font_set: &[u8;80])
+    {
+        let start = FONT_ADDRESS as usize;
+        self.memory[start..start + 80].copy_from_slice(font_set);
+    }
+
+    fn load_big_font(&mut self, font_set: &[u8;160])
+    {
+        let start = BIG_FONT_ADDRESS as usize;
+        self.memory[start..start + 160].copy_from_slice(font_set);
+    }
+
+    /// The address of the instruction being executed. The program counter has already been
+    /// advanced past it at this point.
+    fn instruction_address(&self) -> u16
+    {
+        self.program_counter.wrapping_sub(2)
+    }
+
+    /// Advances the program counter past the next instruction. In XO-CHIP mode that may be
+    /// the four byte 0xF000 NNNN.
+    fn skip_next_instruction(&mut self)
+    {
+        let next = self.program_counter as usize;
+        let is_long = self.variant.has_xochip()
+            && self.memory.get(next) == Some(&0xF0) && self.memory.get(next + 1) == Some(&0x00);
+        self.program_counter = self.program_counter.wrapping_add(if is_long {4} else {2});
+    }
+
+    fn unknown_opcode(&self) -> Chip8Error
+    {
+        Chip8Error::UnknownOpcode{ opcode: self.current_opcode, address: self.instruction_address() }
+    }
+
+    /// Checks that `length` bytes starting at `start` lie inside main memory.
+    /// `address` is the location of the instruction performing the access.
+    fn check_memory_range(&self, address: u16, start: usize, length: usize) -> Result<(), Chip8Error>
+    {
+        let size = self.memory.len();
+        if start + length > size
+        {
+            return Err(Chip8Error::MemoryOutOfBounds{ address, target: start.max(size) });
+        }
+        Ok(())
+    }
+
+    fn read_memory(&self, address: u16, start: usize, length: usize) -> Result<&[u8], Chip8Error>
+    {
+        self.check_memory_range(address, start, length)?;
+        Ok(&self.memory[start..start + length])
+    }
+
+    // Opcode implementations
+
+    /// 0x00E0: Clears the screen. In XO-CHIP mode only the selected bit-planes are cleared.
+    fn clear_screen(&mut self)
+    {
+        let mask = self.plane_mask;
+        self.texture.iter_mut().for_each(|pixel| *pixel &= !mask);
+    }
+
+    /// Moves the selected bit-planes of the display by `dx` and `dy` pixels.
+    /// Pixels moved in from outside the screen are blank.
+    fn scroll(&mut self, dx: isize, dy: isize)
+    {
+        let width = self.screen_width() as isize;
+        let height = self.screen_height() as isize;
+        let mask = self.plane_mask;
+        let previous = self.texture;
+
+        for y in 0..height
+        {
+            for x in 0..width
+            {
+                let (source_x, source_y) = (x - dx, y - dy);
+                let moved = if (0..width).contains(&source_x) && (0..height).contains(&source_y)
+                {
+                    previous[(source_x + source_y * width) as usize] & mask
+                }
+                else
+                {
+                    0
+                };
+                let index = (x + y * width) as usize;
+                self.texture[index] = (previous[index] & !mask) | moved;
+            }
+        }
+    }
+
+    /// 0x00CN: Scrolls the display down by N pixels.
+    fn scroll_down(&mut self)
+    {
+        self.scroll(0, self.oppcode_data.n as isize);
+    }
+
+    /// 0x00DN: Scrolls the display up by N pixels.
+    fn scroll_up(&mut self)
+    {
+        self.scroll(0, -(self.oppcode_data.n as isize));
+    }
+
+    /// 0x00FB: Scrolls the display right by 4 pixels.
+    fn scroll_right(&mut self)
+    {
+        self.scroll(4, 0);
+    }
+
+    /// 0x00FC: Scrolls the display left by 4 pixels.
+    fn scroll_left(&mut self)
+    {
+        self.scroll(-4, 0);
+    }
+
+    /// 0x00FD: Exits the interpreter. The program counter stays on this instruction.
+    fn exit(&mut self)
+    {
+        self.halted = true;
+        self.program_counter = self.instruction_address();
+    }
+
+    /// 0x00FE and 0x00FF: Switches between low and high resolution. Clears all bit-planes.
+    fn set_hires(&mut self, hires: bool)
+    {
+        self.hires = hires;
+        self.texture = [0;HIRES_TEXTURE_SIZE];
+    }
+
+    /// 0x00EE: Returns from subroutine.
+    fn return_from_subroutine(&mut self) -> Result<(), Chip8Error>
+    {
+        if self.stack_pointer == 0
+        {
+            return Err(Chip8Error::StackUnderflow{ address: self.instruction_address() });
+        }
+
+        // stack pop
+        self.stack_pointer -= 1; 
+        self.program_counter = self.stack[self.stack_pointer as usize];
+        if let Some(profiler) = &mut self.profiler
+        {
+            profiler.leave();
+        }
+        Ok(())
+    }
+
+    /// 0x1NNN: Jumps to the given address.
+    fn jump_to_address(&mut self)
+    {
+        self.program_counter = self.oppcode_data.nnn;
+    }
+
+    /// 0x2NNN: Calls the given subroutine.
+    fn call_subroutine(&mut self) -> Result<(), Chip8Error>
+    {
+        if self.stack_pointer as usize == self.stack.len()
+        {
+            return Err(Chip8Error::StackOverflow{ address: self.instruction_address() });
+        }
+
+        // stack push
+        self.stack[self.stack_pointer as usize] = self.program_counter;
+        self.stack_pointer += 1;
+        
+        self.program_counter = self.oppcode_data.nnn;
+        if let Some(profiler) = &mut self.profiler
+        {
+            profiler.enter(self.program_counter);
+        }
+        Ok(())
+    }
+
+    /// 0x3XNN: Skips the next instruction if register x equals NN.
+    fn skip_if_x_equal(&mut self)
+    {
+        if self.registers[self.oppcode_data.x as usize] == self.oppcode_data.nn
+        {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// 0x4XNN: Skips the next instruction if register x does not equal NN.
+    fn skip_if_x_not_equal(&mut self)
+    {
+        if self.registers[self.oppcode_data.x as usize] != self.oppcode_data.nn
+        {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// 0x5XY0: Skips the next instruction if register x equals register y.
+    fn skip_if_x_y_equal(&mut self)
+    {
+        if self.registers[self.oppcode_data.x as usize] == self.registers[self.oppcode_data.y as usize]
+        {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// 0x6XNN: Assigns NN to register x.
+    fn assign_nn(&mut self)
+    {
+        self.registers[self.oppcode_data.x as usize] = self.oppcode_data.nn;
+    }
+
+    /// 0x7XNN: Adds NN to register x.
+    fn add_nnn(&mut self)
+    {
+        let x = self.oppcode_data.x as usize;
+        self.registers[x] = self.registers[x].wrapping_add(self.oppcode_data.nn);
+    }
+
+    /// 0x8XY0: Assigns register y's value to register x.
+    fn assign(&mut self)
+    {
+        self.registers[self.oppcode_data.x as usize] = self.registers[self.oppcode_data.y as usize];
+    }
+
+    /// 0x8XY1: ORs register x and register y. Stores the result in register x.
+    fn or(&mut self)
+    {
+        self.registers[self.oppcode_data.x as usize] |= self.registers[self.oppcode_data.y as usize];
+        self.reset_vf_if_quirky();
+    }
+    
+    /// 0x8XY2: ANDs register x and register y. Stores the result in register x.
+    fn and(&mut self)
+    {
+        self.registers[self.oppcode_data.x as usize] &= self.registers[self.oppcode_data.y as usize];
+        self.reset_vf_if_quirky();
+    }
+    
+    ///  0x8XY3: XORs register x and register y. Stores the result in register x.
+    fn xor(&mut self)
+    {
+        self.registers[self.oppcode_data.x as usize] ^= self.registers[self.oppcode_data.y as usize];
+        self.reset_vf_if_quirky();
+    }
+    
+    /// The logic instructions of the COSMAC VIP clobber register 0xF.
+    fn reset_vf_if_quirky(&mut self)
+    {
+        if self.quirks.vf_reset
+        {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    /// The value shifted by 0x8XY6 and 0x8XYE.
+    fn shift_operand(&self) -> u8
+    {
+        let source = if self.quirks.shift_uses_vy {self.oppcode_data.y} else {self.oppcode_data.x};
+        self.registers[source as usize]
+    }
+
+    /// 0x8XY4: Adds register y to register x. Stores the result in register x.    
+    /// Sets register 0xF to 1 if an overflow occurs, sets it to 0 otherwise.
+    fn add(&mut self)
+    {
+        let x_value = self.registers[self.oppcode_data.x as usize];
+        let y_value = self.registers[self.oppcode_data.y as usize];
+        let (result,overflow) = x_value.overflowing_add(y_value);
+
+        self.registers[self.oppcode_data.x as usize] = result;
+        self.registers[0xF] = if overflow {1} else {0};
+    }
+
+    /// 0x8XY5: Subtracts register y from register x. Stores the result in register x.
+    /// Sets register 0xF to 0 if a borrow occurs, sets it to 1 otherwise.
+    fn subtract_y_from_x(&mut self)
+    {
+        let x_value = self.registers[self.oppcode_data.x as usize];
+        let y_value = self.registers[self.oppcode_data.y as usize];
+        let (result,borrow) = x_value.overflowing_sub(y_value);
+        
+        self.registers[self.oppcode_data.x as usize] = result;
+        self.registers[0xF] = if borrow {0} else {1}
+    }
+    
+    /// 0x8XY6: Shifts register x one to the right. The eliminated bit is stored in register 0xF.
+    /// With the shift quirk, register y is shifted and the result stored in register x.
+    fn shift_x_right(&mut self)
+    {
+        let value = self.shift_operand();
+        let least_significant_bit : u8 = value & 0x0001;
+        
+        self.registers[self.oppcode_data.x as usize] = value >> 1;
+        self.registers[0xF] = least_significant_bit;
+    }
+    
+    /// 0x8XY7: Subtracts register x from register y. Stores the result in register x.
+    /// Sets register 0xF to 0 if a borrow occurs, sets it to 1 otherwise.
+    fn subtract_x_from_y(&mut self)
+    {
+        let x : u8 = self.registers[self.oppcode_data.x as usize];
+        let y : u8 = self.registers[self.oppcode_data.y as usize];
+
+        let (result, borrow) = y.overflowing_sub(x);
+
+        self.registers[self.oppcode_data.x as usize] = result;
+        self.registers[0xF] = if borrow {0} else {1};
+    }
+    
+    /// 0x8XYE: Shifts register x one to the left. The eliminated bit is stored in register 0xF.
+    /// With the shift quirk, register y is shifted and the result stored in register x.
+    fn shift_x_left(&mut self)
+    {
+        let value = self.shift_operand();
+        let most_significant_bit : u8 = (value & 0x0080) >> 7;
+        
+        self.registers[self.oppcode_data.x as usize] = value << 1;
+        self.registers[0xF] = most_significant_bit;
+    }
+
+    /// 0x9XY0: Skips the next instruction if register x does not equal register y.
+    fn skip_if_x_y_not_equal(&mut self)
+    {
+        if self.registers[self.oppcode_data.x as usize] != self.registers[self.oppcode_data.y as usize]
+        {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// 0xANNN: Sets the index register (I) to NNN.
+    fn set_index_register(&mut self)
+    {
+        self.index_register = self.oppcode_data.nnn;
+    }
+
+    /// 0xBNNN: Jumps the program counter to register 0 + NNN.
+    /// With the jump quirk this is 0xBXNN instead and jumps to register x + XNN.
+    fn jump_to_address_plus_register_0(&mut self)
+    {
+        let register = if self.quirks.jump_uses_vx {self.oppcode_data.x} else {0};
+        self.program_counter = self.oppcode_data.nnn + self.registers[register as usize] as u16;
+    }
+
+    /// 0xCXNN: Generates a random number [0,255] and ANDs it with NN. Stores the result in register x.
+    fn set_x_to_random_and(&mut self)
+    {
+        let nn :u8 = self.oppcode_data.nn;
+        let rn :u8 = self.random.next_byte(&self.memory);
+
+        self.registers[self.oppcode_data.x as usize] = nn & rn;
+    }
+
+    /// 0xDXYN: Draws the sprite at coordinates x and y.
+    /// The sprite is 8 pixels wide and N pixels tall. 
+    /// On SUPER-CHIP, 0xDXY0 draws a 16x16 sprite stored as two bytes per row.
+    /// The sprite is read from main memory at the address that the index register (I) is pointing to.
+    /// In XO-CHIP mode the sprite is drawn to every selected bit-plane, with the data for
+    /// each plane following the previous one in memory.
+    /// The drawn pixels are XORd with the screen content. Pixels that fall off the screen
+    /// wrap around to the opposite edge, or are dropped with the clipping quirk.
+    /// If any pixels are flipped from set to unset then the register 0xF is set to 1. 
+    /// Otherwise it is set to 0.
+    fn draw_sprite(&mut self) -> Result<(), Chip8Error>
+    {
+        let width = self.screen_width();
+        let height = self.screen_height();
+        let x : usize = self.registers[self.oppcode_data.x as usize] as usize % width;
+        let y : usize = self.registers[self.oppcode_data.y as usize] as usize % height;
+
+        let (sprite_width, rows) = if self.oppcode_data.n == 0 && self.variant.has_superchip()
+        {
+            (16, 16)
+        }
+        else
+        {
+            (8, self.oppcode_data.n as usize)
+        };
+        let row_bytes = sprite_width / 8;
+        let sprite_size = rows * row_bytes;
+
+        let planes : Vec<u8> = [1u8, 2].iter().copied().filter(|plane| self.plane_mask & plane != 0).collect();
+        let sprite_memory = self.index_register as usize;
+        let sprites = self.read_memory(self.instruction_address(), sprite_memory, sprite_size * planes.len())?.to_vec();
+        self.log_access(AccessKind::Read, sprite_memory, sprites.len());
+
+        self.registers[0xF] = 0;
+
+        for (plane, sprite) in planes.iter().zip(sprites.chunks(sprite_size.max(1)))
+        {
+            for (y_line, row) in sprite.chunks(row_bytes).enumerate()
+            {
+                // Bits of the row, most significant pixel first.
+                let pixel : u16 = row.iter().fold(0, |bits, byte| (bits << 8) | *byte as u16);
+
+                for x_line in 0..sprite_width
+                {
+                    if (pixel & (1 << (sprite_width - 1 - x_line))) != 0
+                    {
+                        if self.quirks.clip_sprites && (x + x_line >= width || y + y_line >= height)
+                        {
+                            continue;
+                        }
+                        let screen_x = (x + x_line) % width;
+                        let screen_y = (y + y_line) % height;
+                        let index = screen_x + screen_y * width;
+
+                        if self.texture[index] & plane != 0
+                        {
+                            self.registers[0xF] = 1;
+                        }
+                        self.texture[index] ^= plane
+                    }
+                }
+            }
+        }
+
+        if self.quirks.display_wait
+        {
+            self.waiting_for_vblank = true;
+        }
+        Ok(())
+    }
+
+    /// 0xEX9E: Skips the next instruction if the key stored in register x is pressed.
+    fn skip_if_key_is_pressed(&mut self)
+    {
+        if self.is_key_pressed(self.registers[self.oppcode_data.x as usize])
+        {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// 0xEXA1: Skips the next instruction if the key stored in register x is not pressed.
+    fn skip_if_key_is_not_pressed(&mut self)
+    {
+        if !self.is_key_pressed(self.registers[self.oppcode_data.x as usize])
+        {
+            self.skip_next_instruction();
+        }
+    }
+
+    /// 0xFX07: Stores the delay timer to register x.
+    fn get_delay_timer(&mut self)
+    {
+        self.registers[self.oppcode_data.x as usize] = self.delay_timer;
+    }
+
+    /// 0xFX0A: Blocks execution untill a key is pressed and released again.
+    /// Only keys that go down after the instruction started waiting count.
+    /// Once the key is released, it will be stored in register x.
+    fn wait_for_key_press(&mut self)
+    {
+        self.key_wait = match self.key_wait
+        {
+            KeyWait::Idle =>
+            {
+                self.pressed_keys_edges = 0;
+                KeyWait::WaitingForPress
+            },
+            KeyWait::WaitingForPress if self.pressed_keys_edges != 0 =>
+            {
+                KeyWait::WaitingForRelease(self.pressed_keys_edges.trailing_zeros() as u8)
+            },
+            wait => wait,
+        };
+
+        if let KeyWait::WaitingForRelease(key) = self.key_wait
+        {
+            if !self.is_key_pressed(key)
+            {
+                self.registers[self.oppcode_data.x as usize] = key;
+                self.key_wait = KeyWait::Idle;
+                return;
+            }
+        }
+
+        self.program_counter = self.program_counter.wrapping_sub(2); // This means this command will be executed again next cycle.
+    }
+
+    /// 0xFX15: Sets the delay timer to register x.
+    fn set_delay_timer(&mut self)
+    {
+        self.delay_timer = self.registers[self.oppcode_data.x as usize];
+    }
+
+    /// 0xFX18: Sets the sound timer to register x.
+    fn set_sound_timer(&mut self)
+    {
+        self.sound_timer = self.registers[self.oppcode_data.x as usize];
+    }
+
+    /// 0xFX1E: Adds register x to the index register. Register 0xF is not affected.
+    fn add_to_index(&mut self)
+    {
+        let value = self.registers[self.oppcode_data.x as usize] as u16;
+        self.index_register = self.index_register.wrapping_add(value);
+    }
+
+    /// 0xFX29: Sets the index register (I) to the location of the sprite for the character in register x.
+    /// Characters 0x0-0xF are represented by a 4x5 font; only the low nibble of register x counts.
+    /// Each font sprite is 5 bytes in size.
+    fn set_sprite_address(&mut self)
+    {
+        let digit = self.registers[self.oppcode_data.x as usize] as u16 & 0xF;
+        self.index_register = FONT_ADDRESS + digit * 5;
+    }
+
+    /// 0xFX30: Sets the index register (I) to the location of the large sprite for the digit in register x.
+    /// Each large font sprite is 8x10 pixels and 10 bytes in size.
+    fn set_big_sprite_address(&mut self)
+    {
+        let digit = self.registers[self.oppcode_data.x as usize] as u16 & 0xF;
+        self.index_register = BIG_FONT_ADDRESS + digit * 10;
+    }
+
+    /// 0xFX33: Stores the decimal representation of register x and stores each character into
+    /// memory at the address that the index register is pointing to (with a maximum of 3). 
+    fn binary_coded_decimal(&mut self) -> Result<(), Chip8Error>
+    {
+        let base :usize = self.index_register as usize;
+        self.check_memory_range(self.instruction_address(), base, 3)?;
+        self.log_access(AccessKind::Write, base, 3);
+        self.memory[base] = (self.registers[self.oppcode_data.x as usize] / 100) %10;
+        self.memory[base + 1] = (self.registers[self.oppcode_data.x as usize] / 10) %10;
+        self.memory[base + 2] = self.registers[self.oppcode_data.x as usize] %10;
+        Ok(())
+    }
+
+    /// 0xFX55: Stores the content of register 0-X (x inclusive) at main memory, starting at
+    /// the addres at the index register (I). The load/store quirk advances I past the stored bytes.
+    fn register_dump(&mut self) -> Result<(), Chip8Error>
+    {
+        let base: usize = self.index_register as usize; 
+        self.check_memory_range(self.instruction_address(), base, self.oppcode_data.x as usize + 1)?;
+        self.log_access(AccessKind::Write, base, self.oppcode_data.x as usize + 1);
+        for i in 0..=self.oppcode_data.x as usize
+        {
+            self.memory[base + i] = self.registers[i];
+        }
+
+        if self.quirks.load_store_increments_i
+        {
+            self.index_register = self.index_register.wrapping_add(self.oppcode_data.x as u16 + 1);
+        }
+        Ok(())
+    }
+    
+    /// 0xFX65: Loads the memory pointed at by the index register (I) into the registers 0-X(x inclusive).
+    /// The load/store quirk advances I past the loaded bytes.
+    fn register_load(&mut self) -> Result<(), Chip8Error>
+    {
+        let base: usize = self.index_register as usize; 
+        self.check_memory_range(self.instruction_address(), base, self.oppcode_data.x as usize + 1)?;
+        self.log_access(AccessKind::Read, base, self.oppcode_data.x as usize + 1);
+        for i in 0..=self.oppcode_data.x as usize
+        {
+            self.registers[i] = self.memory[base + i];
+        }
+
+        if self.quirks.load_store_increments_i
+        {
+            self.index_register = self.index_register.wrapping_add(self.oppcode_data.x as u16 + 1);
+        }
+        Ok(())
+    }
+
+    /// 0xFX75: Saves registers 0-X (x inclusive) to the RPL user flags.
+    /// SUPER-CHIP only has 8 flags, so at most registers 0-7 are saved.
+    fn save_flags(&mut self)
+    {
+        let count = (self.oppcode_data.x as usize + 1).min(self.variant.rpl_flag_count());
+        self.rpl_flags[..count].copy_from_slice(&self.registers[..count]);
+    }
+
+    /// 0xFX85: Loads registers 0-X (x inclusive) from the RPL user flags.
+    fn load_flags(&mut self)
+    {
+        let count = (self.oppcode_data.x as usize + 1).min(self.variant.rpl_flag_count());
+        self.registers[..count].copy_from_slice(&self.rpl_flags[..count]);
+    }
+
+    /// 0x5XY2: Stores registers x through y at main memory, starting at the address in the
+    /// index register (I). Works in either direction; I is not changed.
+    fn save_register_range(&mut self) -> Result<(), Chip8Error>
+    {
+        let registers = self.register_range();
+        let base = self.index_register as usize;
+        self.check_memory_range(self.instruction_address(), base, registers.len())?;
+        self.log_access(AccessKind::Write, base, registers.len());
+        for (offset, register) in registers.iter().enumerate()
+        {
+            self.memory[base + offset] = self.registers[*register];
+        }
+        Ok(())
+    }
+
+    /// 0x5XY3: Loads registers x through y from main memory, starting at the address in the
+    /// index register (I). Works in either direction; I is not changed.
+    fn load_register_range(&mut self) -> Result<(), Chip8Error>
+    {
+        let registers = self.register_range();
+        let base = self.index_register as usize;
+        self.check_memory_range(self.instruction_address(), base, registers.len())?;
+        self.log_access(AccessKind::Read, base, registers.len());
+        for (offset, register) in registers.iter().enumerate()
+        {
+            self.registers[*register] = self.memory[base + offset];
+        }
+        Ok(())
+    }
+
+    /// The registers from x to y inclusive, in that order.
+    fn register_range(&self) -> Vec<usize>
+    {
+        let (x, y) = (self.oppcode_data.x as usize, self.oppcode_data.y as usize);
+        if x <= y
+        {
+            (x..=y).collect()
+        }
+        else
+        {
+            (y..=x).rev().collect()
+        }
+    }
+
+    /// 0xF000 NNNN: Loads the 16 bit address following the instruction into the index register (I).
+    fn load_long_index(&mut self, address: u16) -> Result<(), Chip8Error>
+    {
+        self.check_memory_range(self.instruction_address(), self.program_counter as usize, 2)?;
+        self.index_register = address;
+        self.program_counter = self.program_counter.wrapping_add(2);
+        Ok(())
+    }
+
+    /// 0xFN01: Selects the bit-planes N (1, 2 or both) for drawing, clearing and scrolling.
+    fn select_planes(&mut self)
+    {
+        self.plane_mask = self.oppcode_data.x & 0x3;
+    }
+
+    /// 0xF002: Loads the 16 bytes at the index register (I) into the audio pattern buffer.
+    fn load_audio_pattern(&mut self) -> Result<(), Chip8Error>
+    {
+        let mut pattern = [0u8; 16];
+        pattern.copy_from_slice(self.read_memory(self.instruction_address(), self.index_register as usize, 16)?);
+        self.audio_pattern = Some(pattern);
+        self.log_access(AccessKind::Read, self.index_register as usize, 16);
+        Ok(())
+    }
+
+    /// 0xFX3A: Sets the pitch of the audio pattern to register x.
+    fn set_pitch(&mut self)
+    {
+        self.pitch = self.registers[self.oppcode_data.x as usize];
+    }
+
+}
@@ -0,0 +1,70 @@
+use std::collections::{HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// Options that may be given more than once, each adding to the previous ones.
+const REPEATABLE : &[&str] = &["--quirk", "--break", "--press", "--keys"];
+
+/// A minimal command line parser that hands out arguments one at a time.
+///
+/// An option that takes a value may only be given once, unless it is [`REPEATABLE`].
+pub struct Args
+{
+    args : VecDeque<String>,
+    /// Options whose value was taken.
+    seen : HashSet<String>,
+}
+
+impl Args
+{
+    pub fn new(args: Vec<String>) -> Args
+    {
+        Args{ args: args.into(), seen: HashSet::new() }
+    }
+
+    pub fn next(&mut self) -> Option<String>
+    {
+        self.args.pop_front()
+    }
+
+    pub fn peek(&self) -> Option<&str>
+    {
+        self.args.front().map(|arg| arg.as_str())
+    }
+
+    /// Takes the value following `flag` and parses it.
+    pub fn value<T: FromStr>(&mut self, flag: &str) -> Result<T, String>
+        where T::Err: std::fmt::Display
+    {
+        let value = self.take(flag)?;
+        value.parse().map_err(|error| format!("invalid value for {}: {}", flag, error))
+    }
+
+    /// Takes the number following `flag`, given in decimal or as hex with a `0x` prefix.
+    pub fn number<T: TryFrom<u64>>(&mut self, flag: &str) -> Result<T, String>
+    {
+        let value = self.take(flag)?;
+        parse_number(&value).ok_or_else(|| format!("invalid value for {}: {}", flag, value))
+    }
+
+    /// Takes the raw value following `flag`.
+    fn take(&mut self, flag: &str) -> Result<String, String>
+    {
+        if !self.seen.insert(flag.to_string()) && !REPEATABLE.contains(&flag)
+        {
+            return Err(format!("{} is given more than once", flag));
+        }
+        self.next().ok_or_else(|| format!("{} expects a value", flag))
+    }
+}
+
+/// Parses a decimal or `0x` prefixed hex number.
+pub fn parse_number<T: TryFrom<u64>>(value: &str) -> Option<T>
+{
+    let number = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => value.parse().ok()?,
+    };
+    T::try_from(number).ok()
+}
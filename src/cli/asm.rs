@@ -0,0 +1,71 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use chip_8::asm::Assembler;
+use chip_8::Variant;
+
+use super::args::Args;
+
+/// Options of the `asm` subcommand.
+struct AsmOptions
+{
+    source : String,
+    output : Option<String>,
+    listing : Option<String>,
+    variant : Variant,
+}
+
+impl AsmOptions
+{
+    fn parse(mut args: Args) -> Result<AsmOptions, String>
+    {
+        let mut source = None;
+        let mut options = AsmOptions{
+            source: String::new(),
+            output: None,
+            listing: None,
+            variant: Variant::Chip8,
+        };
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "-o" | "--output" => options.output = Some(args.value(&arg)?),
+                "--listing" => options.listing = Some(args.value(&arg)?),
+                "--variant" => options.variant = args.value(&arg)?,
+                _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+                _ if source.is_none() => source = Some(arg),
+                _ => return Err(format!("unexpected argument: {}", arg)),
+            }
+        }
+
+        options.source = source.ok_or("no source file given")?;
+        Ok(options)
+    }
+}
+
+/// Assembles a source file into a ROM next to it, or wherever `-o` says.
+pub fn run(args: Args) -> Result<(), Box<dyn Error>>
+{
+    let options = AsmOptions::parse(args)?;
+    let assembly = Assembler::new(options.variant).assemble_file(&options.source)?;
+
+    let output = match &options.output
+    {
+        Some(output) => output.clone(),
+        None => Path::new(&options.source).with_extension("ch8").display().to_string(),
+    };
+    if output == options.source
+    {
+        return Err(format!("refusing to overwrite the source file {}", output).into());
+    }
+    fs::write(&output, &assembly.bytes)?;
+
+    if let Some(listing) = &options.listing
+    {
+        fs::write(listing, assembly.format_listing())?;
+    }
+    Ok(())
+}
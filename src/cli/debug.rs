@@ -0,0 +1,382 @@
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+use chip_8::debugger::{Condition, Debugger, StopReason, WatchKind, Watchpoint};
+use chip_8::{disasm, dump, AccessKind, Chip, Quirks, RandomKind, Variant};
+
+use super::args::{parse_number, Args};
+use super::run::random;
+
+const HELP : &str = "\
+commands:
+    s, step [n]             execute n instructions (default 1)
+    n, next                 execute one instruction, running calls to completion
+    f, finish               run until the current subroutine returns
+    c, continue             run until a breakpoint, the program exits or waits for a key
+    b, break <addr> [if <expr>]
+                            set a breakpoint, optionally only stopping when expr holds
+    b, break if <expr>      stop wherever the program is once expr holds
+    watch <addr> [n]        stop when the program writes any of n bytes (default 1)
+    rwatch <addr> [n]       stop when the program reads them
+    awatch <addr> [n]       stop when the program reads or writes them
+    d, delete [addr]        remove the breakpoint and watchpoints at addr, or everything
+    delete cond <n>         remove the condition with number n
+    breakpoints             list the breakpoints, conditions and watchpoints
+    p, print <expr>         evaluate an expression
+    r, registers            print V0-VF, I, PC, the stack and the timers
+    i, index                print the index register
+    stack                   print the call stack
+    timers                  print the delay and sound timers
+    m, memory <addr> [n]    dump n bytes of memory (default 64)
+    l, list [addr] [n]      disassemble n instructions (default: 10 from PC)
+    screen                  print the display
+    press <key>             press a keypad key
+    release <key>           release a keypad key
+    h, help                 show this message
+    q, quit                 leave the debugger
+An empty line repeats the previous command.
+
+Expressions use numbers, V0-VF, I, PC, SP, DT, ST and [addr] for a byte of memory,
+combined with the operators of C, e.g. `v3 == 10 && [i + 1] != 0`.";
+
+/// How many instructions `continue`, `next` and `finish` run before giving up, so a program
+/// stuck in a loop does not hang the debugger.
+const RUN_LIMIT : u64 = 10_000_000;
+
+/// Options of the `debug` subcommand.
+struct DebugOptions
+{
+    rom : String,
+    variant : Variant,
+    quirks : Option<Quirks>,
+    instructions_per_frame : u32,
+    breakpoints : Vec<u16>,
+    random_kind : RandomKind,
+    seed : Option<u64>,
+}
+
+impl DebugOptions
+{
+    fn parse(mut args: Args) -> Result<DebugOptions, String>
+    {
+        let mut rom = None;
+        let mut options = DebugOptions{
+            rom: String::new(),
+            variant: Variant::Chip8,
+            quirks: None,
+            instructions_per_frame: 12,
+            breakpoints: Vec::new(),
+            random_kind: RandomKind::default(),
+            seed: None,
+        };
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "--variant" => options.variant = args.value(&arg)?,
+                "--quirks" => options.quirks = Some(args.value(&arg)?),
+                "--ipf" => options.instructions_per_frame = args.number(&arg)?,
+                "--break" => options.breakpoints.push(args.number(&arg)?),
+                "--rng" => options.random_kind = args.value(&arg)?,
+                "--seed" => options.seed = Some(args.number(&arg)?),
+                _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+                _ if rom.is_none() => rom = Some(arg),
+                _ => return Err(format!("unexpected argument: {}", arg)),
+            }
+        }
+
+        options.rom = rom.ok_or("no rom given")?;
+        Ok(options)
+    }
+}
+
+/// Loads the ROM and reads debugger commands from standard input until `quit` or the end
+/// of the input.
+pub fn run(args: Args) -> Result<(), Box<dyn Error>>
+{
+    let options = DebugOptions::parse(args)?;
+
+    let mut chip = Chip::new();
+    chip.set_variant(options.variant);
+    chip.set_quirks(options.quirks.unwrap_or_else(|| Quirks::for_variant(options.variant)));
+    chip.set_random(random(options.random_kind, options.seed));
+    chip.load_rom(&options.rom)?;
+
+    let mut debugger = Debugger::new(chip, options.instructions_per_frame);
+    for address in options.breakpoints
+    {
+        debugger.add_breakpoint(address);
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut previous = String::new();
+
+    print_location(&debugger, &mut out)?;
+    loop
+    {
+        write!(out, "(chip8) ")?;
+        out.flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0
+        {
+            writeln!(out)?;
+            return Ok(());
+        }
+        let line = line.trim();
+        let command = if line.is_empty() {previous.clone()} else {line.to_string()};
+        if command.is_empty()
+        {
+            continue;
+        }
+        previous = command.clone();
+
+        match execute(&mut debugger, &command, &mut out)
+        {
+            Ok(true) => {},
+            Ok(false) => return Ok(()),
+            Err(error) => writeln!(out, "error: {}", error)?,
+        }
+    }
+}
+
+/// Runs one command. Returns false when the debugger should exit.
+fn execute(debugger: &mut Debugger, command: &str, out: &mut impl Write) -> Result<bool, Box<dyn Error>>
+{
+    let words : Vec<&str> = command.split_whitespace().collect();
+    let number = |index: usize| -> Result<Option<u64>, String>
+    {
+        words.get(index)
+            .map(|word| parse_number(word).ok_or_else(|| format!("invalid number: {}", word)))
+            .transpose()
+    };
+    let address = |index: usize| -> Result<Option<u16>, String>
+    {
+        words.get(index)
+            .map(|word| parse_number::<u16>(word).ok_or_else(|| format!("invalid address: {}", word)))
+            .transpose()
+    };
+
+    match words[0]
+    {
+        "s" | "step" =>
+        {
+            let count = number(1)?.unwrap_or(1);
+            let mut reason = StopReason::Stepped;
+            for _ in 0..count
+            {
+                reason = debugger.step()?;
+                if reason != StopReason::Stepped
+                {
+                    break;
+                }
+            }
+            report(debugger, reason, out)?;
+        },
+        "n" | "next" =>
+        {
+            let reason = debugger.step_over(RUN_LIMIT)?;
+            report(debugger, reason, out)?;
+        },
+        "f" | "finish" =>
+        {
+            let reason = debugger.run_until_return(RUN_LIMIT)?;
+            report(debugger, reason, out)?;
+        },
+        "c" | "continue" =>
+        {
+            let reason = debugger.run(RUN_LIMIT)?;
+            report(debugger, reason, out)?;
+        },
+        "b" | "break" =>
+        {
+            // Everything after the word `if` is the condition.
+            let rest = command.split_once(char::is_whitespace).map_or("", |(_, rest)| rest).trim();
+            let (address, condition) = match split_keyword(rest, "if")
+            {
+                Some((address, condition)) => (address, Some(condition.parse::<Condition>()?)),
+                None => (rest, None),
+            };
+
+            match (address, condition)
+            {
+                ("", None) => return Err("break expects an address or a condition".into()),
+                ("", Some(condition)) =>
+                {
+                    let index = debugger.add_condition(condition);
+                    writeln!(out, "condition {}", index)?;
+                },
+                (address, condition) =>
+                {
+                    let address = parse_number::<u16>(address).ok_or_else(|| format!("invalid address: {}", address))?;
+                    match condition
+                    {
+                        Some(condition) => debugger.add_conditional_breakpoint(address, condition),
+                        None => debugger.add_breakpoint(address),
+                    };
+                    writeln!(out, "breakpoint at {:04X}", address)?;
+                },
+            }
+        },
+        "watch" | "rwatch" | "awatch" =>
+        {
+            let start = address(1)?.ok_or("watch expects an address")?;
+            let length = number(2)?.unwrap_or(1) as usize;
+            let kind = match words[0]
+            {
+                "watch" => WatchKind::Write,
+                "rwatch" => WatchKind::Read,
+                _ => WatchKind::Access,
+            };
+            debugger.add_watchpoint(Watchpoint{ start, length, kind });
+            writeln!(out, "watchpoint at {:04X}", start)?;
+        },
+        "d" | "delete" if words.get(1) == Some(&"cond") =>
+        {
+            let index = number(2)?.ok_or("delete cond expects a condition number")? as usize;
+            if debugger.remove_condition(index).is_none()
+            {
+                writeln!(out, "no condition {}", index)?;
+            }
+        },
+        "d" | "delete" => match address(1)?
+        {
+            Some(address) =>
+            {
+                let breakpoint = debugger.remove_breakpoint(address);
+                let watchpoint = debugger.remove_watchpoint(address);
+                if !breakpoint && !watchpoint
+                {
+                    writeln!(out, "no breakpoint or watchpoint at {:04X}", address)?;
+                }
+            },
+            None => debugger.clear_breakpoints(),
+        },
+        "breakpoints" =>
+        {
+            for (address, condition) in debugger.breakpoints()
+            {
+                match condition
+                {
+                    Some(condition) => writeln!(out, "break {:04X} if {}", address, condition)?,
+                    None => writeln!(out, "break {:04X}", address)?,
+                }
+            }
+            for (index, condition) in debugger.conditions().iter().enumerate()
+            {
+                writeln!(out, "cond {}: {}", index, condition)?;
+            }
+            for watchpoint in debugger.watchpoints()
+            {
+                let kind = match watchpoint.kind
+                {
+                    WatchKind::Write => "watch",
+                    WatchKind::Read => "rwatch",
+                    WatchKind::Access => "awatch",
+                };
+                writeln!(out, "{} {:04X} {}", kind, watchpoint.start, watchpoint.length)?;
+            }
+        },
+        "p" | "print" =>
+        {
+            let expression = command.split_once(char::is_whitespace).map_or("", |(_, rest)| rest);
+            let value = expression.parse::<Condition>()?.evaluate(debugger.chip());
+            writeln!(out, "{} (0x{:X})", value, value)?;
+        },
+        "r" | "registers" => write!(out, "{}", dump::format_registers(debugger.chip()))?,
+        "i" | "index" => writeln!(out, "I={:04X}", debugger.chip().index_register())?,
+        "stack" =>
+        {
+            let stack : Vec<String> = debugger.chip().stack().iter().map(|address| format!("{:04X}", address)).collect();
+            writeln!(out, "SP={:X} stack=[{}]", debugger.chip().stack_pointer(), stack.join(" "))?;
+        },
+        "timers" => writeln!(out, "DT={:02X} ST={:02X}", debugger.chip().delay_timer(), debugger.chip().sound_timer())?,
+        "m" | "memory" =>
+        {
+            let start = address(1)?.ok_or("memory expects an address")? as usize;
+            let length = number(2)?.unwrap_or(64) as usize;
+            let memory = debugger.chip().memory();
+            let start = start.min(memory.len());
+            let end = start.saturating_add(length).min(memory.len());
+            write!(out, "{}", dump::format_memory(&memory[start..end], start))?;
+        },
+        "l" | "list" =>
+        {
+            let start = address(1)?.unwrap_or(debugger.chip().program_counter());
+            let count = number(2)?.unwrap_or(10) as usize;
+            list(debugger, start, count, out)?;
+        },
+        "screen" => write!(out, "{}", dump::format_screen(debugger.chip()))?,
+        "press" | "release" =>
+        {
+            let key = number(1)?.filter(|key| *key < 16).ok_or("expected a key from 0 to F")? as u8;
+            debugger.chip_mut().set_key(key, words[0] == "press");
+        },
+        "h" | "help" => writeln!(out, "{}", HELP)?,
+        "q" | "quit" => return Ok(false),
+        _ => writeln!(out, "unknown command {:?}, try help", words[0])?,
+    }
+    Ok(true)
+}
+
+/// Splits `text` around the first word that is exactly `keyword`, returning the trimmed
+/// text before it and the text after it.
+fn split_keyword<'a>(text: &'a str, keyword: &str) -> Option<(&'a str, &'a str)>
+{
+    let mut position = 0;
+    for word in text.split_whitespace()
+    {
+        let start = position + text[position..].find(word).unwrap();
+        position = start + word.len();
+        if word == keyword
+        {
+            return Some((text[..start].trim(), &text[position..]));
+        }
+    }
+    None
+}
+
+/// Prints why execution stopped and where.
+fn report(debugger: &Debugger, reason: StopReason, out: &mut impl Write) -> io::Result<()>
+{
+    match reason
+    {
+        StopReason::Stepped | StopReason::Returned => {},
+        StopReason::Breakpoint(address) => writeln!(out, "breakpoint at {:04X}", address)?,
+        StopReason::Condition(index) =>
+            writeln!(out, "condition {} holds: {}", index, debugger.conditions()[index])?,
+        StopReason::Watchpoint{ instruction, access } =>
+        {
+            let kind = if access.kind == AccessKind::Write {"wrote"} else {"read"};
+            writeln!(out, "watchpoint: the instruction at {:04X} {} {} byte(s) at {:04X}",
+                instruction, kind, access.length, access.start)?;
+        },
+        StopReason::Halted => writeln!(out, "the program exited")?,
+        StopReason::WaitingForKey => writeln!(out, "waiting for a key, use press and release")?,
+        StopReason::Limit => writeln!(out, "stopped after {} instructions", RUN_LIMIT)?,
+    }
+    print_location(debugger, out)
+}
+
+fn print_location(debugger: &Debugger, out: &mut impl Write) -> io::Result<()>
+{
+    list(debugger, debugger.chip().program_counter(), 1, out)
+}
+
+/// Disassembles `count` instructions from `start`, marking the program counter with `=>`
+/// and breakpoints with `*`.
+fn list(debugger: &Debugger, start: u16, count: usize, out: &mut impl Write) -> io::Result<()>
+{
+    let pc = debugger.chip().program_counter();
+    let lines = disasm::disassemble_memory(debugger.chip(), start, count * 4);
+    for line in lines.iter().take(count)
+    {
+        let marker = if line.address == pc {"=>"} else {"  "};
+        let breakpoint = if debugger.breakpoints().any(|(address, _)| address == line.address) {'*'} else {' '};
+        writeln!(out, "{}{} {}", marker, breakpoint, line)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::io::{self, Write};
+
+use chip_8::{disasm, Chip, Variant, PROGRAM_START};
+
+use super::args::Args;
+
+/// Options of the `disasm` subcommand.
+struct DisasmOptions
+{
+    rom : String,
+    variant : Variant,
+    start : u16,
+    length : Option<usize>,
+}
+
+impl DisasmOptions
+{
+    fn parse(mut args: Args) -> Result<DisasmOptions, String>
+    {
+        let mut rom = None;
+        let mut options = DisasmOptions{
+            rom: String::new(),
+            variant: Variant::Chip8,
+            start: PROGRAM_START,
+            length: None,
+        };
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "--variant" => options.variant = args.value(&arg)?,
+                "--start" => options.start = args.number(&arg)?,
+                "--length" => options.length = Some(args.number(&arg)?),
+                _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+                _ if rom.is_none() => rom = Some(arg),
+                _ => return Err(format!("unexpected argument: {}", arg)),
+            }
+        }
+
+        options.rom = rom.ok_or("no rom given")?;
+        Ok(options)
+    }
+}
+
+/// Loads the ROM into a fresh machine and prints a listing of the requested memory range,
+/// which defaults to the ROM itself.
+pub fn run(args: Args) -> Result<(), Box<dyn Error>>
+{
+    let options = DisasmOptions::parse(args)?;
+
+    let mut chip = Chip::new();
+    chip.set_variant(options.variant);
+    chip.load_rom(&options.rom)?;
+
+    let length = match options.length
+    {
+        Some(length) => length,
+        None =>
+        {
+            let rom_end = PROGRAM_START as usize + std::fs::metadata(&options.rom)?.len() as usize;
+            rom_end.saturating_sub(options.start as usize)
+        },
+    };
+    let lines = disasm::disassemble_memory(&chip, options.start, length);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let _ = out.write_all(disasm::format_listing(&lines).as_bytes());
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use std::io::{self, BufWriter, Stdout};
+use std::time::{Duration, Instant};
+
+use chip_8::keymap::Keymap;
+use chip_8::terminal::{Glyphs, HostKey, KeyHold, KeyboardReader, RawMode, TerminalRenderer};
+use chip_8::{Chip, Palette};
+
+/// What the emulation loop should do after the frontend handled its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control
+{
+    Continue,
+    Quit,
+    /// Save the machine to the current save slot.
+    SaveState,
+    /// Restore the machine from the current save slot.
+    LoadState,
+    /// Select the next save slot.
+    NextSlot,
+    /// Write a screenshot of the display.
+    Screenshot,
+}
+
+/// Shows the display in the terminal and feeds typed keys into the keypad.
+pub struct TerminalFrontend
+{
+    out : BufWriter<Stdout>,
+    renderer : TerminalRenderer,
+    // Input is optional so the display still works when stdin is not a terminal.
+    input : Option<(RawMode, KeyboardReader)>,
+    keymap : Keymap,
+    key_hold : KeyHold,
+    /// Until when Backspace counts as held down, like the keypad keys in `key_hold`.
+    rewind_until : Option<Instant>,
+    rewind_hold : Duration,
+}
+
+impl TerminalFrontend
+{
+    pub fn new(glyphs: Glyphs, palette: Palette, keymap: Keymap, key_hold: Duration) -> io::Result<TerminalFrontend>
+    {
+        let input = match RawMode::enable()
+        {
+            Ok(raw_mode) => Some((raw_mode, KeyboardReader::spawn())),
+            Err(error) =>
+            {
+                eprintln!("warning: keyboard input disabled: {}", error);
+                None
+            },
+        };
+
+        let mut frontend = TerminalFrontend{
+            out: BufWriter::new(io::stdout()),
+            renderer: TerminalRenderer::new(glyphs, palette),
+            input,
+            keymap,
+            key_hold: KeyHold::new(key_hold),
+            rewind_until: None,
+            rewind_hold: key_hold,
+        };
+        frontend.renderer.begin(&mut frontend.out)?;
+        Ok(frontend)
+    }
+
+    /// Applies the keys typed since the last call to `chip`. Escape and Ctrl-C quit;
+    /// Ctrl-S, Ctrl-L and Ctrl-N save, load and select save slots; Ctrl-P takes a screenshot;
+    /// Backspace rewinds.
+    pub fn handle_input(&mut self, chip: &mut Chip) -> Control
+    {
+        let now = Instant::now();
+        let keys = match &self.input
+        {
+            Some((_, reader)) => reader.poll(),
+            None => Vec::new(),
+        };
+
+        let mut control = Control::Continue;
+        for key in keys
+        {
+            control = match key
+            {
+                HostKey::Escape | HostKey::Ctrl('c') => Control::Quit,
+                HostKey::Ctrl('s') => Control::SaveState,
+                HostKey::Ctrl('l') => Control::LoadState,
+                HostKey::Ctrl('n') => Control::NextSlot,
+                HostKey::Ctrl('p') => Control::Screenshot,
+                HostKey::Backspace =>
+                {
+                    self.rewind_until = Some(now + self.rewind_hold);
+                    continue;
+                },
+                _ =>
+                {
+                    if let Some(keypad) = self.keymap.get(key)
+                    {
+                        self.key_hold.press(chip, keypad, now);
+                    }
+                    continue;
+                },
+            };
+            break;
+        }
+
+        // Releasing after pressing keeps a key that was typed again in time down, instead of
+        // letting it go and pressing it again within the same frame. That way every key
+        // changes at most once per call, which input movies rely on.
+        self.key_hold.update(chip, now);
+        control
+    }
+
+    /// Whether Backspace is held down, in which case the emulation runs backwards.
+    pub fn is_rewinding(&self) -> bool
+    {
+        self.rewind_until.is_some_and(|deadline| Instant::now() < deadline)
+    }
+
+    pub fn present(&mut self, chip: &Chip) -> io::Result<()>
+    {
+        self.renderer.render(chip, &mut self.out).map(|_| ())
+    }
+
+    /// Shows a message below the display.
+    pub fn status(&mut self, text: &str) -> io::Result<()>
+    {
+        self.renderer.status(text, &mut self.out)
+    }
+
+    /// Restores the terminal. Raw mode ends when the frontend is dropped.
+    pub fn finish(mut self) -> io::Result<()>
+    {
+        self.renderer.end(&mut self.out)
+    }
+}
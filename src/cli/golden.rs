@@ -0,0 +1,163 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fs;
+
+use chip_8::harness::{Harness, KeyScript, Screen};
+use chip_8::{Chip, Palette, Quirks, Random, RandomKind, Variant};
+
+use super::args::{parse_number, Args};
+
+/// Options of the `golden` subcommand.
+struct GoldenOptions
+{
+    rom : String,
+    reference : String,
+    frames : u64,
+    instructions_per_frame : u32,
+    variant : Variant,
+    quirks : Option<Quirks>,
+    random_kind : RandomKind,
+    seed : u64,
+    script : KeyScript,
+    palette : Palette,
+    update : bool,
+}
+
+impl GoldenOptions
+{
+    fn parse(mut args: Args) -> Result<GoldenOptions, String>
+    {
+        let mut positional = Vec::new();
+        let mut options = GoldenOptions{
+            rom: String::new(),
+            reference: String::new(),
+            frames: 60,
+            instructions_per_frame: 12,
+            variant: Variant::Chip8,
+            quirks: None,
+            random_kind: RandomKind::default(),
+            seed: 0,
+            script: KeyScript::new(),
+            palette: Palette::default(),
+            update: false,
+        };
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "--frames" => options.frames = args.number(&arg)?,
+                "--ipf" => options.instructions_per_frame = args.number(&arg)?,
+                "--variant" => options.variant = args.value(&arg)?,
+                "--quirks" => options.quirks = Some(args.value(&arg)?),
+                "--rng" => options.random_kind = args.value(&arg)?,
+                "--seed" => options.seed = args.number(&arg)?,
+                "--press" =>
+                {
+                    let press : String = args.value(&arg)?;
+                    let (frame, key, frames) = parse_press(&press)
+                        .ok_or_else(|| format!("invalid value for --press: {}, expected <frame>:<key>[:<frames>]", press))?;
+                    options.script.press(frame, key, frames);
+                },
+                "--keys" =>
+                {
+                    let path : String = args.value(&arg)?;
+                    let text = fs::read_to_string(&path).map_err(|error| format!("could not read {}: {}", path, error))?;
+                    let script : KeyScript = text.parse().map_err(|error| format!("{}: {}", path, error))?;
+                    for event in script.events()
+                    {
+                        options.script.push(*event);
+                    }
+                },
+                "--fg" => options.palette.foreground = args.value(&arg)?,
+                "--bg" => options.palette.background = args.value(&arg)?,
+                "--update" => options.update = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+                _ => positional.push(arg),
+            }
+        }
+
+        if options.instructions_per_frame == 0
+        {
+            return Err("the instruction rate must be at least 1 per frame".to_string());
+        }
+        match <[String; 2]>::try_from(positional)
+        {
+            Ok([rom, reference]) =>
+            {
+                options.rom = rom;
+                options.reference = reference;
+            },
+            Err(positional) if positional.len() < 2 => return Err("expected a rom and a reference screen".to_string()),
+            Err(positional) => return Err(format!("unexpected argument: {}", positional[2])),
+        }
+        Ok(options)
+    }
+}
+
+/// Parses `<frame>:<key>[:<frames>]`, where the key is a hex digit and the press lasts
+/// 5 frames unless given.
+fn parse_press(press: &str) -> Option<(u64, u8, u64)>
+{
+    let mut parts = press.split(':');
+    let frame = parse_number(parts.next()?)?;
+    let key = u8::from_str_radix(parts.next()?, 16).ok().filter(|key| *key < 16)?;
+    let frames = match parts.next()
+    {
+        Some(frames) => parse_number(frames)?,
+        None => 5,
+    };
+    if parts.next().is_some()
+    {
+        return None;
+    }
+    Some((frame, key, frames))
+}
+
+/// Runs a ROM headlessly for a number of frames or until it exits and compares the display
+/// with a reference screen, or writes the reference with `--update`.
+pub fn run(args: Args) -> Result<(), Box<dyn Error>>
+{
+    let options = GoldenOptions::parse(args)?;
+
+    let mut chip = Chip::new();
+    chip.set_variant(options.variant);
+    chip.set_quirks(options.quirks.unwrap_or_else(|| Quirks::for_variant(options.variant)));
+    chip.set_random(Random::new(options.random_kind, options.seed));
+    chip.load_rom(&options.rom)?;
+
+    let mut harness = Harness::new(chip, options.instructions_per_frame);
+    harness.set_script(options.script);
+    let outcome = harness.run(options.frames)?;
+    if outcome.halted
+    {
+        println!("{} exited after {} frames, {} instructions", options.rom, outcome.frames, outcome.cycles);
+    }
+    else
+    {
+        println!("ran {} for {} frames, {} instructions", options.rom, outcome.frames, outcome.cycles);
+    }
+
+    let screen = harness.screen();
+    if options.update
+    {
+        screen.save(&options.reference, &options.palette)?;
+        println!("wrote {}", options.reference);
+        return Ok(());
+    }
+
+    let expected = Screen::load(&options.reference, screen.width(), screen.height(), &options.palette)?;
+    match screen.diff(&expected)
+    {
+        None =>
+        {
+            println!("screen matches {}", options.reference);
+            Ok(())
+        },
+        Some(diff) =>
+        {
+            print!("{}", diff);
+            Err(format!("screen differs from {}", options.reference).into())
+        },
+    }
+}
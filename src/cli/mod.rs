@@ -0,0 +1,177 @@
+//! The `chip_8` command line frontend.
+
+mod args;
+mod asm;
+mod debug;
+mod disasm;
+mod frontend;
+mod golden;
+mod replay;
+mod run;
+mod trace;
+
+use std::error::Error;
+
+use args::Args;
+
+const USAGE : &str = "\
+usage: chip_8 [run] <rom> [options]
+
+commands:
+    run <rom>           run a ROM (the default when no command is given)
+    disasm <rom>        print a disassembly of a ROM
+    asm <source>        assemble a program into a ROM
+    debug <rom>         run a ROM under the interactive debugger, `help` lists its commands
+    golden <rom> <ref>  run a ROM headlessly and compare the display with a reference
+                        screen, as text like --dump-screen prints or as a PNG
+    replay <movie>      replay a movie recorded with --record-movie and check its end state
+    trace <file>        print a binary trace written by --trace-format binary as text
+    help                show this message
+
+run options:
+    --ipf <n>           instructions per 60 Hz frame (default 12)
+    --ips <n>           instructions per second, rounded to whole frames
+    --unthrottled       run frames back to back instead of at 60 Hz
+    --cycles <n>        stop after executing <n> instructions
+    --frames <n>        stop after <n> frames
+    --variant <name>    instruction set: chip8 (default), schip or xochip
+    --quirks <preset>   interpreter behavior: default, vip, chip48, schip or xochip;
+                        follows the variant unless given
+    --quirk <name>[=on|off]
+                        toggle a single quirk: shift-vy, load-store-i, jump-vx,
+                        vf-reset, clip or display-wait; may be repeated
+    --rng <name>        random number generator: xorshift (default) or vip
+    --seed <n>          seed the random number generator for reproducible runs
+    --terminal          show the display in the terminal and read keys from it;
+                        Esc quits, Ctrl-S saves to and Ctrl-L loads from the
+                        current save slot, Ctrl-N selects the next slot,
+                        Ctrl-P writes a screenshot to <rom>.<n>.png,
+                        holding Backspace rewinds
+    --braille           draw with braille characters instead of half blocks
+    --fg <RRGGBB>       color of lit pixels
+    --bg <RRGGBB>       color of unlit pixels
+    --keymap <preset>   keypad layout: qwerty (1234/QWER/ASDF/ZXCV, default),
+                        azerty or numpad
+    --keymap-file <f>   adjust the keymap with a config file, see `chip_8::keymap`
+    --key-hold <ms>     how long a typed key stays pressed (default 150)
+    --audio             play sound (needs the host-audio feature)
+    --wav <file>        record the sound to a WAV file
+    --tone <hz>         pitch of the tone (default 440)
+    --sample-rate <hz>  audio sample rate (default 44100)
+    --dump              dump screen, registers and memory on exit
+    --dump-screen       dump the framebuffer on exit
+    --dump-registers    dump the registers, stack and timers on exit
+    --dump-memory       dump main memory on exit
+    --screenshot <f>    write a screenshot on exit, as PNG, PBM or PGM by the extension
+    --scale <n>         size of a pixel in screenshots and videos (default 1)
+    --gif <file>        record the display as an animated GIF
+    --y4m <file>        stream the display as YUV4MPEG2 video, - for standard output
+    --raw-video <file>  stream the display as raw 24 bit RGB frames at 60 per second,
+                        - for standard output; frames are 64x32, or 128x64 for schip
+                        and xochip, times --scale
+    --trace <file>      log every executed instruction with the registers it changed
+    --trace-format <f>  text (default), one line per instruction, or binary
+    --trace-range <a>-<b>
+                        only log instructions at addresses from <a> to <b>
+    --trace-limit <n>   stop logging after <n> instructions
+    --profile <file>    write the most executed addresses and the instructions spent in
+                        each subroutine on exit, - for standard output
+    --profile-folded <file>
+                        write the call stacks on exit in the folded format of
+                        flamegraph tools, - for standard output
+    --profile-top <n>   number of addresses the profile lists (default 20)
+    --slot <n>          save slot the hotkeys start with, 0 to 9 (default 0);
+                        slot <n> is stored next to the ROM as <rom>.state<n>
+    --load-state <f|n>  start from a save state file or slot
+    --save-state <f|n>  write a save state to a file or slot on exit
+    --record-movie <f>  record the keypad into a movie file that `replay` reproduces;
+                        turns rewinding and loading states off
+    --rewind <mib>      memory for the rewind history in MiB, 0 turns it off
+                        (default 16)
+
+disasm options:
+    --variant <name>    instruction set to decode: chip8 (default), schip or xochip
+    --start <addr>      first address to disassemble (default 0x200)
+    --length <n>        number of bytes to disassemble (default up to the end of the ROM)
+
+asm options:
+    -o, --output <f>    where to write the ROM (default: the source with a .ch8 extension)
+    --listing <f>       also write a listing with addresses, bytes and symbols
+    --variant <name>    instruction set to accept: chip8 (default), schip or xochip
+
+debug options:
+    --variant <name>    instruction set: chip8 (default), schip or xochip
+    --quirks <preset>   interpreter behavior, follows the variant unless given
+    --ipf <n>           instructions per 60 Hz timer tick (default 12)
+    --break <addr>      set a breakpoint before starting, may be repeated
+    --rng <name>        random number generator: xorshift (default) or vip
+    --seed <n>          seed the random number generator
+
+golden options:
+    --frames <n>        frames to run unless the ROM exits first (default 60)
+    --ipf <n>           instructions per 60 Hz frame (default 12)
+    --variant <name>    instruction set: chip8 (default), schip or xochip
+    --quirks <preset>   interpreter behavior, follows the variant unless given
+    --rng <name>        random number generator: xorshift (default) or vip
+    --seed <n>          seed of the random number generator (default 0)
+    --press <f>:<k>[:<n>]
+                        press key <k> at frame <f> for <n> frames (default 5),
+                        may be repeated
+    --keys <file>       press keys as a script says, see `chip_8::harness::KeyScript`,
+                        may be repeated
+    --fg <RRGGBB>       color of lit pixels in a PNG reference
+    --bg <RRGGBB>       color of unlit pixels in a PNG reference
+    --update            write the reference from the result instead of comparing
+
+replay options:
+    --dump-screen       dump the framebuffer at the end
+    --dump-registers    dump the registers, stack and timers at the end";
+
+pub fn run(args: Vec<String>) -> Result<(), Box<dyn Error>>
+{
+    let mut args = Args::new(args);
+    match args.peek()
+    {
+        None | Some("help") | Some("-h") | Some("--help") =>
+        {
+            println!("{}", USAGE);
+            Ok(())
+        },
+        Some("asm") =>
+        {
+            args.next();
+            asm::run(args)
+        },
+        Some("debug") =>
+        {
+            args.next();
+            debug::run(args)
+        },
+        Some("disasm") =>
+        {
+            args.next();
+            disasm::run(args)
+        },
+        Some("golden") =>
+        {
+            args.next();
+            golden::run(args)
+        },
+        Some("replay") =>
+        {
+            args.next();
+            replay::run(args)
+        },
+        Some("trace") =>
+        {
+            args.next();
+            trace::run(args)
+        },
+        Some("run") =>
+        {
+            args.next();
+            run::run(args)
+        },
+        Some(_) => run::run(args),
+    }
+}
@@ -0,0 +1,80 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+
+use chip_8::dump;
+use chip_8::movie::Movie;
+
+use super::args::Args;
+
+/// Options of the `replay` subcommand.
+struct ReplayOptions
+{
+    movie : String,
+    dump_screen : bool,
+    dump_registers : bool,
+}
+
+impl ReplayOptions
+{
+    fn parse(mut args: Args) -> Result<ReplayOptions, String>
+    {
+        let mut movie = None;
+        let mut options = ReplayOptions{
+            movie: String::new(),
+            dump_screen: false,
+            dump_registers: false,
+        };
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "--dump-screen" => options.dump_screen = true,
+                "--dump-registers" => options.dump_registers = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+                _ if movie.is_none() => movie = Some(arg),
+                _ => return Err(format!("unexpected argument: {}", arg)),
+            }
+        }
+
+        options.movie = movie.ok_or("no movie given")?;
+        Ok(options)
+    }
+}
+
+/// Replays a movie recorded with `run --record-movie` as fast as possible and checks that
+/// it ends in the recorded state.
+pub fn run(args: Args) -> Result<(), Box<dyn Error>>
+{
+    let options = ReplayOptions::parse(args)?;
+
+    let bytes = fs::read(&options.movie).map_err(|error| format!("could not read {}: {}", options.movie, error))?;
+    let movie = Movie::from_bytes(&bytes).map_err(|error| format!("{}: {}", options.movie, error))?;
+    let replay = movie.replay()?;
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if options.dump_screen
+    {
+        out.write_all(dump::format_screen(&replay.chip).as_bytes())?;
+    }
+    if options.dump_registers
+    {
+        out.write_all(dump::format_registers(&replay.chip).as_bytes())?;
+    }
+    writeln!(out, "replayed {} frames, {} instructions", movie.frames.len(), replay.cycles)?;
+    if let Some(error) = &replay.error
+    {
+        writeln!(out, "ended with: {}", error)?;
+    }
+
+    if !replay.matches(&movie)
+    {
+        let recorded_end = movie.error.as_deref().unwrap_or("no error");
+        return Err(format!("replay diverged: recorded {} instructions, state hash {:08X} and {}",
+            movie.cycles, movie.final_hash, recorded_end).into());
+    }
+    writeln!(out, "final state matches the recording (hash {:08X})", replay.final_hash)?;
+    Ok(())
+}
@@ -0,0 +1,698 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use chip_8::audio::{AudioSink, ToneGenerator, WavSink, DEFAULT_SAMPLE_RATE, MAX_SAMPLE_RATE};
+use chip_8::capture::{FrameSink, GifSink, RawSink, Y4mSink};
+use chip_8::keymap::{Keymap, KeymapConfig, Preset};
+use chip_8::movie::Recorder;
+use chip_8::profile::Profiler;
+use chip_8::rewind::{self, Rewind};
+use chip_8::scheduler::{Scheduler, SystemClock, TIMER_FREQUENCY};
+use chip_8::screenshot::{self, ImageFormat};
+use chip_8::terminal::Glyphs;
+use chip_8::trace::{TraceFormat, Tracer};
+use chip_8::{dump, Chip, Chip8Error, Palette, Quirks, Random, RandomKind, Variant};
+
+use super::args::{parse_number, Args};
+use super::frontend::{Control, TerminalFrontend};
+
+struct RunOptions
+{
+    rom : String,
+    instructions_per_frame : u32,
+    unthrottled : bool,
+    cycle_limit : Option<u64>,
+    frame_limit : Option<u64>,
+    variant : Variant,
+    quirks : Quirks,
+    terminal : bool,
+    glyphs : Glyphs,
+    palette : Palette,
+    keymap_preset : Preset,
+    keymap_file : Option<String>,
+    key_hold : Duration,
+    host_audio : bool,
+    wav_file : Option<String>,
+    tone_frequency : f32,
+    sample_rate : u32,
+    dump_screen : bool,
+    dump_registers : bool,
+    dump_memory : bool,
+    random_kind : RandomKind,
+    /// Seed of the random number generator; a fresh one every run if not given.
+    seed : Option<u64>,
+    /// The save slot the terminal hotkeys start with.
+    slot : u32,
+    load_state : Option<PathBuf>,
+    save_state : Option<PathBuf>,
+    /// Memory budget of the rewind history in bytes, 0 turns rewinding off.
+    rewind_budget : usize,
+    record_movie : Option<String>,
+    /// Where to write a screenshot on exit.
+    screenshot : Option<String>,
+    /// Size of a pixel in screenshots and videos.
+    screenshot_scale : usize,
+    gif_file : Option<String>,
+    /// Where to stream YUV4MPEG2 video, `-` for standard output.
+    y4m_file : Option<String>,
+    /// Where to stream raw RGB video, `-` for standard output.
+    raw_video_file : Option<String>,
+    trace_file : Option<String>,
+    trace_format : TraceFormat,
+    trace_range : RangeInclusive<u16>,
+    trace_limit : Option<u64>,
+    profile_file : Option<String>,
+    folded_file : Option<String>,
+    profile_top : usize,
+}
+
+/// Number of save slots the terminal hotkeys cycle through.
+const SLOT_COUNT : u32 = 10;
+
+/// Largest pixel size of screenshots and videos; at 64 a SUPER-CHIP display is 8192 pixels wide.
+const MAX_SCALE : usize = 64;
+
+impl RunOptions
+{
+    fn parse(mut args: Args) -> Result<RunOptions, String>
+    {
+        let mut rom = None;
+        let mut quirks_preset : Option<Quirks> = None;
+        let mut quirk_settings : Vec<(String, bool)> = Vec::new();
+        let mut options = RunOptions{
+            rom: String::new(),
+            instructions_per_frame: 12,
+            unthrottled: false,
+            cycle_limit: None,
+            frame_limit: None,
+            variant: Variant::Chip8,
+            quirks: Quirks::default(),
+            terminal: false,
+            glyphs: Glyphs::HalfBlock,
+            palette: Palette::default(),
+            keymap_preset: Preset::Qwerty,
+            keymap_file: None,
+            key_hold: Duration::from_millis(150),
+            host_audio: false,
+            wav_file: None,
+            tone_frequency: 440.0,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            dump_screen: false,
+            dump_registers: false,
+            dump_memory: false,
+            random_kind: RandomKind::default(),
+            seed: None,
+            slot: 0,
+            load_state: None,
+            save_state: None,
+            rewind_budget: rewind::DEFAULT_BUDGET,
+            record_movie: None,
+            screenshot: None,
+            screenshot_scale: 1,
+            gif_file: None,
+            y4m_file: None,
+            raw_video_file: None,
+            trace_file: None,
+            trace_format: TraceFormat::Text,
+            trace_range: 0..=u16::MAX,
+            trace_limit: None,
+            profile_file: None,
+            folded_file: None,
+            profile_top: 20,
+        };
+        let mut load_state : Option<String> = None;
+        let mut save_state : Option<String> = None;
+
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "--ipf" => options.instructions_per_frame = args.number(&arg)?,
+                "--ips" =>
+                {
+                    let instructions_per_second : u32 = args.number(&arg)?;
+                    let rounded = instructions_per_second.checked_add(TIMER_FREQUENCY / 2)
+                        .ok_or_else(|| format!("invalid value for --ips: {}", instructions_per_second))?;
+                    options.instructions_per_frame = rounded / TIMER_FREQUENCY;
+                },
+                "--unthrottled" => options.unthrottled = true,
+                "--cycles" => options.cycle_limit = Some(args.number(&arg)?),
+                "--frames" => options.frame_limit = Some(args.number(&arg)?),
+                "--variant" => options.variant = args.value(&arg)?,
+                "--quirks" => quirks_preset = Some(args.value(&arg)?),
+                "--quirk" =>
+                {
+                    let setting : String = args.value(&arg)?;
+                    let (name, enabled) = match setting.split_once('=')
+                    {
+                        Some((name, "on")) => (name, true),
+                        Some((name, "off")) => (name, false),
+                        Some(_) => return Err(format!("invalid value for --quirk: {}, expected on or off", setting)),
+                        None => (setting.as_str(), true),
+                    };
+                    quirk_settings.push((name.to_string(), enabled));
+                },
+                "--terminal" => options.terminal = true,
+                "--braille" => options.glyphs = Glyphs::Braille,
+                "--fg" => options.palette.foreground = args.value(&arg)?,
+                "--bg" => options.palette.background = args.value(&arg)?,
+                "--keymap" => options.keymap_preset = args.value(&arg)?,
+                "--keymap-file" => options.keymap_file = Some(args.value(&arg)?),
+                "--key-hold" => options.key_hold = Duration::from_millis(args.number(&arg)?),
+                "--audio" => options.host_audio = true,
+                "--wav" => options.wav_file = Some(args.value(&arg)?),
+                "--tone" => options.tone_frequency = args.value(&arg)?,
+                "--sample-rate" => options.sample_rate = args.number(&arg)?,
+                "--dump" =>
+                {
+                    options.dump_screen = true;
+                    options.dump_registers = true;
+                    options.dump_memory = true;
+                },
+                "--dump-screen" => options.dump_screen = true,
+                "--dump-registers" => options.dump_registers = true,
+                "--dump-memory" => options.dump_memory = true,
+                "--rng" => options.random_kind = args.value(&arg)?,
+                "--seed" => options.seed = Some(args.number(&arg)?),
+                "--slot" => options.slot = args.number(&arg)?,
+                "--load-state" => load_state = Some(args.value(&arg)?),
+                "--save-state" => save_state = Some(args.value(&arg)?),
+                "--record-movie" => options.record_movie = Some(args.value(&arg)?),
+                "--screenshot" => options.screenshot = Some(args.value(&arg)?),
+                "--scale" => options.screenshot_scale = args.number(&arg)?,
+                "--gif" => options.gif_file = Some(args.value(&arg)?),
+                "--y4m" => options.y4m_file = Some(args.value(&arg)?),
+                "--raw-video" => options.raw_video_file = Some(args.value(&arg)?),
+                "--trace" => options.trace_file = Some(args.value(&arg)?),
+                "--trace-format" => options.trace_format = args.value(&arg)?,
+                "--trace-range" =>
+                {
+                    let range : String = args.value(&arg)?;
+                    options.trace_range = parse_range(&range)
+                        .ok_or_else(|| format!("invalid value for --trace-range: {}, expected <start>-<end>", range))?;
+                },
+                "--trace-limit" => options.trace_limit = Some(args.number(&arg)?),
+                "--profile" => options.profile_file = Some(args.value(&arg)?),
+                "--profile-folded" => options.folded_file = Some(args.value(&arg)?),
+                "--profile-top" => options.profile_top = args.number(&arg)?,
+                "--rewind" =>
+                {
+                    let mebibytes : usize = args.number(&arg)?;
+                    options.rewind_budget = mebibytes << 20;
+                },
+                _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+                _ if rom.is_none() => rom = Some(arg),
+                _ => return Err(format!("unexpected argument: {}", arg)),
+            }
+        }
+
+        if !(1000..=MAX_SAMPLE_RATE).contains(&options.sample_rate)
+        {
+            return Err(format!("the sample rate must be from 1000 to {} Hz", MAX_SAMPLE_RATE));
+        }
+        if options.instructions_per_frame == 0
+        {
+            return Err("the instruction rate must be at least 1 per frame".to_string());
+        }
+        // Without an explicit preset the quirks follow the selected variant.
+        options.quirks = quirks_preset.unwrap_or_else(|| Quirks::for_variant(options.variant));
+        for (name, enabled) in quirk_settings
+        {
+            options.quirks.set(&name, enabled)?;
+        }
+
+        if !(1..=MAX_SCALE).contains(&options.screenshot_scale)
+        {
+            return Err(format!("the scale must be from 1 to {}", MAX_SCALE));
+        }
+        if let Some(path) = &options.screenshot
+        {
+            ImageFormat::from_path(path).ok_or_else(|| format!("unknown image format of {}, expected .png, .pbm or .pgm", path))?;
+        }
+
+        let streams = [&options.y4m_file, &options.raw_video_file];
+        let to_stdout = streams.iter().filter(|file| file.as_deref() == Some("-")).count();
+        if to_stdout > 1
+        {
+            return Err("only one video can be streamed to standard output".to_string());
+        }
+        let profile_to_stdout = [&options.profile_file, &options.folded_file].iter().any(|file| file.as_deref() == Some("-"));
+        if to_stdout == 1 && (options.terminal || options.dump_screen || options.dump_registers || options.dump_memory || profile_to_stdout)
+        {
+            return Err("a video on standard output cannot be combined with --terminal, dumps or profiles".to_string());
+        }
+
+        if options.slot >= SLOT_COUNT
+        {
+            return Err(format!("the save slot must be between 0 and {}", SLOT_COUNT - 1));
+        }
+
+        options.rom = rom.ok_or("no rom given")?;
+        options.load_state = load_state.map(|state| state_path(&options.rom, &state));
+        options.save_state = save_state.map(|state| state_path(&options.rom, &state));
+        Ok(options)
+    }
+}
+
+/// Runs a ROM until a cycle or frame limit is reached, the program exits or faults, optionally showing
+/// the display in the terminal and reading the keypad from it.
+pub fn run(args: Args) -> Result<(), Box<dyn Error>>
+{
+    let options = RunOptions::parse(args)?;
+
+    let mut chip = Chip::new();
+    chip.set_variant(options.variant);
+    chip.set_quirks(options.quirks);
+    chip.set_random(random(options.random_kind, options.seed));
+    chip.load_rom(&options.rom)?;
+    if let Some(path) = &options.load_state
+    {
+        let state = fs::read(path).map_err(|error| format!("could not read {}: {}", path.display(), error))?;
+        chip.load_state(&state).map_err(|error| format!("{}: {}", path.display(), error))?;
+    }
+
+    if let Some(path) = &options.trace_file
+    {
+        let mut tracer = Tracer::create(path, options.trace_format)
+            .map_err(|error| format!("could not create {}: {}", path, error))?;
+        tracer.set_range(options.trace_range.clone());
+        tracer.set_limit(options.trace_limit);
+        chip.set_tracer(Some(tracer));
+    }
+    if options.profile_file.is_some() || options.folded_file.is_some()
+    {
+        chip.set_profiler(Some(Profiler::new()));
+    }
+
+    let mut frontend = if options.terminal
+    {
+        let keymap = load_keymap(&options)?;
+        Some(TerminalFrontend::new(options.glyphs, options.palette, keymap, options.key_hold)?)
+    }
+    else
+    {
+        None
+    };
+
+    let mut audio = AudioOutput::new(&options)?;
+    let mut video = VideoOutput::new(&options, &chip)?;
+
+    let result = execute(&mut chip, &options, &mut frontend, &mut audio, &mut video);
+    if let Some(frontend) = frontend
+    {
+        frontend.finish()?;
+    }
+    audio.finish()?;
+    video.finish()?;
+    if let (Some(mut tracer), Some(path)) = (chip.take_tracer(), &options.trace_file)
+    {
+        tracer.finish().map_err(|error| format!("could not write {}: {}", path, error))?;
+    }
+    if let Some(profiler) = chip.take_profiler()
+    {
+        if let Some(path) = &options.profile_file
+        {
+            write_output(path, &profiler.report(&chip, options.profile_top))?;
+        }
+        if let Some(path) = &options.folded_file
+        {
+            write_output(path, &profiler.folded())?;
+        }
+    }
+
+    if let Some(path) = &options.save_state
+    {
+        fs::write(path, chip.save_state()).map_err(|error| format!("could not write {}: {}", path.display(), error))?;
+    }
+    if let Some(path) = &options.screenshot
+    {
+        screenshot::save(&chip, path, options.screenshot_scale, &options.palette)
+            .map_err(|error| format!("could not write {}: {}", path, error))?;
+    }
+    print_dumps(&chip, &options);
+    result
+}
+
+/// Runs the emulation loop in 60 Hz frames. The frontend, if any, handles input before and
+/// presents the display after every batch of frames. Writes the movie when recording one.
+fn execute(chip: &mut Chip, options: &RunOptions, frontend: &mut Option<TerminalFrontend>,
+    audio: &mut AudioOutput, video: &mut VideoOutput) -> Result<(), Box<dyn Error>>
+{
+    let mut scheduler = Scheduler::new(SystemClock::new(), options.instructions_per_frame);
+    let mut recorder = options.record_movie.as_ref().map(|_| Recorder::new(chip, options.instructions_per_frame));
+    let result = run_frames(chip, &mut scheduler, options, frontend, audio, video, &mut recorder);
+
+    if let (Some(recorder), Some(path)) = (recorder, &options.record_movie)
+    {
+        let error = result.as_ref().err().and_then(|error| error.downcast_ref::<Chip8Error>());
+        let movie = recorder.finish(chip, scheduler.cycles(), error);
+        fs::write(path, movie.to_bytes()).map_err(|error| format!("could not write {}: {}", path, error))?;
+    }
+
+    if let Some(frontend) = frontend
+    {
+        frontend.present(chip)?;
+    }
+    result
+}
+
+fn run_frames(chip: &mut Chip, scheduler: &mut Scheduler<SystemClock>, options: &RunOptions,
+    frontend: &mut Option<TerminalFrontend>, audio: &mut AudioOutput, video: &mut VideoOutput,
+    recorder: &mut Option<Recorder>) -> Result<(), Box<dyn Error>>
+{
+    let mut slot = options.slot;
+    // Only the terminal can rewind, so without it there is no history to keep. Movies can
+    // only replay what happened going forward, so recording one turns rewinding off.
+    let keep_history = frontend.is_some() && options.rewind_budget > 0 && recorder.is_none();
+    let mut rewind = Rewind::new(options.rewind_budget);
+    loop
+    {
+        let due = if options.unthrottled {1} else {scheduler.due_frames()};
+        if due == 0
+        {
+            thread::sleep(scheduler.time_until_next_frame());
+            continue;
+        }
+
+        for _ in 0..due
+        {
+            let frame_limit_reached = options.frame_limit.is_some_and(|limit| scheduler.frames() >= limit);
+            let cycle_limit_reached = options.cycle_limit.is_some_and(|limit| scheduler.cycles() >= limit);
+            if frame_limit_reached || cycle_limit_reached
+            {
+                return Ok(());
+            }
+            let control = frontend.as_mut().map(|frontend| frontend.handle_input(chip));
+            // Headless runs are recorded too, with the keypad as it stands.
+            if let Some(recorder) = recorder
+            {
+                recorder.record_frame(chip);
+            }
+            if let (Some(frontend), Some(control)) = (frontend.as_mut(), control)
+            {
+                match control
+                {
+                    Control::Continue => {},
+                    Control::Quit => return Ok(()),
+                    Control::SaveState =>
+                    {
+                        let path = state_path(&options.rom, &slot.to_string());
+                        let status = match fs::write(&path, chip.save_state())
+                        {
+                            Ok(()) => format!("saved slot {}", slot),
+                            Err(error) => format!("could not write {}: {}", path.display(), error),
+                        };
+                        frontend.status(&status)?;
+                    },
+                    Control::LoadState if recorder.is_some() =>
+                        frontend.status("cannot load a state while recording a movie")?,
+                    Control::LoadState =>
+                    {
+                        let path = state_path(&options.rom, &slot.to_string());
+                        let loaded = fs::read(&path).map_err(|error| error.to_string())
+                            .and_then(|state| chip.load_state(&state).map_err(|error| error.to_string()));
+                        let status = match loaded
+                        {
+                            Ok(()) => format!("loaded slot {}", slot),
+                            Err(error) => format!("could not load slot {}: {}", slot, error),
+                        };
+                        frontend.status(&status)?;
+                    },
+                    Control::NextSlot =>
+                    {
+                        slot = (slot + 1) % SLOT_COUNT;
+                        frontend.status(&format!("slot {}", slot))?;
+                    },
+                    Control::Screenshot =>
+                    {
+                        let path = screenshot_path(&options.rom);
+                        let status = match screenshot::save(chip, &path, options.screenshot_scale, &options.palette)
+                        {
+                            Ok(()) => format!("wrote {}", path.display()),
+                            Err(error) => format!("could not write {}: {}", path.display(), error),
+                        };
+                        frontend.status(&status)?;
+                    },
+                }
+                if keep_history && frontend.is_rewinding()
+                {
+                    rewind.step_back(chip);
+                    scheduler.skip_frame();
+                    continue;
+                }
+            }
+
+            loop
+            {
+                if options.cycle_limit.is_some_and(|limit| scheduler.cycles() >= limit)
+                {
+                    return Ok(());
+                }
+                let frame_completed = scheduler.step(chip)?;
+                if chip.is_halted()
+                {
+                    return Ok(());
+                }
+                if frame_completed
+                {
+                    audio.frame(chip)?;
+                    video.frame(chip)?;
+                    if keep_history
+                    {
+                        rewind.capture(chip);
+                    }
+                    break;
+                }
+            }
+        }
+
+        if let Some(frontend) = frontend
+        {
+            frontend.present(chip)?;
+        }
+    }
+}
+
+/// Feeds the sound of every completed frame to the selected sinks.
+struct AudioOutput
+{
+    generator : ToneGenerator,
+    sinks : Vec<Box<dyn AudioSink>>,
+    samples : Vec<f32>,
+}
+
+impl AudioOutput
+{
+    fn new(options: &RunOptions) -> Result<AudioOutput, Box<dyn Error>>
+    {
+        let mut sinks : Vec<Box<dyn AudioSink>> = Vec::new();
+        if options.host_audio
+        {
+            sinks.push(host_sink(options.sample_rate)?);
+        }
+        if let Some(path) = &options.wav_file
+        {
+            let sink = WavSink::create(path, options.sample_rate)
+                .map_err(|error| format!("could not create {}: {}", path, error))?;
+            sinks.push(Box::new(sink));
+        }
+
+        let mut generator = ToneGenerator::new(options.sample_rate);
+        generator.set_frequency(options.tone_frequency);
+        Ok(AudioOutput{
+            generator,
+            sinks,
+            samples: Vec::new(),
+        })
+    }
+
+    fn frame(&mut self, chip: &Chip) -> io::Result<()>
+    {
+        if self.sinks.is_empty()
+        {
+            return Ok(());
+        }
+        self.samples.clear();
+        self.generator.generate_frame(chip, &mut self.samples);
+        for sink in &mut self.sinks
+        {
+            sink.write(&self.samples)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()>
+    {
+        for sink in &mut self.sinks
+        {
+            sink.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Feeds the display of every completed frame to the selected video sinks.
+struct VideoOutput
+{
+    sinks : Vec<Box<dyn FrameSink>>,
+}
+
+impl VideoOutput
+{
+    fn new(options: &RunOptions, chip: &Chip) -> Result<VideoOutput, Box<dyn Error>>
+    {
+        let (scale, palette) = (options.screenshot_scale, options.palette);
+        let mut sinks : Vec<Box<dyn FrameSink>> = Vec::new();
+        if let Some(path) = &options.gif_file
+        {
+            let sink = GifSink::create(path, chip, scale, palette)
+                .map_err(|error| format!("could not create {}: {}", path, error))?;
+            sinks.push(Box::new(sink));
+        }
+        if let Some(path) = &options.y4m_file
+        {
+            let sink = Y4mSink::new(output_writer(path)?, chip, scale, palette)
+                .map_err(|error| format!("could not write {}: {}", path, error))?;
+            sinks.push(Box::new(sink));
+        }
+        if let Some(path) = &options.raw_video_file
+        {
+            sinks.push(Box::new(RawSink::new(output_writer(path)?, chip, scale, palette)));
+        }
+        Ok(VideoOutput{ sinks })
+    }
+
+    fn frame(&mut self, chip: &Chip) -> io::Result<()>
+    {
+        for sink in &mut self.sinks
+        {
+            sink.frame(chip)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()>
+    {
+        for sink in &mut self.sinks
+        {
+            sink.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a report to the file at `path`, or to standard output for `-`.
+fn write_output(path: &str, text: &str) -> Result<(), String>
+{
+    let mut out = output_writer(path)?;
+    out.write_all(text.as_bytes()).and_then(|_| out.flush())
+        .map_err(|error| format!("could not write {}: {}", path, error))
+}
+
+/// Opens a file for a video stream or a report, or standard output for `-`.
+fn output_writer(path: &str) -> Result<BufWriter<Box<dyn Write>>, String>
+{
+    let out : Box<dyn Write> = if path == "-"
+    {
+        Box::new(io::stdout())
+    }
+    else
+    {
+        Box::new(File::create(path).map_err(|error| format!("could not create {}: {}", path, error))?)
+    };
+    Ok(BufWriter::new(out))
+}
+
+#[cfg(feature = "host-audio")]
+fn host_sink(sample_rate: u32) -> Result<Box<dyn AudioSink>, Box<dyn Error>>
+{
+    let sink = chip_8::audio::HostSink::new(sample_rate)
+        .map_err(|error| format!("could not open the audio device: {}", error))?;
+    Ok(Box::new(sink))
+}
+
+#[cfg(not(feature = "host-audio"))]
+fn host_sink(_sample_rate: u32) -> Result<Box<dyn AudioSink>, Box<dyn Error>>
+{
+    Err("this build has no audio playback, rebuild with `--features host-audio`".into())
+}
+
+/// The generator for 0xCXNN, seeded with `seed` if given.
+pub fn random(kind: RandomKind, seed: Option<u64>) -> Random
+{
+    match seed
+    {
+        Some(seed) => Random::new(kind, seed),
+        None => Random::from_entropy(kind),
+    }
+}
+
+/// Parses an address range written as `<start>-<end>`, both inclusive.
+fn parse_range(range: &str) -> Option<RangeInclusive<u16>>
+{
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (parse_number(start)?, parse_number(end)?);
+    if start > end
+    {
+        return None;
+    }
+    Some(start..=end)
+}
+
+/// The file of a save state: a plain number names that save slot of `rom`, which is kept
+/// next to the ROM as `<rom>.state<n>`, anything else is a path.
+fn state_path(rom: &str, state: &str) -> PathBuf
+{
+    match state.parse::<u32>()
+    {
+        Ok(slot) => PathBuf::from(format!("{}.state{}", rom, slot)),
+        Err(_) => PathBuf::from(state),
+    }
+}
+
+/// The first of `<rom>.<n>.png` that does not exist yet, for screenshots taken with the hotkey.
+fn screenshot_path(rom: &str) -> PathBuf
+{
+    (0..).map(|number| PathBuf::from(format!("{}.{}.png", rom, number)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+/// Builds the keymap from the selected preset, the config file and its section for the ROM.
+fn load_keymap(options: &RunOptions) -> Result<Keymap, Box<dyn Error>>
+{
+    let base = Keymap::preset(options.keymap_preset);
+    let path = match &options.keymap_file
+    {
+        Some(path) => path,
+        None => return Ok(base),
+    };
+
+    let text = fs::read_to_string(path).map_err(|error| format!("could not read keymap {}: {}", path, error))?;
+    let config : KeymapConfig = text.parse().map_err(|error| format!("{}: {}", path, error))?;
+    let rom_name = Path::new(&options.rom).file_name().and_then(|name| name.to_str());
+    Ok(config.keymap_for(base, rom_name))
+}
+
+fn print_dumps(chip: &Chip, options: &RunOptions)
+{
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if options.dump_screen
+    {
+        let _ = out.write_all(dump::format_screen(chip).as_bytes());
+    }
+    if options.dump_registers
+    {
+        let _ = out.write_all(dump::format_registers(chip).as_bytes());
+    }
+    if options.dump_memory
+    {
+        let _ = out.write_all(dump::format_memory(chip.memory(), 0).as_bytes());
+    }
+}
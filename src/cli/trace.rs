@@ -0,0 +1,36 @@
+use std::error::Error;
+use std::io::{self, Write};
+
+use chip_8::trace;
+
+use super::args::Args;
+
+/// Prints a binary trace in the text format, one line per instruction.
+pub fn run(mut args: Args) -> Result<(), Box<dyn Error>>
+{
+    let mut file = None;
+    while let Some(arg) = args.next()
+    {
+        match arg.as_str()
+        {
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg).into()),
+            _ if file.is_none() => file = Some(arg),
+            _ => return Err(format!("unexpected argument: {}", arg).into()),
+        }
+    }
+    let file = file.ok_or("no trace given")?;
+
+    let bytes = std::fs::read(&file).map_err(|error| format!("could not read {}: {}", file, error))?;
+    let records = trace::read_binary(&bytes).map_err(|error| format!("{}: {}", file, error))?;
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for record in records
+    {
+        if writeln!(out, "{}", record).is_err()
+        {
+            break;
+        }
+    }
+    Ok(())
+}
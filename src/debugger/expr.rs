@@ -0,0 +1,309 @@
+//! The expression language of breakpoint conditions.
+//!
+//! Expressions are evaluated against the machine state as 64 bit signed integers; a
+//! condition holds when its value is non-zero. They may use
+//!
+//! * numbers as in the [assembler](crate::asm): decimal, hex with a `0x`, `$` or `#`
+//!   prefix, binary with `0b`, or a character in single quotes,
+//! * the registers `V0` to `VF`, `I`, `PC`, `SP`, `DT` and `ST`,
+//! * `[addr]` for the byte of memory at `addr`, which is itself an expression, e.g. `[I + 2]`,
+//! * the operators of C with their usual precedence: `! - ~` (unary), `* / %`, `+ -`,
+//!   `<< >>`, `< <= > >=`, `== !=`, `&`, `^`, `|`, `&&` and `||`, and parentheses.
+//!
+//! Register names are case insensitive. For example `v3 == 10 && [0x300] > 5`.
+//!
+//! The assembler parses its own expressions: they are evaluated once against labels rather
+//! than against the machine on every instruction, and have neither comparisons nor the
+//! logical operators. Only the number syntax is shared.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chip::Chip;
+use crate::literal;
+
+/// A parsed expression that keeps the text it was parsed from for display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition
+{
+    source : String,
+    expr : Expr,
+}
+
+impl Condition
+{
+    /// Evaluates the expression. Memory outside of the machine reads as 0 and division by
+    /// zero gives 0, so evaluation never fails.
+    pub fn evaluate(&self, chip: &Chip) -> i64
+    {
+        self.expr.evaluate(chip)
+    }
+
+    /// Whether the condition holds, i.e. evaluates to something other than 0.
+    pub fn is_true(&self, chip: &Chip) -> bool
+    {
+        self.evaluate(chip) != 0
+    }
+}
+
+impl FromStr for Condition
+{
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Condition, String>
+    {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser{ tokens: &tokens, position: 0 };
+        let expr = parser.binary(0)?;
+        if let Some(token) = parser.tokens.get(parser.position)
+        {
+            return Err(format!("unexpected {} in {:?}", token, source.trim()));
+        }
+        Ok(Condition{ source: source.trim().to_string(), expr })
+    }
+}
+
+impl fmt::Display for Condition
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable
+{
+    Register(u8),
+    Index,
+    ProgramCounter,
+    StackPointer,
+    DelayTimer,
+    SoundTimer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr
+{
+    Number(i64),
+    Variable(Variable),
+    Memory(Box<Expr>),
+    Unary(&'static str, Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+}
+
+impl Expr
+{
+    fn evaluate(&self, chip: &Chip) -> i64
+    {
+        match self
+        {
+            Expr::Number(value) => *value,
+            Expr::Variable(variable) => match variable
+            {
+                Variable::Register(x) => chip.registers()[*x as usize] as i64,
+                Variable::Index => chip.index_register() as i64,
+                Variable::ProgramCounter => chip.program_counter() as i64,
+                Variable::StackPointer => chip.stack_pointer() as i64,
+                Variable::DelayTimer => chip.delay_timer() as i64,
+                Variable::SoundTimer => chip.sound_timer() as i64,
+            },
+            Expr::Memory(address) =>
+            {
+                let address = address.evaluate(chip);
+                usize::try_from(address).ok()
+                    .and_then(|address| chip.memory().get(address))
+                    .map_or(0, |byte| *byte as i64)
+            },
+            Expr::Unary(op, operand) =>
+            {
+                let value = operand.evaluate(chip);
+                match *op
+                {
+                    "-" => value.wrapping_neg(),
+                    "!" => (value == 0) as i64,
+                    _ => !value,
+                }
+            },
+            Expr::Binary(lhs, op, rhs) =>
+            {
+                let lhs = lhs.evaluate(chip);
+                // The logical operators short-circuit like in C.
+                match *op
+                {
+                    "&&" => return (lhs != 0 && rhs.evaluate(chip) != 0) as i64,
+                    "||" => return (lhs != 0 || rhs.evaluate(chip) != 0) as i64,
+                    _ => {},
+                }
+                let rhs = rhs.evaluate(chip);
+                match *op
+                {
+                    "*" => lhs.wrapping_mul(rhs),
+                    "/" => lhs.checked_div(rhs).unwrap_or(0),
+                    "%" => lhs.checked_rem(rhs).unwrap_or(0),
+                    "+" => lhs.wrapping_add(rhs),
+                    "-" => lhs.wrapping_sub(rhs),
+                    "<<" => lhs.wrapping_shl(rhs as u32),
+                    ">>" => lhs.wrapping_shr(rhs as u32),
+                    "<" => (lhs < rhs) as i64,
+                    "<=" => (lhs <= rhs) as i64,
+                    ">" => (lhs > rhs) as i64,
+                    ">=" => (lhs >= rhs) as i64,
+                    "==" => (lhs == rhs) as i64,
+                    "!=" => (lhs != rhs) as i64,
+                    "&" => lhs & rhs,
+                    "^" => lhs ^ rhs,
+                    _ => lhs | rhs,
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token
+{
+    Number(i64),
+    Name(String),
+    Operator(&'static str),
+}
+
+impl fmt::Display for Token
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Operator(op) => write!(f, "{:?}", op),
+        }
+    }
+}
+
+/// Operators by length so that `<=` is not read as `<` followed by `=`.
+const OPERATORS : [&str; 24] = [
+    "&&", "||", "<<", ">>", "<=", ">=", "==", "!=",
+    "(", ")", "[", "]", "!", "~", "*", "/", "%", "+", "-", "<", ">", "&", "^", "|",
+];
+
+/// Binary operators from the loosest to the tightest binding, one precedence level each.
+const PRECEDENCE : [&[&str]; 10] = [
+    &["||"], &["&&"], &["|"], &["^"], &["&"], &["==", "!="], &["<", "<=", ">", ">="],
+    &["<<", ">>"], &["+", "-"], &["*", "/", "%"],
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String>
+{
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next()
+    {
+        if let Some(number) = literal::read_number(rest)
+        {
+            let (value, length) = number?;
+            tokens.push(Token::Number(value));
+            rest = &rest[length..];
+        }
+        else if c.is_ascii_alphabetic() || c == '_'
+        {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op))
+        {
+            tokens.push(Token::Operator(op));
+            rest = &rest[op.len()..];
+        }
+        else
+        {
+            return Err(format!("unexpected character {:?}", c));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a>
+{
+    tokens : &'a [Token],
+    position : usize,
+}
+
+impl Parser<'_>
+{
+    /// Parses binary operators of precedence `level` and tighter.
+    fn binary(&mut self, level: usize) -> Result<Expr, String>
+    {
+        if level == PRECEDENCE.len()
+        {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(Token::Operator(op)) = self.tokens.get(self.position)
+        {
+            if !PRECEDENCE[level].contains(op)
+            {
+                break;
+            }
+            self.position += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String>
+    {
+        let token = self.tokens.get(self.position).ok_or("incomplete expression")?.clone();
+        self.position += 1;
+        match token
+        {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Name(name) => variable(&name).map(Expr::Variable),
+            Token::Operator(op @ ("-" | "!" | "~")) => Ok(Expr::Unary(op, Box::new(self.unary()?))),
+            Token::Operator("(") =>
+            {
+                let inner = self.binary(0)?;
+                self.expect(")")?;
+                Ok(inner)
+            },
+            Token::Operator("[") =>
+            {
+                let address = self.binary(0)?;
+                self.expect("]")?;
+                Ok(Expr::Memory(Box::new(address)))
+            },
+            token => Err(format!("unexpected {}", token)),
+        }
+    }
+
+    fn expect(&mut self, op: &'static str) -> Result<(), String>
+    {
+        if self.tokens.get(self.position) != Some(&Token::Operator(op))
+        {
+            return Err(format!("missing {}", op));
+        }
+        self.position += 1;
+        Ok(())
+    }
+}
+
+fn variable(name: &str) -> Result<Variable, String>
+{
+    let upper = name.to_ascii_uppercase();
+    let variable = match upper.as_str()
+    {
+        "I" => Variable::Index,
+        "PC" => Variable::ProgramCounter,
+        "SP" => Variable::StackPointer,
+        "DT" => Variable::DelayTimer,
+        "ST" => Variable::SoundTimer,
+        _ if upper.len() == 2 && upper.starts_with('V') && upper.as_bytes()[1].is_ascii_hexdigit() =>
+            Variable::Register(u8::from_str_radix(&upper[1..], 16).unwrap()),
+        _ => return Err(format!("unknown name {:?}", name)),
+    };
+    Ok(variable)
+}
@@ -0,0 +1,337 @@
+//! A step debugger around [`Chip::emulate_cycle`].
+//!
+//! A [`Debugger`] owns the machine and executes it one instruction at a time, ticking the
+//! timers every `instructions_per_frame` instructions just like the
+//! [`Scheduler`](crate::scheduler::Scheduler) does, but without waiting for the clock.
+//! Execution stops at PC breakpoints, which may carry a [`Condition`], when a condition
+//! without an address becomes true, when a watchpoint sees its memory read or written, when
+//! a subroutine call completes or returns, when the program blocks on the keypad, or after
+//! a given number of instructions:
+//!
+//! ```
+//! use chip_8::debugger::{Debugger, StopReason};
+//! use chip_8::Chip;
+//!
+//! let mut chip = Chip::new();
+//! // 0x200: CALL 0x206, 0x202: JP 0x202, 0x206: LD V0, 0x2A, 0x208: RET
+//! chip.load_rom_bytes(&[0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x60, 0x2A, 0x00, 0xEE]).unwrap();
+//!
+//! let mut debugger = Debugger::new(chip, 12);
+//! debugger.add_breakpoint(0x202);
+//! assert_eq!(debugger.run(1000).unwrap(), StopReason::Breakpoint(0x202));
+//! assert_eq!(debugger.chip().registers()[0], 0x2A);
+//! ```
+
+mod expr;
+
+use std::collections::BTreeMap;
+
+pub use expr::Condition;
+
+use crate::chip::{AccessKind, Chip, MemoryAccess};
+use crate::error::Chip8Error;
+use crate::instruction::Instruction;
+use crate::scheduler::{ManualClock, Scheduler};
+
+/// Why execution stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason
+{
+    /// The requested instruction, or the called subroutine, was executed.
+    Stepped,
+    /// The program counter reached a breakpoint whose condition, if any, holds.
+    Breakpoint(u16),
+    /// The condition with this index in [`Debugger::conditions`] became true.
+    Condition(usize),
+    /// The instruction at `instruction` made a memory access a watchpoint covers.
+    Watchpoint{ instruction : u16, access : MemoryAccess },
+    /// The current subroutine returned.
+    Returned,
+    /// The program exited with 0x00FD.
+    Halted,
+    /// The program is blocked in 0xFX0A until a key is pressed and released.
+    WaitingForKey,
+    /// The instruction limit was reached.
+    Limit,
+}
+
+/// The kinds of memory access a [`Watchpoint`] reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind
+{
+    Read,
+    Write,
+    /// Reads and writes.
+    Access,
+}
+
+impl WatchKind
+{
+    fn matches(self, kind: AccessKind) -> bool
+    {
+        match self
+        {
+            WatchKind::Read => kind == AccessKind::Read,
+            WatchKind::Write => kind == AccessKind::Write,
+            WatchKind::Access => true,
+        }
+    }
+}
+
+/// Stops execution when an instruction touches `length` bytes of memory from `start`.
+/// Only the data accesses listed by [`Chip::memory_accesses`] count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint
+{
+    pub start : u16,
+    pub length : usize,
+    pub kind : WatchKind,
+}
+
+/// Runs a [`Chip`] under control of breakpoints.
+pub struct Debugger
+{
+    chip : Chip,
+    scheduler : Scheduler<ManualClock>,
+    breakpoints : BTreeMap<u16, Option<Condition>>,
+    conditions : Vec<Condition>,
+    watchpoints : Vec<Watchpoint>,
+}
+
+impl Debugger
+{
+    /// Takes control of `chip`. The timers tick once every `instructions_per_frame` instructions.
+    pub fn new(chip: Chip, instructions_per_frame: u32) -> Debugger
+    {
+        Debugger{
+            chip,
+            scheduler: Scheduler::new(ManualClock::new(), instructions_per_frame),
+            breakpoints: BTreeMap::new(),
+            conditions: Vec::new(),
+            watchpoints: Vec::new(),
+        }
+    }
+
+    pub fn chip(&self) -> &Chip
+    {
+        &self.chip
+    }
+
+    /// Mutable access to the machine, e.g. to press keys or patch memory.
+    pub fn chip_mut(&mut self) -> &mut Chip
+    {
+        &mut self.chip
+    }
+
+    pub fn into_chip(self) -> Chip
+    {
+        self.chip
+    }
+
+    /// Number of instructions executed so far, including the idle cycles spent waiting
+    /// for the display.
+    pub fn cycles(&self) -> u64
+    {
+        self.scheduler.cycles()
+    }
+
+    /// Number of 60 Hz frames completed so far.
+    pub fn frames(&self) -> u64
+    {
+        self.scheduler.frames()
+    }
+
+    /// Sets a breakpoint at `address`. Returns false if there already was one, which is
+    /// replaced.
+    pub fn add_breakpoint(&mut self, address: u16) -> bool
+    {
+        self.breakpoints.insert(address, None).is_none()
+    }
+
+    /// Sets a breakpoint at `address` that only stops when `condition` holds.
+    /// Returns false if there already was one, which is replaced.
+    pub fn add_conditional_breakpoint(&mut self, address: u16, condition: Condition) -> bool
+    {
+        self.breakpoints.insert(address, Some(condition)).is_none()
+    }
+
+    /// Removes the breakpoint at `address`. Returns false if there was none.
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool
+    {
+        self.breakpoints.remove(&address).is_some()
+    }
+
+    /// Removes all breakpoints, conditions and watchpoints.
+    pub fn clear_breakpoints(&mut self)
+    {
+        self.breakpoints.clear();
+        self.conditions.clear();
+        self.watchpoints.clear();
+    }
+
+    /// The breakpoints in ascending order of address, with their conditions.
+    pub fn breakpoints(&self) -> impl Iterator<Item = (u16, Option<&Condition>)> + '_
+    {
+        self.breakpoints.iter().map(|(address, condition)| (*address, condition.as_ref()))
+    }
+
+    /// Stops execution after any instruction once `condition` holds, wherever the program is.
+    /// Returns the index of the condition.
+    pub fn add_condition(&mut self, condition: Condition) -> usize
+    {
+        self.conditions.push(condition);
+        self.conditions.len() - 1
+    }
+
+    /// Removes the condition with index `index`. Later conditions move down by one.
+    pub fn remove_condition(&mut self, index: usize) -> Option<Condition>
+    {
+        (index < self.conditions.len()).then(|| self.conditions.remove(index))
+    }
+
+    pub fn conditions(&self) -> &[Condition]
+    {
+        &self.conditions
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint)
+    {
+        self.watchpoints.push(watchpoint);
+    }
+
+    /// Removes all watchpoints starting at `start`. Returns false if there were none.
+    pub fn remove_watchpoint(&mut self, start: u16) -> bool
+    {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|watchpoint| watchpoint.start != start);
+        self.watchpoints.len() != count
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint]
+    {
+        &self.watchpoints
+    }
+
+    /// The instruction at the program counter, or `None` if it does not decode.
+    pub fn current_instruction(&self) -> Option<Instruction>
+    {
+        let pc = self.chip.program_counter() as usize;
+        let memory = self.chip.memory();
+        let word = |at: usize| memory.get(at..at + 2).map(|pair| (pair[0] as u16) << 8 | pair[1] as u16);
+        Instruction::decode(word(pc)?, word(pc + 2).unwrap_or(0), self.chip.variant())
+    }
+
+    /// Executes a single instruction.
+    pub fn step(&mut self) -> Result<StopReason, Chip8Error>
+    {
+        if self.chip.is_halted()
+        {
+            return Ok(StopReason::Halted);
+        }
+        let instruction = self.execute_one()?;
+        Ok(self.stop_state(instruction).unwrap_or(StopReason::Stepped))
+    }
+
+    /// Executes a single instruction, but runs a subroutine call to completion as if it were
+    /// one instruction. Stops early at breakpoints inside the subroutine.
+    pub fn step_over(&mut self, limit: u64) -> Result<StopReason, Chip8Error>
+    {
+        match self.current_instruction()
+        {
+            Some(Instruction::Call{ .. }) =>
+            {
+                let depth = self.chip.stack_pointer();
+                let return_address = self.chip.program_counter().wrapping_add(2);
+                self.run_until(limit, |chip|
+                    chip.stack_pointer() == depth && chip.program_counter() == return_address)
+                    .map(|reason| if reason == StopReason::Returned {StopReason::Stepped} else {reason})
+            },
+            _ => self.step(),
+        }
+    }
+
+    /// Runs until the current subroutine returns to its caller.
+    pub fn run_until_return(&mut self, limit: u64) -> Result<StopReason, Chip8Error>
+    {
+        let depth = self.chip.stack_pointer();
+        self.run_until(limit, |chip| chip.stack_pointer() < depth)
+    }
+
+    /// Runs until a breakpoint is hit, the program stops or `limit` instructions were
+    /// executed. A breakpoint at the current instruction does not stop it again.
+    pub fn run(&mut self, limit: u64) -> Result<StopReason, Chip8Error>
+    {
+        self.run_until(limit, |_| false)
+    }
+
+    /// Runs until `done` returns true after an instruction, reporting that as
+    /// [`StopReason::Returned`], or until anything else stops execution.
+    fn run_until<F: Fn(&Chip) -> bool>(&mut self, limit: u64, done: F) -> Result<StopReason, Chip8Error>
+    {
+        for _ in 0..limit
+        {
+            if self.chip.is_halted()
+            {
+                return Ok(StopReason::Halted);
+            }
+            let instruction = self.execute_one()?;
+            if done(&self.chip)
+            {
+                return Ok(StopReason::Returned);
+            }
+            if let Some(reason) = self.stop_state(instruction)
+            {
+                return Ok(reason);
+            }
+        }
+        Ok(StopReason::Limit)
+    }
+
+    /// Executes the next instruction and returns its address. If the program is waiting for
+    /// the display, the rest of the frame passes first so a step always makes progress.
+    fn execute_one(&mut self) -> Result<u16, Chip8Error>
+    {
+        if self.chip.is_waiting_for_vblank()
+        {
+            self.scheduler.run_frame(&mut self.chip)?;
+        }
+        let address = self.chip.program_counter();
+        self.scheduler.step(&mut self.chip)?;
+        Ok(address)
+    }
+
+    /// Whether the machine is in a state that stops execution after the instruction at
+    /// `instruction` was executed.
+    fn stop_state(&self, instruction: u16) -> Option<StopReason>
+    {
+        let pc = self.chip.program_counter();
+        if self.chip.is_halted()
+        {
+            return Some(StopReason::Halted);
+        }
+
+        let watched = self.chip.memory_accesses().iter().find(|access| self.watchpoints.iter().any(|watchpoint|
+            watchpoint.kind.matches(access.kind) && access.overlaps(watchpoint.start, watchpoint.length)));
+        if let Some(access) = watched
+        {
+            return Some(StopReason::Watchpoint{ instruction, access: *access });
+        }
+
+        if let Some(condition) = self.breakpoints.get(&pc)
+        {
+            if condition.as_ref().is_none_or(|condition| condition.is_true(&self.chip))
+            {
+                return Some(StopReason::Breakpoint(pc));
+            }
+        }
+        if let Some(index) = self.conditions.iter().position(|condition| condition.is_true(&self.chip))
+        {
+            return Some(StopReason::Condition(index));
+        }
+        if self.chip.is_waiting_for_key()
+        {
+            return Some(StopReason::WaitingForKey);
+        }
+        None
+    }
+}
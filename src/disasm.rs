@@ -0,0 +1,110 @@
+//! Turns machine code back into mnemonic listings.
+//!
+//! Decoding goes through [`Instruction::decode`], the same table the interpreter executes,
+//! so a listing always shows what [`Chip`] would do with the bytes. Each line holds the
+//! address, the raw bytes and the instruction:
+//!
+//! ```text
+//! 0200  00E0        CLS
+//! 0202  A22A        LD I, 0x22A
+//! 0204  600C        LD V0, 0x0C
+//! ```
+//!
+//! Words that do not decode are written as `DW` data and a trailing odd byte as `DB`.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+
+use crate::chip::Chip;
+use crate::instruction::Instruction;
+use crate::variant::Variant;
+
+/// One disassembled instruction or data word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line
+{
+    pub address : u16,
+    pub bytes : Vec<u8>,
+    /// The decoded instruction, or `None` if the bytes are not a valid opcode.
+    pub instruction : Option<Instruction>,
+}
+
+impl fmt::Display for Line
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let hex : String = self.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        write!(f, "{:04X}  {:<10}  ", self.address, hex)?;
+        match (&self.instruction, self.bytes.as_slice())
+        {
+            (Some(instruction), _) => write!(f, "{}", instruction),
+            (None, [high, low]) => write!(f, "DW 0x{:02X}{:02X}", high, low),
+            (None, bytes) =>
+            {
+                let data : Vec<String> = bytes.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+                write!(f, "DB {}", data.join(", "))
+            },
+        }
+    }
+}
+
+/// Disassembles `bytes` as if they were loaded at address `start`.
+pub fn disassemble(bytes: &[u8], start: u16, variant: Variant) -> Vec<Line>
+{
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len()
+    {
+        let address = start.wrapping_add(offset as u16);
+        if offset + 1 == bytes.len()
+        {
+            lines.push(Line{ address, bytes: bytes[offset..].to_vec(), instruction: None });
+            break;
+        }
+
+        let word = |at: usize| bytes.get(at..at + 2).map(|pair| (pair[0] as u16) << 8 | pair[1] as u16);
+        let opcode = word(offset).unwrap();
+        let next = word(offset + 2);
+        let instruction = match Instruction::decode(opcode, next.unwrap_or(0), variant)
+        {
+            // A long load cut off by the end of the range is data, not a valid instruction.
+            Some(Instruction::LongIndex{ .. }) if next.is_none() => None,
+            instruction => instruction,
+        };
+
+        let size = instruction.map_or(2, |instruction| instruction.size() as usize);
+        lines.push(Line{ address, bytes: bytes[offset..offset + size].to_vec(), instruction });
+        offset += size;
+    }
+    lines
+}
+
+/// Disassembles `length` bytes of `chip`'s memory starting at `start`, using the chip's
+/// current variant. The range is cut short at the end of memory.
+pub fn disassemble_memory(chip: &Chip, start: u16, length: usize) -> Vec<Line>
+{
+    let memory = chip.memory();
+    let start_index = (start as usize).min(memory.len());
+    let end = start_index.saturating_add(length).min(memory.len());
+    disassemble(&memory[start_index..end], start, chip.variant())
+}
+
+/// Renders `lines` as a listing, one line each. Addresses that are the target of a jump or
+/// call within the listing get a label line of the form `L0228:` in front of them.
+pub fn format_listing(lines: &[Line]) -> String
+{
+    let targets : BTreeSet<u16> = lines.iter()
+        .filter_map(|line| line.instruction.and_then(|instruction| instruction.target()))
+        .collect();
+
+    let mut out = String::new();
+    for line in lines
+    {
+        if targets.contains(&line.address)
+        {
+            writeln!(out, "L{:04X}:", line.address).unwrap();
+        }
+        writeln!(out, "{}", line).unwrap();
+    }
+    out
+}
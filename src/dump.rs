@@ -0,0 +1,65 @@
+//! Plain text renderings of the machine state, used for dumps and debugging output.
+
+use std::fmt::Write;
+
+use crate::chip::Chip;
+
+/// Renders the framebuffer with `#` for lit and `.` for unlit pixels, one line per row.
+/// XO-CHIP pixels lit only in the second bit-plane are `+`, and pixels lit in both are `*`.
+pub fn format_screen(chip: &Chip) -> String
+{
+    let mut out = String::new();
+    let width = chip.screen_width();
+    for row in chip.texture().chunks(width)
+    {
+        out.extend(row.iter().map(|pixel| pixel_char(*pixel)));
+        out.push('\n');
+    }
+    out
+}
+
+/// The character [`format_screen`] uses for a framebuffer pixel.
+pub(crate) fn pixel_char(pixel: u8) -> char
+{
+    match pixel & 0x3
+    {
+        0 => '.',
+        1 => '#',
+        2 => '+',
+        _ => '*',
+    }
+}
+
+/// Renders V0-VF followed by I, the program counter, the stack and both timers.
+pub fn format_registers(chip: &Chip) -> String
+{
+    let mut out = String::new();
+    for (i, value) in chip.registers().iter().enumerate()
+    {
+        let separator = if i % 8 == 7 {'\n'} else {' '};
+        write!(out, "V{:X}={:02X}{}", i, value, separator).unwrap();
+    }
+    writeln!(out, "I={:04X} PC={:04X} SP={:X} DT={:02X} ST={:02X}",
+        chip.index_register(), chip.program_counter(), chip.stack_pointer(),
+        chip.delay_timer(), chip.sound_timer()).unwrap();
+
+    let stack : Vec<String> = chip.stack().iter().map(|address| format!("{:04X}", address)).collect();
+    writeln!(out, "stack=[{}]", stack.join(" ")).unwrap();
+    out
+}
+
+/// Renders `bytes` as a hex dump with 16 bytes per line, labelling the first line with `start`.
+pub fn format_memory(bytes: &[u8], start: usize) -> String
+{
+    let mut out = String::new();
+    for (line, chunk) in bytes.chunks(16).enumerate()
+    {
+        write!(out, "{:04X}:", start + line * 16).unwrap();
+        for byte in chunk
+        {
+            write!(out, " {:02X}", byte).unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can go wrong while loading or running a ROM.
+#[derive(Debug)]
+pub enum Chip8Error
+{
+    /// The ROM file could not be opened or read.
+    RomIo { path: PathBuf, source: io::Error },
+    /// The ROM does not fit into the memory between 0x200 and 0x1000.
+    RomTooLarge { size: usize, max_size: usize },
+    /// The opcode fetched from `address` is not a known instruction.
+    UnknownOpcode { opcode: u16, address: u16 },
+    /// The instruction at `address` called a subroutine while all 16 stack entries were in use.
+    StackOverflow { address: u16 },
+    /// The instruction at `address` returned from a subroutine while the stack was empty.
+    StackUnderflow { address: u16 },
+    /// The instruction at `address` accessed `target`, which lies outside of main memory.
+    /// This happens when I points too close to the end of memory or the program counter runs off it.
+    MemoryOutOfBounds { address: u16, target: usize },
+    /// The bytes passed to [`Chip::load_state`](crate::Chip::load_state) are not a save state
+    /// this version can load.
+    InvalidSaveState { reason: String },
+    /// The bytes passed to [`Movie::from_bytes`](crate::movie::Movie::from_bytes) are not a
+    /// movie this version can play.
+    InvalidMovie { reason: String },
+}
+
+impl fmt::Display for Chip8Error
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Chip8Error::RomIo { path, source } =>
+                write!(f, "could not load rom {}: {}", path.display(), source),
+            Chip8Error::RomTooLarge { size, max_size } =>
+                write!(f, "rom is {} bytes but at most {} bytes fit into memory", size, max_size),
+            Chip8Error::UnknownOpcode { opcode, address } =>
+                write!(f, "unknown opcode {:#06X} at {:#05X}", opcode, address),
+            Chip8Error::StackOverflow { address } =>
+                write!(f, "stack overflow: subroutine call at {:#05X} exceeds 16 levels", address),
+            Chip8Error::StackUnderflow { address } =>
+                write!(f, "stack underflow: return at {:#05X} with an empty stack", address),
+            Chip8Error::MemoryOutOfBounds { address, target } =>
+                write!(f, "instruction at {:#05X} accessed memory at {:#X}, which is out of bounds", address, target),
+            Chip8Error::InvalidSaveState { reason } =>
+                write!(f, "invalid save state: {}", reason),
+            Chip8Error::InvalidMovie { reason } =>
+                write!(f, "invalid movie: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>
+    {
+        match self
+        {
+            Chip8Error::RomIo { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,461 @@
+//! Golden-screen tests: run a ROM headlessly and compare the display with a reference image.
+//!
+//! A [`Harness`] runs a machine in 60 Hz frames without a clock, pressing keys as a
+//! [`KeyScript`] says, until a frame count is reached or the program exits. The resulting
+//! [`Screen`] is compared with a reference stored as text, in the format of
+//! [`dump::format_screen`](crate::dump::format_screen), or as a PNG:
+//!
+//! ```
+//! use chip_8::harness::{Harness, Screen};
+//! use chip_8::Chip;
+//!
+//! let mut chip = Chip::new();
+//! // 0x200: LD V0, 1, 0x202: LD F, V0, 0x204: DRW V1, V1, 5, 0x206: JP 0x206
+//! chip.load_rom_bytes(&[0x60, 0x01, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06]).unwrap();
+//!
+//! let mut harness = Harness::new(chip, 12);
+//! let outcome = harness.run(10).unwrap();
+//! assert_eq!(outcome.frames, 10);
+//!
+//! let mut expected = String::new();
+//! for row in ["..#.....", ".##.....", "..#.....", "..#.....", ".###...."]
+//! {
+//!     expected += &format!("{:.<64}\n", row);
+//! }
+//! expected += &format!("{:.<64}\n", "").repeat(27);
+//! let expected : Screen = expected.parse().unwrap();
+//! assert!(harness.screen().diff(&expected).is_none());
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::chip::Chip;
+use crate::dump::pixel_char;
+use crate::error::Chip8Error;
+use crate::palette::{Palette, Rgb};
+use crate::scheduler::{ManualClock, Scheduler};
+use crate::screenshot::{self, ImageFormat};
+
+/// A key going down or up at the start of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent
+{
+    pub frame : u64,
+    pub key : u8,
+    pub pressed : bool,
+}
+
+/// Scripted key presses for a headless run.
+///
+/// The text form has one event per line, `#` starts a comment:
+///
+/// ```text
+/// # frame  event  key  [frames]
+/// 30       down   5
+/// 40       up     5
+/// 60       press  a    4        # down at frame 60, up at frame 64
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyScript
+{
+    /// Sorted by frame; events of the same frame keep their order.
+    events : Vec<KeyEvent>,
+}
+
+impl KeyScript
+{
+    pub fn new() -> KeyScript
+    {
+        KeyScript::default()
+    }
+
+    pub fn push(&mut self, event: KeyEvent)
+    {
+        let position = self.events.partition_point(|other| other.frame <= event.frame);
+        self.events.insert(position, event);
+    }
+
+    /// Presses `key` at the start of `frame` and releases it `frames` frames later, or at
+    /// the last frame if that is further than a `u64` counts.
+    pub fn press(&mut self, frame: u64, key: u8, frames: u64)
+    {
+        self.push(KeyEvent{ frame, key, pressed: true });
+        self.push(KeyEvent{ frame: frame.saturating_add(frames.max(1)), key, pressed: false });
+    }
+
+    pub fn events(&self) -> &[KeyEvent]
+    {
+        &self.events
+    }
+
+    fn apply(&self, chip: &mut Chip, frame: u64)
+    {
+        let start = self.events.partition_point(|event| event.frame < frame);
+        for event in self.events[start..].iter().take_while(|event| event.frame == frame)
+        {
+            chip.set_key(event.key, event.pressed);
+        }
+    }
+}
+
+impl FromStr for KeyScript
+{
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<KeyScript, String>
+    {
+        let mut script = KeyScript::new();
+        for (number, line) in text.lines().enumerate()
+        {
+            let line = line.split('#').next().unwrap();
+            let words : Vec<&str> = line.split_whitespace().collect();
+            if words.is_empty()
+            {
+                continue;
+            }
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+
+            let frame : u64 = words[0].parse().map_err(|_| error("expected a frame number"))?;
+            let key = words.get(2)
+                .and_then(|key| u8::from_str_radix(key, 16).ok())
+                .filter(|key| *key < 16)
+                .ok_or_else(|| error("expected a key from 0 to f"))?;
+            match (words[1], &words[3..])
+            {
+                ("down", []) => script.push(KeyEvent{ frame, key, pressed: true }),
+                ("up", []) => script.push(KeyEvent{ frame, key, pressed: false }),
+                ("press", []) | ("press", [_]) =>
+                {
+                    let frames = match words.get(3)
+                    {
+                        Some(frames) => frames.parse().map_err(|_| error("expected a number of frames"))?,
+                        None => 1,
+                    };
+                    frame.checked_add(frames.max(1)).ok_or_else(|| error("frame out of range"))?;
+                    script.press(frame, key, frames);
+                },
+                ("down", _) | ("up", _) | ("press", _) => return Err(error("too many values")),
+                (other, _) => return Err(error(&format!("unknown event {:?}, expected down, up or press", other))),
+            }
+        }
+        Ok(script)
+    }
+}
+
+/// How a [`Harness::run`] ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome
+{
+    /// Number of frames run in total.
+    pub frames : u64,
+    /// Number of instructions executed in total.
+    pub cycles : u64,
+    /// Whether the program exited with 0x00FD.
+    pub halted : bool,
+}
+
+/// Runs a machine headlessly and deterministically, frame by frame.
+pub struct Harness
+{
+    chip : Chip,
+    scheduler : Scheduler<ManualClock>,
+    script : KeyScript,
+}
+
+impl Harness
+{
+    /// Takes over `chip`, which should have its ROM loaded, to run it at `instructions_per_frame`.
+    pub fn new(chip: Chip, instructions_per_frame: u32) -> Harness
+    {
+        Harness{
+            chip,
+            scheduler: Scheduler::new(ManualClock::new(), instructions_per_frame),
+            script: KeyScript::new(),
+        }
+    }
+
+    pub fn set_script(&mut self, script: KeyScript)
+    {
+        self.script = script;
+    }
+
+    /// Runs until `frames` frames have passed since the start or the program exits.
+    /// Fails if the program faults.
+    pub fn run(&mut self, frames: u64) -> Result<Outcome, Chip8Error>
+    {
+        while self.scheduler.frames() < frames && !self.chip.is_halted()
+        {
+            self.script.apply(&mut self.chip, self.scheduler.frames());
+            while !self.chip.is_halted()
+            {
+                if self.scheduler.step(&mut self.chip)?
+                {
+                    break;
+                }
+            }
+        }
+
+        Ok(Outcome{
+            frames: self.scheduler.frames(),
+            cycles: self.scheduler.cycles(),
+            halted: self.chip.is_halted(),
+        })
+    }
+
+    pub fn chip(&self) -> &Chip
+    {
+        &self.chip
+    }
+
+    pub fn into_chip(self) -> Chip
+    {
+        self.chip
+    }
+
+    pub fn screen(&self) -> Screen
+    {
+        Screen::capture(&self.chip)
+    }
+}
+
+/// A copy of the display, with one value from 0 to 3 per pixel like [`Chip::texture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen
+{
+    width : usize,
+    height : usize,
+    pixels : Vec<u8>,
+}
+
+impl Screen
+{
+    pub fn capture(chip: &Chip) -> Screen
+    {
+        Screen{
+            width: chip.screen_width(),
+            height: chip.screen_height(),
+            pixels: chip.texture().iter().map(|pixel| pixel & 0x3).collect(),
+        }
+    }
+
+    pub fn width(&self) -> usize
+    {
+        self.width
+    }
+
+    pub fn height(&self) -> usize
+    {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8]
+    {
+        &self.pixels
+    }
+
+    /// Reads a reference: a PNG if the file name ends in `.png`, text otherwise. A PNG may be
+    /// an integer multiple of `width` by `height` and may only use colors of `palette`.
+    pub fn load<P: AsRef<Path>>(path: P, width: usize, height: usize, palette: &Palette) -> Result<Screen, String>
+    {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|error| format!("could not read {}: {}", path.display(), error))?;
+        let screen = if is_png(path)
+        {
+            Screen::from_png(&bytes, width, height, palette)
+        }
+        else
+        {
+            String::from_utf8_lossy(&bytes).parse()
+        };
+        screen.map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Writes the screen as a reference that [`load`](Screen::load) reads back.
+    pub fn save<P: AsRef<Path>>(&self, path: P, palette: &Palette) -> Result<(), String>
+    {
+        let path = path.as_ref();
+        let bytes = if is_png(path) {self.to_png(palette)} else {self.to_string().into_bytes()};
+        fs::write(path, bytes).map_err(|error| format!("could not write {}: {}", path.display(), error))
+    }
+
+    /// Decodes a PNG of this screen scaled by an integer factor.
+    pub fn from_png(bytes: &[u8], width: usize, height: usize, palette: &Palette) -> Result<Screen, String>
+    {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(|error| error.to_string())?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(|error| error.to_string())?;
+
+        let (image_width, image_height) = (info.width as usize, info.height as usize);
+        let scale = image_width / width;
+        if scale == 0 || image_width != width * scale || image_height != height * scale
+        {
+            return Err(format!("the image is {}x{}, which is not the {}x{} screen scaled up",
+                image_width, image_height, width, height));
+        }
+
+        let channels = info.color_type.samples();
+        let rgb = |x: usize, y: usize|
+        {
+            let at = y * info.line_size + x * channels;
+            match info.color_type
+            {
+                png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => (buffer[at], buffer[at], buffer[at]),
+                _ => (buffer[at], buffer[at + 1], buffer[at + 2]),
+            }
+        };
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height
+        {
+            for x in 0..width
+            {
+                let (r, g, b) = rgb(x * scale, y * scale);
+                let pixel = (0..4).find(|pixel| palette.color(*pixel) == Rgb(r, g, b))
+                    .ok_or_else(|| format!("pixel ({}, {}) has the color #{:02X}{:02X}{:02X}, which is not in the palette",
+                        x, y, r, g, b))?;
+                pixels.push(pixel);
+            }
+        }
+        Ok(Screen{ width, height, pixels })
+    }
+
+    /// Encodes the screen as a PNG with one image pixel per screen pixel.
+    pub fn to_png(&self, palette: &Palette) -> Vec<u8>
+    {
+        screenshot::encode(&self.pixels, self.width, ImageFormat::Png, 1, palette)
+    }
+
+    /// Compares the screen with `expected`. Returns `None` if they are the same.
+    pub fn diff(&self, expected: &Screen) -> Option<ScreenDiff>
+    {
+        if self == expected
+        {
+            return None;
+        }
+        Some(ScreenDiff{ actual: self.clone(), expected: expected.clone() })
+    }
+
+    fn row(&self, y: usize) -> &[u8]
+    {
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+}
+
+impl FromStr for Screen
+{
+    type Err = String;
+
+    /// Parses the format of [`dump::format_screen`](crate::dump::format_screen). Trailing
+    /// whitespace and empty lines at the end are ignored.
+    fn from_str(text: &str) -> Result<Screen, String>
+    {
+        let rows : Vec<&str> = text.trim_end().lines().map(|line| line.trim_end()).collect();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width == 0
+        {
+            return Err("the screen is empty".to_string());
+        }
+
+        let mut pixels = Vec::with_capacity(width * rows.len());
+        for (y, row) in rows.iter().enumerate()
+        {
+            if row.chars().count() != width
+            {
+                return Err(format!("row {} has {} pixels instead of {}", y, row.chars().count(), width));
+            }
+            for (x, c) in row.chars().enumerate()
+            {
+                let pixel = (0..4).find(|pixel| pixel_char(*pixel) == c)
+                    .ok_or_else(|| format!("unexpected {:?} at ({}, {}), expected one of .#+*", c, x, y))?;
+                pixels.push(pixel);
+            }
+        }
+        Ok(Screen{ width, height: rows.len(), pixels })
+    }
+}
+
+impl fmt::Display for Screen
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        for y in 0..self.height
+        {
+            let row : String = self.row(y).iter().map(|pixel| pixel_char(*pixel)).collect();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+/// How many differing rows a [`ScreenDiff`] shows.
+const MAX_DIFF_ROWS : usize = 8;
+
+/// The difference between two screens, displayed as the differing rows of both with the
+/// differing pixels marked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenDiff
+{
+    pub actual : Screen,
+    pub expected : Screen,
+}
+
+impl ScreenDiff
+{
+    /// The coordinates of all differing pixels, row by row. Empty if the sizes differ.
+    pub fn pixels(&self) -> Vec<(usize, usize)>
+    {
+        if (self.actual.width, self.actual.height) != (self.expected.width, self.expected.height)
+        {
+            return Vec::new();
+        }
+        let width = self.actual.width;
+        self.actual.pixels.iter().zip(&self.expected.pixels).enumerate()
+            .filter(|(_, (actual, expected))| actual != expected)
+            .map(|(index, _)| (index % width, index / width))
+            .collect()
+    }
+}
+
+impl fmt::Display for ScreenDiff
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let (actual, expected) = (&self.actual, &self.expected);
+        if (actual.width, actual.height) != (expected.width, expected.height)
+        {
+            return writeln!(f, "the screen is {}x{} but the reference is {}x{}",
+                actual.width, actual.height, expected.width, expected.height);
+        }
+
+        let pixels = self.pixels();
+        writeln!(f, "{} of {} pixels differ, the first at ({}, {})",
+            pixels.len(), actual.pixels.len(), pixels[0].0, pixels[0].1)?;
+        let mut rows : Vec<usize> = pixels.iter().map(|(_, y)| *y).collect();
+        rows.dedup();
+        for y in rows.iter().take(MAX_DIFF_ROWS)
+        {
+            let (actual_row, expected_row) = (actual.row(*y), expected.row(*y));
+            let marks : String = actual_row.iter().zip(expected_row)
+                .map(|(actual, expected)| if actual == expected {' '} else {'^'})
+                .collect();
+            writeln!(f, "row {}:", y)?;
+            writeln!(f, "  expected {}", expected_row.iter().map(|pixel| pixel_char(*pixel)).collect::<String>())?;
+            writeln!(f, "  actual   {}", actual_row.iter().map(|pixel| pixel_char(*pixel)).collect::<String>())?;
+            writeln!(f, "           {}", marks.trim_end())?;
+        }
+        if rows.len() > MAX_DIFF_ROWS
+        {
+            writeln!(f, "and {} more rows", rows.len() - MAX_DIFF_ROWS)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_png(path: &Path) -> bool
+{
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+}
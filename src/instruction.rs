@@ -0,0 +1,469 @@
+use std::fmt;
+
+use crate::opcode::OppCodeData;
+use crate::variant::Variant;
+
+/// A decoded instruction. [`Instruction::decode`] is the only place that knows how opcodes
+/// map to instructions; the interpreter and the disassembler both go through it.
+///
+/// The [`Display`](fmt::Display) implementation writes the instruction in the mnemonic
+/// syntax used by the [`disasm`](crate::disasm) module, e.g. `LD V0, 0x1F` or `DRW V1, V2, 5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction
+{
+    /// 0x00CN (SUPER-CHIP)
+    ScrollDown{ n : u8 },
+    /// 0x00DN (XO-CHIP)
+    ScrollUp{ n : u8 },
+    /// 0x00E0
+    ClearScreen,
+    /// 0x00EE
+    Return,
+    /// 0x00FB (SUPER-CHIP)
+    ScrollRight,
+    /// 0x00FC (SUPER-CHIP)
+    ScrollLeft,
+    /// 0x00FD (SUPER-CHIP)
+    Exit,
+    /// 0x00FE (SUPER-CHIP)
+    LowRes,
+    /// 0x00FF (SUPER-CHIP)
+    HighRes,
+    /// 0x1NNN
+    Jump{ nnn : u16 },
+    /// 0x2NNN
+    Call{ nnn : u16 },
+    /// 0x3XNN
+    SkipIfEqual{ x : u8, nn : u8 },
+    /// 0x4XNN
+    SkipIfNotEqual{ x : u8, nn : u8 },
+    /// 0x5XY0
+    SkipIfRegistersEqual{ x : u8, y : u8 },
+    /// 0x5XY2 (XO-CHIP)
+    SaveRange{ x : u8, y : u8 },
+    /// 0x5XY3 (XO-CHIP)
+    LoadRange{ x : u8, y : u8 },
+    /// 0x6XNN
+    Assign{ x : u8, nn : u8 },
+    /// 0x7XNN
+    AddImmediate{ x : u8, nn : u8 },
+    /// 0x8XY0
+    Copy{ x : u8, y : u8 },
+    /// 0x8XY1
+    Or{ x : u8, y : u8 },
+    /// 0x8XY2
+    And{ x : u8, y : u8 },
+    /// 0x8XY3
+    Xor{ x : u8, y : u8 },
+    /// 0x8XY4
+    Add{ x : u8, y : u8 },
+    /// 0x8XY5
+    Subtract{ x : u8, y : u8 },
+    /// 0x8XY6
+    ShiftRight{ x : u8, y : u8 },
+    /// 0x8XY7
+    SubtractReverse{ x : u8, y : u8 },
+    /// 0x8XYE
+    ShiftLeft{ x : u8, y : u8 },
+    /// 0x9XY0
+    SkipIfRegistersNotEqual{ x : u8, y : u8 },
+    /// 0xANNN
+    SetIndex{ nnn : u16 },
+    /// 0xBNNN
+    JumpOffset{ nnn : u16 },
+    /// 0xCXNN
+    Random{ x : u8, nn : u8 },
+    /// 0xDXYN
+    Draw{ x : u8, y : u8, n : u8 },
+    /// 0xEX9E
+    SkipIfKey{ x : u8 },
+    /// 0xEXA1
+    SkipIfNotKey{ x : u8 },
+    /// 0xF000 NNNN (XO-CHIP)
+    LongIndex{ nnnn : u16 },
+    /// 0xFN01 (XO-CHIP)
+    SelectPlanes{ n : u8 },
+    /// 0xF002 (XO-CHIP)
+    LoadAudio,
+    /// 0xFX07
+    GetDelayTimer{ x : u8 },
+    /// 0xFX0A
+    WaitForKey{ x : u8 },
+    /// 0xFX15
+    SetDelayTimer{ x : u8 },
+    /// 0xFX18
+    SetSoundTimer{ x : u8 },
+    /// 0xFX1E
+    AddToIndex{ x : u8 },
+    /// 0xFX29
+    Font{ x : u8 },
+    /// 0xFX30 (SUPER-CHIP)
+    BigFont{ x : u8 },
+    /// 0xFX33
+    BinaryCodedDecimal{ x : u8 },
+    /// 0xFX3A (XO-CHIP)
+    Pitch{ x : u8 },
+    /// 0xFX55
+    Store{ x : u8 },
+    /// 0xFX65
+    Load{ x : u8 },
+    /// 0xFX75 (SUPER-CHIP)
+    SaveFlags{ x : u8 },
+    /// 0xFX85 (SUPER-CHIP)
+    LoadFlags{ x : u8 },
+}
+
+impl Instruction
+{
+    /// Decodes `opcode` for the given instruction set. `next` is the word following the
+    /// opcode in memory, which only the four byte XO-CHIP 0xF000 NNNN uses.
+    /// Returns `None` for opcodes the variant does not know.
+    pub fn decode(opcode: u16, next: u16, variant: Variant) -> Option<Instruction>
+    {
+        let OppCodeData{ nnn, nn, n, x, y } = OppCodeData::new(opcode);
+        let superchip = variant.has_superchip();
+        let xochip = variant.has_xochip();
+
+        let instruction = match opcode & 0xF000
+        {
+            // Display and flow control opcodes are identified by the whole opcode
+            0x0000 => match opcode
+            {
+                // 0x00CN: scrolls the display down by N pixels (SUPER-CHIP)
+                0x00C0..=0x00CF if superchip => Instruction::ScrollDown{ n },
+
+                // 0x00DN: scrolls the display up by N pixels (XO-CHIP)
+                0x00D0..=0x00DF if xochip => Instruction::ScrollUp{ n },
+
+                // 0x00E0: clears the screen
+                0x00E0 => Instruction::ClearScreen,
+
+                // 0x00EE: returns from subroutine
+                0x00EE => Instruction::Return,
+
+                // 0x00FB: scrolls the display right by 4 pixels (SUPER-CHIP)
+                0x00FB if superchip => Instruction::ScrollRight,
+
+                // 0x00FC: scrolls the display left by 4 pixels (SUPER-CHIP)
+                0x00FC if superchip => Instruction::ScrollLeft,
+
+                // 0x00FD: exits the interpreter (SUPER-CHIP)
+                0x00FD if superchip => Instruction::Exit,
+
+                // 0x00FE: switches to low resolution (SUPER-CHIP)
+                0x00FE if superchip => Instruction::LowRes,
+
+                // 0x00FF: switches to high resolution (SUPER-CHIP)
+                0x00FF if superchip => Instruction::HighRes,
+
+                _ => return None,
+            },
+
+            // 0x1NNN jumps to address NNN
+            0x1000 => Instruction::Jump{ nnn },
+
+            // 0x2NNN call subroutine
+            0x2000 => Instruction::Call{ nnn },
+
+            // 0x3XNN skip if x equal
+            0x3000 => Instruction::SkipIfEqual{ x, nn },
+
+            // 0x4XNN skip if x not equal
+            0x4000 => Instruction::SkipIfNotEqual{ x, nn },
+
+            0x5000 => match n
+            {
+                // 0x5XY0 skip if x and y are equal
+                0x0 => Instruction::SkipIfRegistersEqual{ x, y },
+
+                // 0x5XY2 save registers x - y to memory at I (XO-CHIP)
+                0x2 if xochip => Instruction::SaveRange{ x, y },
+
+                // 0x5XY3 load registers x - y from memory at I (XO-CHIP)
+                0x3 if xochip => Instruction::LoadRange{ x, y },
+
+                _ => return None,
+            },
+
+            // 0x6XNN set register x to nn
+            0x6000 => Instruction::Assign{ x, nn },
+
+            // 0x7XNN add nn to register x
+            0x7000 => Instruction::AddImmediate{ x, nn },
+
+            0x8000 => match n
+            {
+                // 0x8XY0 set register x to register y
+                0x0 => Instruction::Copy{ x, y },
+
+                // 0x8XY1 set x to x or y
+                0x1 => Instruction::Or{ x, y },
+
+                // 0x8XY2 set x to x and y
+                0x2 => Instruction::And{ x, y },
+
+                // 0x8XY3 set x to x xor y
+                0x3 => Instruction::Xor{ x, y },
+
+                // 0x8XY4 set x to x + y and set register F to 1 on carry
+                0x4 => Instruction::Add{ x, y },
+
+                // 0x8XY5 set x to x - y and set register F to 0 on borrow
+                0x5 => Instruction::Subtract{ x, y },
+
+                // 0x8XY6 shift right by one, the shifted out bit goes to register F
+                0x6 => Instruction::ShiftRight{ x, y },
+
+                // 0x8XY7 set x to y - x and set register F to 0 on borrow
+                0x7 => Instruction::SubtractReverse{ x, y },
+
+                // 0x8XYE shift left by one, the shifted out bit goes to register F
+                0xE => Instruction::ShiftLeft{ x, y },
+
+                _ => return None,
+            },
+
+            // 0x9XY0 skips the next instruction if register x != register y
+            0x9000 if n == 0 => Instruction::SkipIfRegistersNotEqual{ x, y },
+
+            // 0xANNN sets I (index_register) to the address NNN
+            0xA000 => Instruction::SetIndex{ nnn },
+
+            // 0xBNNN jumps to the address NNN + register 0
+            0xB000 => Instruction::JumpOffset{ nnn },
+
+            // 0xCXNN sets register x to rnd() & NN
+            0xC000 => Instruction::Random{ x, nn },
+
+            // 0xDXYN draws a sprite at coordinate (register x, register y)
+            0xD000 => Instruction::Draw{ x, y, n },
+
+            0xE000 => match nn
+            {
+                // 0xEX9E skips the next instruction if the key stored in register x is pressed
+                0x9E => Instruction::SkipIfKey{ x },
+
+                // 0xEXA1 skips the next instruction if the key stored in register x is not pressed
+                0xA1 => Instruction::SkipIfNotKey{ x },
+
+                _ => return None,
+            },
+
+            0xF000 => match nn
+            {
+                // 0xF000 NNNN loads the following 16 bit word into I (XO-CHIP)
+                0x00 if x == 0 && xochip => Instruction::LongIndex{ nnnn: next },
+
+                // 0xFN01 selects the bit-planes N for drawing (XO-CHIP)
+                0x01 if xochip => Instruction::SelectPlanes{ n: x },
+
+                // 0xF002 loads the 16 byte audio pattern at I (XO-CHIP)
+                0x02 if x == 0 && xochip => Instruction::LoadAudio,
+
+                // 0xFX07 stores the delay timer to register x
+                0x07 => Instruction::GetDelayTimer{ x },
+
+                // 0xFX0A waits for key press and stores it in register x
+                0x0A => Instruction::WaitForKey{ x },
+
+                // 0xFX15 sets the delay timer to register x
+                0x15 => Instruction::SetDelayTimer{ x },
+
+                // 0xFX18 sets the sound timer to register x
+                0x18 => Instruction::SetSoundTimer{ x },
+
+                // 0xFX1E adds register x to the index register (I)
+                0x1E => Instruction::AddToIndex{ x },
+
+                // 0xFX29 points I at the font sprite of the digit in register x
+                0x29 => Instruction::Font{ x },
+
+                // 0xFX30 points I at the large font sprite of the digit in register x (SUPER-CHIP)
+                0x30 if superchip => Instruction::BigFont{ x },
+
+                // 0xFX33 stores register x as three decimal digits at I
+                0x33 => Instruction::BinaryCodedDecimal{ x },
+
+                // 0xFX3A sets the audio pattern pitch to register x (XO-CHIP)
+                0x3A if xochip => Instruction::Pitch{ x },
+
+                // 0xFX55 dump registers 0 - x into main memory
+                0x55 => Instruction::Store{ x },
+
+                // 0xFX65 load main memory into registers 0 - x
+                0x65 => Instruction::Load{ x },
+
+                // 0xFX75 saves registers 0 - x to the RPL user flags (SUPER-CHIP)
+                0x75 if superchip => Instruction::SaveFlags{ x },
+
+                // 0xFX85 loads registers 0 - x from the RPL user flags (SUPER-CHIP)
+                0x85 if superchip => Instruction::LoadFlags{ x },
+
+                _ => return None,
+            },
+
+            _ => return None,
+        };
+        Some(instruction)
+    }
+
+    /// Encodes the instruction back into its opcode. This is the inverse of [`decode`](Instruction::decode);
+    /// operands are masked to the width of their field.
+    pub fn opcode(&self) -> u16
+    {
+        let xy = |base: u16, x: u8, y: u8| base | (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4;
+        let xnn = |base: u16, x: u8, nn: u8| base | (x as u16 & 0xF) << 8 | nn as u16;
+        let x_ = |base: u16, x: u8| base | (x as u16 & 0xF) << 8;
+        match *self
+        {
+            Instruction::ScrollDown{ n } => 0x00C0 | (n as u16 & 0xF),
+            Instruction::ScrollUp{ n } => 0x00D0 | (n as u16 & 0xF),
+            Instruction::ClearScreen => 0x00E0,
+            Instruction::Return => 0x00EE,
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::LowRes => 0x00FE,
+            Instruction::HighRes => 0x00FF,
+            Instruction::Jump{ nnn } => 0x1000 | (nnn & 0xFFF),
+            Instruction::Call{ nnn } => 0x2000 | (nnn & 0xFFF),
+            Instruction::SkipIfEqual{ x, nn } => xnn(0x3000, x, nn),
+            Instruction::SkipIfNotEqual{ x, nn } => xnn(0x4000, x, nn),
+            Instruction::SkipIfRegistersEqual{ x, y } => xy(0x5000, x, y),
+            Instruction::SaveRange{ x, y } => xy(0x5002, x, y),
+            Instruction::LoadRange{ x, y } => xy(0x5003, x, y),
+            Instruction::Assign{ x, nn } => xnn(0x6000, x, nn),
+            Instruction::AddImmediate{ x, nn } => xnn(0x7000, x, nn),
+            Instruction::Copy{ x, y } => xy(0x8000, x, y),
+            Instruction::Or{ x, y } => xy(0x8001, x, y),
+            Instruction::And{ x, y } => xy(0x8002, x, y),
+            Instruction::Xor{ x, y } => xy(0x8003, x, y),
+            Instruction::Add{ x, y } => xy(0x8004, x, y),
+            Instruction::Subtract{ x, y } => xy(0x8005, x, y),
+            Instruction::ShiftRight{ x, y } => xy(0x8006, x, y),
+            Instruction::SubtractReverse{ x, y } => xy(0x8007, x, y),
+            Instruction::ShiftLeft{ x, y } => xy(0x800E, x, y),
+            Instruction::SkipIfRegistersNotEqual{ x, y } => xy(0x9000, x, y),
+            Instruction::SetIndex{ nnn } => 0xA000 | (nnn & 0xFFF),
+            Instruction::JumpOffset{ nnn } => 0xB000 | (nnn & 0xFFF),
+            Instruction::Random{ x, nn } => xnn(0xC000, x, nn),
+            Instruction::Draw{ x, y, n } => xy(0xD000, x, y) | (n as u16 & 0xF),
+            Instruction::SkipIfKey{ x } => x_(0xE09E, x),
+            Instruction::SkipIfNotKey{ x } => x_(0xE0A1, x),
+            Instruction::LongIndex{ .. } => 0xF000,
+            Instruction::SelectPlanes{ n } => x_(0xF001, n),
+            Instruction::LoadAudio => 0xF002,
+            Instruction::GetDelayTimer{ x } => x_(0xF007, x),
+            Instruction::WaitForKey{ x } => x_(0xF00A, x),
+            Instruction::SetDelayTimer{ x } => x_(0xF015, x),
+            Instruction::SetSoundTimer{ x } => x_(0xF018, x),
+            Instruction::AddToIndex{ x } => x_(0xF01E, x),
+            Instruction::Font{ x } => x_(0xF029, x),
+            Instruction::BigFont{ x } => x_(0xF030, x),
+            Instruction::BinaryCodedDecimal{ x } => x_(0xF033, x),
+            Instruction::Pitch{ x } => x_(0xF03A, x),
+            Instruction::Store{ x } => x_(0xF055, x),
+            Instruction::Load{ x } => x_(0xF065, x),
+            Instruction::SaveFlags{ x } => x_(0xF075, x),
+            Instruction::LoadFlags{ x } => x_(0xF085, x),
+        }
+    }
+
+    /// The machine code of the instruction, [`size`](Instruction::size) bytes long.
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut bytes = self.opcode().to_be_bytes().to_vec();
+        if let Instruction::LongIndex{ nnnn } = *self
+        {
+            bytes.extend_from_slice(&nnnn.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Size of the instruction in bytes. Everything is 2 bytes except 0xF000 NNNN.
+    pub fn size(&self) -> u16
+    {
+        match self
+        {
+            Instruction::LongIndex{ .. } => 4,
+            _ => 2,
+        }
+    }
+
+    /// The address the instruction jumps to or calls, if it is a fixed one.
+    pub fn target(&self) -> Option<u16>
+    {
+        match *self
+        {
+            Instruction::Jump{ nnn } | Instruction::Call{ nnn } => Some(nnn),
+            _ => None,
+        }
+    }
+
+    /// Whether execution can continue at the following instruction.
+    pub fn falls_through(&self) -> bool
+    {
+        !matches!(self, Instruction::Jump{ .. } | Instruction::JumpOffset{ .. }
+            | Instruction::Return | Instruction::Exit)
+    }
+}
+
+impl fmt::Display for Instruction
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match *self
+        {
+            Instruction::ScrollDown{ n } => write!(f, "SCD {}", n),
+            Instruction::ScrollUp{ n } => write!(f, "SCU {}", n),
+            Instruction::ClearScreen => write!(f, "CLS"),
+            Instruction::Return => write!(f, "RET"),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::LowRes => write!(f, "LOW"),
+            Instruction::HighRes => write!(f, "HIGH"),
+            Instruction::Jump{ nnn } => write!(f, "JP 0x{:03X}", nnn),
+            Instruction::Call{ nnn } => write!(f, "CALL 0x{:03X}", nnn),
+            Instruction::SkipIfEqual{ x, nn } => write!(f, "SE V{:X}, 0x{:02X}", x, nn),
+            Instruction::SkipIfNotEqual{ x, nn } => write!(f, "SNE V{:X}, 0x{:02X}", x, nn),
+            Instruction::SkipIfRegistersEqual{ x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::SaveRange{ x, y } => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            Instruction::LoadRange{ x, y } => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            Instruction::Assign{ x, nn } => write!(f, "LD V{:X}, 0x{:02X}", x, nn),
+            Instruction::AddImmediate{ x, nn } => write!(f, "ADD V{:X}, 0x{:02X}", x, nn),
+            Instruction::Copy{ x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or{ x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And{ x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor{ x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::Add{ x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Subtract{ x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight{ x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubtractReverse{ x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft{ x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipIfRegistersNotEqual{ x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::SetIndex{ nnn } => write!(f, "LD I, 0x{:03X}", nnn),
+            Instruction::JumpOffset{ nnn } => write!(f, "JP V0, 0x{:03X}", nnn),
+            Instruction::Random{ x, nn } => write!(f, "RND V{:X}, 0x{:02X}", x, nn),
+            Instruction::Draw{ x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::SkipIfKey{ x } => write!(f, "SKP V{:X}", x),
+            Instruction::SkipIfNotKey{ x } => write!(f, "SKNP V{:X}", x),
+            Instruction::LongIndex{ nnnn } => write!(f, "LD I, LONG 0x{:04X}", nnnn),
+            Instruction::SelectPlanes{ n } => write!(f, "PLANE {}", n),
+            Instruction::LoadAudio => write!(f, "AUDIO"),
+            Instruction::GetDelayTimer{ x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitForKey{ x } => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelayTimer{ x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSoundTimer{ x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddToIndex{ x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::Font{ x } => write!(f, "LD F, V{:X}", x),
+            Instruction::BigFont{ x } => write!(f, "LD HF, V{:X}", x),
+            Instruction::BinaryCodedDecimal{ x } => write!(f, "LD B, V{:X}", x),
+            Instruction::Pitch{ x } => write!(f, "PITCH V{:X}", x),
+            Instruction::Store{ x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::Load{ x } => write!(f, "LD V{:X}, [I]", x),
+            Instruction::SaveFlags{ x } => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadFlags{ x } => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
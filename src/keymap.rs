@@ -0,0 +1,302 @@
+//! Mapping of host keyboard keys onto the 16 key hex keypad.
+//!
+//! A [`Keymap`] starts out from one of the [`Preset`] layouts and can be adjusted with a
+//! config file. The file consists of `host key = keypad key` lines and an optional
+//! `preset = <name>` line. Lines after a `[rom name]` header only apply to the ROM with
+//! that file name and override the lines before the first header:
+//!
+//! ```text
+//! # Use AZERTY everywhere, but move key 5 onto the space bar.
+//! preset = azerty
+//! space = 5
+//!
+//! [tetris.ch8]
+//! left = 5
+//! right = 6
+//! up = 4
+//! ```
+//!
+//! Host keys are single characters other than `#` and `=`, or one of `up`, `down`, `left`,
+//! `right`, `enter` and `space`; Backspace is reserved by the frontend. Keypad keys are hex
+//! digits; `none` removes a binding. Everything after a `#` is a comment.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::terminal::HostKey;
+
+/// The built-in keyboard layouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset
+{
+    /// The left four columns of a QWERTY keyboard: 1234/QWER/ASDF/ZXCV.
+    Qwerty,
+    /// The same keys on an AZERTY keyboard: 1234/AZER/QSDF/WXCV. The unshifted
+    /// characters of the number row (`&é"'`) work as well.
+    Azerty,
+    /// The numeric keypad: 789//456*/123-/0.⏎+ in the positions of the hex keypad rows.
+    Numpad,
+}
+
+impl FromStr for Preset
+{
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Preset, String>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "qwerty" => Ok(Preset::Qwerty),
+            "azerty" => Ok(Preset::Azerty),
+            "numpad" => Ok(Preset::Numpad),
+            _ => Err(format!("unknown keymap preset {:?}, expected qwerty, azerty or numpad", name)),
+        }
+    }
+}
+
+/// The layout of the COSMAC VIP hex keypad, row by row.
+const KEYPAD_LAYOUT : [[u8; 4]; 4] =
+[
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Assigns host keys to keypad keys. Several host keys may share a keypad key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap
+{
+    bindings : HashMap<HostKey, u8>,
+}
+
+impl Default for Keymap
+{
+    fn default() -> Keymap
+    {
+        Keymap::preset(Preset::Qwerty)
+    }
+}
+
+impl Keymap
+{
+    /// A keymap without any bindings.
+    pub fn empty() -> Keymap
+    {
+        Keymap{ bindings: HashMap::new() }
+    }
+
+    pub fn preset(preset: Preset) -> Keymap
+    {
+        let rows : [&str; 4] = match preset
+        {
+            Preset::Qwerty => ["1234", "qwer", "asdf", "zxcv"],
+            Preset::Azerty => ["1234", "azer", "qsdf", "wxcv"],
+            Preset::Numpad => ["789/", "456*", "123-", "0.\n+"],
+        };
+
+        let mut keymap = Keymap::empty();
+        for (row, keys) in rows.iter().enumerate()
+        {
+            for (column, c) in keys.chars().enumerate()
+            {
+                let host = if c == '\n' {HostKey::Enter} else {HostKey::Char(c)};
+                keymap.bind(host, KEYPAD_LAYOUT[row][column]);
+            }
+        }
+        if preset == Preset::Azerty
+        {
+            for (column, c) in "&é\"'".chars().enumerate()
+            {
+                keymap.bind(HostKey::Char(c), KEYPAD_LAYOUT[0][column]);
+            }
+        }
+        keymap
+    }
+
+    /// Binds `host` to keypad key `keypad` (0x0-0xF), replacing any previous binding of `host`.
+    pub fn bind(&mut self, host: HostKey, keypad: u8)
+    {
+        self.bindings.insert(normalize(host), keypad & 0xF);
+    }
+
+    pub fn unbind(&mut self, host: HostKey)
+    {
+        self.bindings.remove(&normalize(host));
+    }
+
+    /// The keypad key bound to `host`. Letters match regardless of case.
+    pub fn get(&self, host: HostKey) -> Option<u8>
+    {
+        self.bindings.get(&normalize(host)).copied()
+    }
+}
+
+/// Folds letters to lower case so that caps lock and shift do not change the mapping.
+fn normalize(host: HostKey) -> HostKey
+{
+    match host
+    {
+        HostKey::Char(c) => HostKey::Char(c.to_lowercase().next().unwrap_or(c)),
+        _ => host,
+    }
+}
+
+/// A syntax error in a keymap config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeymapError
+{
+    pub line : usize,
+    pub message : String,
+}
+
+impl fmt::Display for KeymapError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+/// One block of a config file: an optional preset followed by individual bindings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Layer
+{
+    preset : Option<Preset>,
+    bindings : Vec<(HostKey, Option<u8>)>,
+}
+
+impl Layer
+{
+    fn apply(&self, keymap: &mut Keymap)
+    {
+        if let Some(preset) = self.preset
+        {
+            *keymap = Keymap::preset(preset);
+        }
+        for (host, keypad) in &self.bindings
+        {
+            match keypad
+            {
+                Some(keypad) => keymap.bind(*host, *keypad),
+                None => keymap.unbind(*host),
+            }
+        }
+    }
+}
+
+/// A parsed keymap config file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeymapConfig
+{
+    global : Layer,
+    roms : HashMap<String, Layer>,
+}
+
+impl FromStr for KeymapConfig
+{
+    type Err = KeymapError;
+
+    fn from_str(text: &str) -> Result<KeymapConfig, KeymapError>
+    {
+        let mut config = KeymapConfig::default();
+        let mut section : Option<String> = None;
+
+        for (index, raw_line) in text.lines().enumerate()
+        {
+            let error = |message: String| KeymapError{ line: index + 1, message };
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            if line.is_empty()
+            {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[')
+            {
+                let name = header.strip_suffix(']').ok_or_else(|| error("unterminated section header".to_string()))?;
+                section = Some(name.trim().to_string());
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=')
+            {
+                Some((key, value)) if !key.trim().is_empty() && !value.trim().is_empty() => (key.trim(), value.trim()),
+                _ => return Err(error(format!("expected `key = value`, found {:?}", line))),
+            };
+            let layer = match &section
+            {
+                Some(rom) => config.roms.entry(rom.clone()).or_default(),
+                None => &mut config.global,
+            };
+
+            if key == "preset"
+            {
+                layer.preset = Some(value.parse().map_err(error)?);
+                continue;
+            }
+            if key.eq_ignore_ascii_case("backspace")
+            {
+                return Err(error("backspace is reserved by the frontend and cannot be bound".to_string()));
+            }
+            let host = parse_host_key(key).ok_or_else(|| error(format!("unknown host key {:?}", key)))?;
+            let keypad = parse_keypad_key(value).ok_or_else(|| error(format!("invalid keypad key {:?}, expected 0-F or none", value)))?;
+            layer.bindings.push((host, keypad));
+        }
+        Ok(config)
+    }
+}
+
+impl KeymapConfig
+{
+    /// Builds the keymap for the ROM with file name `rom`, starting from `base`.
+    pub fn keymap_for(&self, base: Keymap, rom: Option<&str>) -> Keymap
+    {
+        let mut keymap = base;
+        self.global.apply(&mut keymap);
+        if let Some(layer) = rom.and_then(|rom| self.roms.get(rom))
+        {
+            layer.apply(&mut keymap);
+        }
+        keymap
+    }
+}
+
+fn parse_host_key(name: &str) -> Option<HostKey>
+{
+    let key = match name.to_ascii_lowercase().as_str()
+    {
+        "up" => HostKey::Up,
+        "down" => HostKey::Down,
+        "left" => HostKey::Left,
+        "right" => HostKey::Right,
+        "enter" => HostKey::Enter,
+        "space" => HostKey::Char(' '),
+        _ =>
+        {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next())
+            {
+                (Some(c), None) => HostKey::Char(c),
+                _ => return None,
+            }
+        },
+    };
+    Some(key)
+}
+
+fn parse_keypad_key(value: &str) -> Option<Option<u8>>
+{
+    if value.eq_ignore_ascii_case("none")
+    {
+        return Some(None);
+    }
+    let digits = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value);
+    match u8::from_str_radix(digits, 16)
+    {
+        Ok(key) if key <= 0xF => Some(Some(key)),
+        _ => None,
+    }
+}
@@ -0,0 +1,70 @@
+//! A CHIP-8 interpreter core.
+//!
+//! The [`Chip`] type holds the complete machine state (memory, registers, timers, stack,
+//! framebuffer and keypad) and executes one instruction per call to [`Chip::emulate_cycle`].
+//! The timers count down separately at 60 Hz through [`Chip::tick_timers`]; the
+//! [`scheduler`] module takes care of interleaving the two.
+//! Frontends drive the interpreter by loading a ROM, feeding key presses and reading the
+//! framebuffer back out:
+//!
+//! ```no_run
+//! use chip_8::{Chip, Chip8Error};
+//!
+//! fn run() -> Result<(), Chip8Error>
+//! {
+//!     let mut chip = Chip::new();
+//!     chip.load_rom("roms/pong.ch8")?;
+//!     loop
+//!     {
+//!         chip.emulate_cycle()?;
+//!         let _pixels = chip.texture();
+//!     }
+//! }
+//! ```
+//!
+//! Opcodes are decoded into [`Instruction`] values by a single table that the interpreter
+//! and the [`disasm`] module share.
+//!
+//! Faults in the running program, such as unknown opcodes or stack overflows, are reported
+//! as [`Chip8Error`] values instead of aborting the process.
+
+pub mod asm;
+pub mod audio;
+pub mod capture;
+mod chip;
+pub mod debugger;
+pub mod disasm;
+pub mod dump;
+mod error;
+mod font;
+pub mod harness;
+mod instruction;
+pub mod keymap;
+mod literal;
+pub mod movie;
+mod opcode;
+mod palette;
+pub mod profile;
+mod quirks;
+mod random;
+pub mod rewind;
+pub mod savestate;
+pub mod scheduler;
+pub mod screenshot;
+pub mod terminal;
+pub mod trace;
+mod variant;
+
+pub use chip::{
+    AccessKind, Chip, MemoryAccess, BIG_FONT_ADDRESS, DEFAULT_PITCH, FONT_ADDRESS, HIRES_SCREEN_HEIGHT,
+    HIRES_SCREEN_WIDTH, HIRES_TEXTURE_SIZE, MEMORY_SIZE, PROGRAM_START, SCREEN_HEIGHT, SCREEN_WIDTH,
+    TEXTURE_SIZE, XO_MEMORY_SIZE,
+};
+pub use error::Chip8Error;
+pub use font::{BIG_FONT_SET, FONT_SET};
+pub use instruction::Instruction;
+pub use opcode::OppCodeData;
+pub use palette::{Palette, Rgb};
+pub use quirks::Quirks;
+pub use random::{Random, RandomKind};
+pub use variant::Variant;
@@ -0,0 +1,49 @@
+//! Number literals, shared by the [assembler](crate::asm) and the expressions of the
+//! [debugger](crate::debugger) so that a number means the same in both.
+
+/// Reads the number literal at the start of `text`: decimal, hex with a `0x`, `$` or `#`
+/// prefix, binary with `0b`, or a character in single quotes. Returns the value and the
+/// number of bytes the literal takes, or `None` if `text` does not start with one.
+/// A `$` or `#` on its own is not a literal.
+pub(crate) fn read_number(text: &str) -> Option<Result<(i64, usize), String>>
+{
+    let word_end = |from: usize| text[from..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .map_or(text.len(), |end| from + end);
+
+    let (digits, radix, end) = match text.chars().next()?
+    {
+        '$' | '#' =>
+        {
+            let end = word_end(1);
+            if end == 1
+            {
+                return None;
+            }
+            (&text[1..end], 16, end)
+        },
+        '\'' =>
+        {
+            let mut chars = text[1..].chars();
+            return match (chars.next(), chars.next())
+            {
+                (Some(c), Some('\'')) if c.is_ascii() => Some(Ok((c as i64, 3))),
+                _ => Some(Err(format!("invalid character literal in {:?}", text))),
+            };
+        },
+        c if c.is_ascii_digit() =>
+        {
+            let end = word_end(0);
+            let word = &text[..end];
+            match word.get(..2)
+            {
+                Some("0x" | "0X") => (&word[2..], 16, end),
+                Some("0b" | "0B") => (&word[2..], 2, end),
+                _ => (word, 10, end),
+            }
+        },
+        _ => return None,
+    };
+    let value = i64::from_str_radix(digits, radix).map_err(|_| format!("invalid number {:?}", &text[..end]));
+    Some(value.map(|value| (value, end)))
+}
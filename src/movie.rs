@@ -0,0 +1,256 @@
+//! Input movies: a recording of a session that replays exactly.
+//!
+//! Everything a running [`Chip`] does follows from its state and the keypad, as long as the
+//! random numbers come from its own [`Random`](crate::Random) generator. A [`Movie`] therefore
+//! only needs the state at the start, the instruction rate and the keypad at the start of
+//! every 60 Hz frame. It also keeps a hash of the final state, so a replay can tell whether
+//! it reproduced the session:
+//!
+//! ```
+//! use chip_8::movie::Recorder;
+//! use chip_8::scheduler::{ManualClock, Scheduler};
+//! use chip_8::Chip;
+//!
+//! let mut chip = Chip::new();
+//! // 0x200: LD V0, K, 0x202: JP 0x200
+//! chip.load_rom_bytes(&[0xF0, 0x0A, 0x12, 0x00]).unwrap();
+//!
+//! let mut scheduler = Scheduler::new(ManualClock::new(), 10);
+//! let mut recorder = Recorder::new(&chip, 10);
+//! for frame in 0..30
+//! {
+//!     chip.set_key(0x5, (10..15).contains(&frame));
+//!     recorder.record_frame(&chip);
+//!     scheduler.run_frame(&mut chip).unwrap();
+//! }
+//! let movie = recorder.finish(&chip, scheduler.cycles(), None);
+//!
+//! let replay = movie.replay().unwrap();
+//! assert!(replay.matches(&movie));
+//! assert_eq!(replay.chip.registers()[0], 0x5);
+//! ```
+//!
+//! Movie files use the container of [save states](crate::savestate) with the magic `C8MV`.
+
+use crate::chip::Chip;
+use crate::error::Chip8Error;
+use crate::savestate::{crc32, StateReader, StateWriter};
+use crate::scheduler::{ManualClock, Scheduler};
+
+/// The movie version this crate writes and reads.
+pub const MOVIE_VERSION : u16 = 1;
+/// The oldest reader version that can replay the movies this crate writes.
+pub const MOVIE_MIN_READER_VERSION : u16 = 1;
+
+const MAGIC : &[u8; 4] = b"C8MV";
+/// The longest movie that loads, a day at 60 frames per second. The keypad is stored in
+/// runs, so without a limit a few bytes could ask for gigabytes of frames.
+const MAX_FRAMES : u64 = 24 * 60 * 60 * 60;
+
+/// A hash of the complete state of `chip`, used to compare the end of a replay with the
+/// end of the recording.
+pub fn state_hash(chip: &Chip) -> u32
+{
+    crc32(&chip.save_state())
+}
+
+/// A recorded session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie
+{
+    pub instructions_per_frame : u32,
+    /// The [save state](Chip::save_state) the recording started from.
+    pub start_state : Vec<u8>,
+    /// The keypad at the start of every frame, see [`Chip::key_mask`].
+    pub frames : Vec<u16>,
+    /// Number of instructions executed during the recording.
+    pub cycles : u64,
+    /// [`state_hash`] of the machine at the end of the recording.
+    pub final_hash : u32,
+    /// The error the recording ended with, if the program faulted.
+    pub error : Option<String>,
+}
+
+/// The outcome of [`Movie::replay`].
+pub struct Replay
+{
+    pub chip : Chip,
+    pub cycles : u64,
+    pub final_hash : u32,
+    pub error : Option<Chip8Error>,
+}
+
+impl Replay
+{
+    /// Whether the replay ended exactly like the recording of `movie`.
+    pub fn matches(&self, movie: &Movie) -> bool
+    {
+        self.cycles == movie.cycles
+            && self.final_hash == movie.final_hash
+            && self.error.as_ref().map(|error| error.to_string()) == movie.error
+    }
+}
+
+impl Movie
+{
+    /// Restores the start state and runs the recorded number of instructions, feeding in
+    /// the recorded keypad. Fails only if the start state cannot be loaded; a replay that
+    /// goes differently, including one that faults early, shows in [`Replay::matches`].
+    pub fn replay(&self) -> Result<Replay, Chip8Error>
+    {
+        let mut chip = Chip::new();
+        chip.load_state(&self.start_state)?;
+        let mut scheduler = Scheduler::new(ManualClock::new(), self.instructions_per_frame);
+
+        let mut next_frame = 0;
+        let mut error = None;
+        loop
+        {
+            // The keypad changes at the start of every frame, including one the recording
+            // ended on before executing anything.
+            if scheduler.frames() == next_frame
+            {
+                if let Some(mask) = self.frames.get(next_frame as usize)
+                {
+                    chip.set_key_mask(*mask);
+                }
+                next_frame += 1;
+            }
+            // A recording that ended with an error stopped right before the failed instruction,
+            // which the replay executes too. Replays that run past the end went wrong.
+            if scheduler.cycles() == self.cycles && self.error.is_none() || scheduler.cycles() > self.cycles
+            {
+                break;
+            }
+            if let Err(fault) = scheduler.step(&mut chip)
+            {
+                error = Some(fault);
+                break;
+            }
+        }
+
+        Ok(Replay{
+            final_hash: state_hash(&chip),
+            cycles: scheduler.cycles(),
+            chip,
+            error,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut writer = StateWriter::with_format(MAGIC, MOVIE_VERSION, MOVIE_MIN_READER_VERSION);
+        writer.chunk(b"INFO", |chunk| {
+            chunk.u32(self.instructions_per_frame);
+            chunk.u64(self.cycles);
+            chunk.u32(self.final_hash);
+            chunk.blob(self.error.as_deref().unwrap_or("").as_bytes());
+        });
+        writer.chunk(b"STRT", |chunk| chunk.blob(&self.start_state));
+        // Runs of frames with the same keypad, as count and mask.
+        writer.chunk(b"KEYS", |chunk| {
+            let mut frames = self.frames.iter().peekable();
+            while let Some(mask) = frames.next()
+            {
+                let mut count = 1;
+                while frames.next_if_eq(&mask).is_some()
+                {
+                    count += 1;
+                }
+                chunk.u32(count);
+                chunk.u16(*mask);
+            }
+        });
+        writer.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Movie, Chip8Error>
+    {
+        Movie::parse(bytes).map_err(|reason| Chip8Error::InvalidMovie{ reason })
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Movie, String>
+    {
+        let movie = StateReader::parse_format(bytes, MAGIC, MOVIE_VERSION, "movie")?;
+
+        let mut info = movie.require(b"INFO")?;
+        let instructions_per_frame = info.u32()?;
+        let cycles = info.u64()?;
+        let final_hash = info.u32()?;
+        let error = String::from_utf8_lossy(info.blob()?).into_owned();
+        let start_state = movie.require(b"STRT")?.blob()?.to_vec();
+
+        let mut keys = movie.require(b"KEYS")?;
+        let mut runs = Vec::new();
+        let mut total : u64 = 0;
+        while !keys.is_empty()
+        {
+            let count = keys.u32()?;
+            let mask = keys.u16()?;
+            total += count as u64;
+            if total > MAX_FRAMES
+            {
+                return Err(format!("the movie is longer than {} frames", MAX_FRAMES));
+            }
+            runs.push((count, mask));
+        }
+        let mut frames = Vec::with_capacity(total as usize);
+        for (count, mask) in runs
+        {
+            frames.extend(std::iter::repeat_n(mask, count as usize));
+        }
+
+        Ok(Movie{
+            instructions_per_frame,
+            start_state,
+            frames,
+            cycles,
+            final_hash,
+            error: if error.is_empty() {None} else {Some(error)},
+        })
+    }
+}
+
+/// Records a session into a [`Movie`].
+pub struct Recorder
+{
+    instructions_per_frame : u32,
+    start_state : Vec<u8>,
+    frames : Vec<u16>,
+}
+
+impl Recorder
+{
+    /// Starts recording from the current state of `chip`, which is about to run at
+    /// `instructions_per_frame`. Call this at the start of a frame.
+    pub fn new(chip: &Chip, instructions_per_frame: u32) -> Recorder
+    {
+        Recorder{
+            instructions_per_frame,
+            start_state: chip.save_state(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Notes the keypad. Call this at the start of every frame, after the input was applied
+    /// and before the first instruction of the frame. Between two calls every key may only
+    /// change once, so that no key press is lost.
+    pub fn record_frame(&mut self, chip: &Chip)
+    {
+        self.frames.push(chip.key_mask());
+    }
+
+    /// Ends the recording. `cycles` is the number of instructions executed since the start and
+    /// `error` the error the session ended with, if any.
+    pub fn finish(self, chip: &Chip, cycles: u64, error: Option<&Chip8Error>) -> Movie
+    {
+        Movie{
+            instructions_per_frame: self.instructions_per_frame,
+            start_state: self.start_state,
+            frames: self.frames,
+            cycles,
+            final_hash: state_hash(chip),
+            error: error.map(|error| error.to_string()),
+        }
+    }
+}
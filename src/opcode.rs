@@ -0,0 +1,42 @@
+/// The operand fields of a 16 bit opcode.
+///
+/// Opcodes are written as hex digits where `X` and `Y` name registers, `N` is a 4 bit
+/// constant, `NN` an 8 bit constant and `NNN` a 12 bit address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OppCodeData
+{
+    pub nnn : u16,
+    pub nn : u8,
+    pub n : u8,
+    pub x : u8,
+    pub y : u8,
+}
+
+impl OppCodeData
+{
+    pub fn new(oppcode: u16) -> OppCodeData
+    {
+        let nnn_ : u16 = oppcode & 0x0FFF;
+        let nn_ : u8 = (oppcode & 0x00FF) as u8;
+        let n_ : u8 = (oppcode & 0x000F) as u8;
+        let x_ : u8 = ((oppcode & 0x0F00) >> 8) as u8;
+        let y_ : u8 = ((oppcode & 0x00F0) >> 4) as u8; 
+        OppCodeData{
+            nnn: nnn_,
+            nn: nn_,
+            n: n_,
+            x: x_,
+            y: y_,
+        }
+    }
+
+    pub fn init(&mut self, oppcode: u16)
+    {
+        self.nnn = oppcode & 0x0FFF;
+        self.nn= (oppcode & 0x00FF) as u8;
+        self.n= (oppcode & 0x000F) as u8;
+        self.x= ((oppcode & 0x0F00) >> 8) as u8;
+        self.y= ((oppcode & 0x00F0) >> 4) as u8; 
+    }
+
+}
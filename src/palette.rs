@@ -0,0 +1,72 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A 24 bit color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl FromStr for Rgb
+{
+    type Err = String;
+
+    /// Parses a color written as `RRGGBB`, optionally prefixed with `#`.
+    fn from_str(text: &str) -> Result<Rgb, String>
+    {
+        let hex = text.strip_prefix('#').unwrap_or(text);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(format!("invalid color {:?}, expected RRGGBB", text));
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        Ok(Rgb(channel(0), channel(2), channel(4)))
+    }
+}
+
+impl fmt::Display for Rgb
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        write!(f, "#{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+}
+
+/// The colors used to present the framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette
+{
+    pub background : Rgb,
+    /// Pixels lit in the first bit-plane, which is the only one outside of XO-CHIP.
+    pub foreground : Rgb,
+    /// Pixels lit only in the second XO-CHIP bit-plane.
+    pub plane2 : Rgb,
+    /// Pixels lit in both XO-CHIP bit-planes.
+    pub overlap : Rgb,
+}
+
+impl Default for Palette
+{
+    fn default() -> Palette
+    {
+        Palette{
+            background: Rgb(0x00, 0x00, 0x00),
+            foreground: Rgb(0xFF, 0xFF, 0xFF),
+            plane2: Rgb(0xFF, 0x66, 0x00),
+            overlap: Rgb(0x66, 0x22, 0x00),
+        }
+    }
+}
+
+impl Palette
+{
+    /// The color of a framebuffer pixel.
+    pub fn color(&self, pixel: u8) -> Rgb
+    {
+        match pixel & 0x3
+        {
+            0 => self.background,
+            1 => self.foreground,
+            2 => self.plane2,
+            _ => self.overlap,
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Execution profiles: where a program spends its instructions.
+//!
+//! A [`Profiler`] is handed to the machine with [`Chip::set_profiler`] and counts every
+//! executed instruction twice: by its address, for a list of hot spots, and by the chain of
+//! subroutines that were active when it ran, following 0x2NNN calls and 0x00EE returns.
+//! The second count gives each subroutine the instructions it executed itself and those of
+//! everything it called.
+//!
+//! [`Profiler::report`] renders both as a table, and [`Profiler::folded`] writes the call
+//! chains in the folded format of flamegraph tools, one chain per line with the number of
+//! instructions executed in it:
+//!
+//! ```text
+//! main 1200
+//! main;sub_0240 3400
+//! main;sub_0240;sub_0300 800
+//! ```
+//!
+//! ```
+//! use chip_8::profile::Profiler;
+//! use chip_8::Chip;
+//!
+//! let mut chip = Chip::new();
+//! // 0x200: CALL 0x206, 0x202: JP 0x200, 0x206: ADD V0, 1, 0x208: RET
+//! chip.load_rom_bytes(&[0x22, 0x06, 0x12, 0x00, 0x00, 0x00, 0x70, 0x01, 0x00, 0xEE]).unwrap();
+//! chip.set_profiler(Some(Profiler::new()));
+//! for _ in 0..40
+//! {
+//!     chip.emulate_cycle().unwrap();
+//! }
+//!
+//! let profiler = chip.profiler().unwrap();
+//! assert_eq!(profiler.count(0x206), 10);
+//! assert_eq!(profiler.folded(), "main 20\nmain;sub_0206 20\n");
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::chip::{Chip, XO_MEMORY_SIZE};
+use crate::instruction::Instruction;
+
+/// Instructions executed at one address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotSpot
+{
+    pub address : u16,
+    pub count : u64,
+}
+
+/// The instructions attributed to one subroutine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubroutineProfile
+{
+    /// The address the subroutine was called at.
+    pub entry : u16,
+    pub calls : u64,
+    /// Instructions executed in the subroutine itself, including its return.
+    pub self_count : u64,
+    /// Instructions executed in the subroutine and everything it called. Recursive calls
+    /// are counted once.
+    pub total_count : u64,
+}
+
+/// One chain of active subroutines, a node of the call tree.
+#[derive(Debug, Clone)]
+struct Frame
+{
+    /// The entry address of the innermost subroutine; unused for the top level.
+    entry : u16,
+    parent : usize,
+    children : Vec<usize>,
+    calls : u64,
+    count : u64,
+}
+
+/// Counts the instructions a machine executes, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Profiler
+{
+    /// Executions per address.
+    counts : Vec<u64>,
+    /// The call tree; the first frame is the top level, outside of any subroutine.
+    frames : Vec<Frame>,
+    current : usize,
+    total : u64,
+}
+
+impl Default for Profiler
+{
+    fn default() -> Profiler
+    {
+        Profiler::new()
+    }
+}
+
+impl Profiler
+{
+    pub fn new() -> Profiler
+    {
+        Profiler{
+            counts: vec![0; XO_MEMORY_SIZE],
+            frames: vec![Frame{ entry: 0, parent: 0, children: Vec::new(), calls: 0, count: 0 }],
+            current: 0,
+            total: 0,
+        }
+    }
+
+    /// Number of instructions executed since profiling started.
+    pub fn total(&self) -> u64
+    {
+        self.total
+    }
+
+    /// Number of instructions executed at `address`.
+    pub fn count(&self, address: u16) -> u64
+    {
+        self.counts[address as usize]
+    }
+
+    /// Every address that executed an instruction, the most executed first.
+    pub fn hot_spots(&self) -> Vec<HotSpot>
+    {
+        let mut hot_spots : Vec<HotSpot> = self.counts.iter().enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(address, count)| HotSpot{ address: address as u16, count: *count })
+            .collect();
+        hot_spots.sort_by(|a, b| b.count.cmp(&a.count).then(a.address.cmp(&b.address)));
+        hot_spots
+    }
+
+    /// Every subroutine that was called, the one with the highest total first.
+    pub fn subroutines(&self) -> Vec<SubroutineProfile>
+    {
+        let mut subroutines = BTreeMap::new();
+        for (index, frame) in self.frames.iter().enumerate().skip(1)
+        {
+            let profile = subroutines.entry(frame.entry)
+                .or_insert(SubroutineProfile{ entry: frame.entry, calls: 0, self_count: 0, total_count: 0 });
+            profile.calls += frame.calls;
+            profile.self_count += frame.count;
+
+            // Credits the frame's instructions to every subroutine in its chain, once each.
+            let mut chain = self.chain(index);
+            chain.sort_unstable();
+            chain.dedup();
+            for entry in chain
+            {
+                subroutines.entry(entry)
+                    .or_insert(SubroutineProfile{ entry, calls: 0, self_count: 0, total_count: 0 })
+                    .total_count += frame.count;
+            }
+        }
+        let mut subroutines : Vec<SubroutineProfile> = subroutines.into_values().collect();
+        subroutines.sort_by(|a, b| b.total_count.cmp(&a.total_count).then(a.entry.cmp(&b.entry)));
+        subroutines
+    }
+
+    /// Renders the `limit` hottest addresses with their disassembly from `chip`, followed by
+    /// the subroutines.
+    pub fn report(&self, chip: &Chip, limit: usize) -> String
+    {
+        let share = |count: u64| 100.0 * count as f64 / self.total.max(1) as f64;
+        let mut out = String::new();
+        let hot_spots = self.hot_spots();
+        writeln!(out, "{} instructions at {} addresses", self.total, hot_spots.len()).unwrap();
+
+        writeln!(out, "\nhot spots:").unwrap();
+        writeln!(out, "address        count   share  instruction").unwrap();
+        for hot_spot in hot_spots.iter().take(limit)
+        {
+            writeln!(out, "{:04X}    {:>12}  {:>5.1}%  {}",
+                hot_spot.address, hot_spot.count, share(hot_spot.count), disassemble(chip, hot_spot.address)).unwrap();
+        }
+        if hot_spots.len() > limit
+        {
+            writeln!(out, "and {} more addresses", hot_spots.len() - limit).unwrap();
+        }
+
+        writeln!(out, "\nsubroutines:").unwrap();
+        writeln!(out, "name             calls          self         total   share").unwrap();
+        writeln!(out, "{:<8} {:>13} {:>13} {:>13}  {:>5.1}%", "main", "-", self.frames[0].count, self.total, 100.0).unwrap();
+        for subroutine in self.subroutines()
+        {
+            writeln!(out, "{:<8} {:>13} {:>13} {:>13}  {:>5.1}%",
+                name(subroutine.entry), subroutine.calls, subroutine.self_count, subroutine.total_count,
+                share(subroutine.total_count)).unwrap();
+        }
+        out
+    }
+
+    /// Renders the call chains in the folded stack format, sorted by chain. Chains that did
+    /// not execute anything themselves are left out.
+    pub fn folded(&self) -> String
+    {
+        let mut lines : Vec<String> = self.frames.iter().enumerate()
+            .filter(|(_, frame)| frame.count > 0)
+            .map(|(index, frame)| {
+                let mut names = vec!["main".to_string()];
+                names.extend(self.chain(index).iter().rev().map(|entry| name(*entry)));
+                format!("{} {}\n", names.join(";"), frame.count)
+            })
+            .collect();
+        lines.sort();
+        lines.concat()
+    }
+
+    /// Counts the instruction at `address`, which is about to execute.
+    pub(crate) fn execute(&mut self, address: u16)
+    {
+        self.counts[address as usize] += 1;
+        self.frames[self.current].count += 1;
+        self.total += 1;
+    }
+
+    /// Takes back the count of an instruction that faulted instead of executing.
+    pub(crate) fn retract(&mut self, address: u16)
+    {
+        self.counts[address as usize] -= 1;
+        self.frames[self.current].count -= 1;
+        self.total -= 1;
+    }
+
+    /// Notes a call to the subroutine at `entry`.
+    pub(crate) fn enter(&mut self, entry: u16)
+    {
+        let current = self.current;
+        let child = self.frames[current].children.iter().copied().find(|child| self.frames[*child].entry == entry);
+        self.current = match child
+        {
+            Some(child) => child,
+            None =>
+            {
+                self.frames.push(Frame{ entry, parent: current, children: Vec::new(), calls: 0, count: 0 });
+                let child = self.frames.len() - 1;
+                self.frames[current].children.push(child);
+                child
+            },
+        };
+        self.frames[self.current].calls += 1;
+    }
+
+    /// Notes a return. Returns from calls made before profiling started are ignored.
+    pub(crate) fn leave(&mut self)
+    {
+        self.current = self.frames[self.current].parent;
+    }
+
+    /// Continues at the top level, after the machine's stack was replaced.
+    pub(crate) fn reset_stack(&mut self)
+    {
+        self.current = 0;
+    }
+
+    /// The entry addresses of the subroutines active in `frame`, innermost first.
+    fn chain(&self, mut frame: usize) -> Vec<u16>
+    {
+        let mut chain = Vec::new();
+        while frame != 0
+        {
+            chain.push(self.frames[frame].entry);
+            frame = self.frames[frame].parent;
+        }
+        chain
+    }
+}
+
+/// The name of the subroutine at `entry` in reports and folded stacks.
+fn name(entry: u16) -> String
+{
+    format!("sub_{:04X}", entry)
+}
+
+/// Disassembles the instruction at `address` in the machine's current memory.
+fn disassemble(chip: &Chip, address: u16) -> String
+{
+    let word = |address: usize| chip.memory().get(address..address + 2).map_or(0, |pair| (pair[0] as u16) << 8 | pair[1] as u16);
+    let opcode = word(address as usize);
+    match Instruction::decode(opcode, word(address as usize + 2), chip.variant())
+    {
+        Some(instruction) => instruction.to_string(),
+        None => format!("DW 0x{:04X}", opcode),
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::variant::Variant;
+
+/// Behaviors that differ between CHIP-8 interpreters.
+///
+/// Programs were written against whichever interpreter their authors had, so running them
+/// correctly means picking the matching set of quirks. [`Quirks::default`] turns all of them
+/// off, which is the behavior this crate always had; the named presets follow the original
+/// interpreters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quirks
+{
+    /// 0x8XY6 and 0x8XYE shift register y and store the result in register x, instead of
+    /// shifting register x in place.
+    pub shift_uses_vy : bool,
+    /// 0xFX55 and 0xFX65 leave the index register pointing past the last register accessed.
+    pub load_store_increments_i : bool,
+    /// 0xBNNN jumps to NNN + register x, where x is the highest nibble of NNN (read as BXNN),
+    /// instead of NNN + register 0.
+    pub jump_uses_vx : bool,
+    /// 0x8XY1, 0x8XY2 and 0x8XY3 reset register 0xF to 0.
+    pub vf_reset : bool,
+    /// Sprites are cut off at the screen edges instead of wrapping around to the opposite side.
+    /// Sprites that start off screen wrap in either case.
+    pub clip_sprites : bool,
+    /// 0xDXYN waits for the next 60 Hz frame after drawing, like the COSMAC VIP did.
+    pub display_wait : bool,
+}
+
+impl Quirks
+{
+    /// The original interpreter on the RCA COSMAC VIP.
+    pub fn cosmac_vip() -> Quirks
+    {
+        Quirks{
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_uses_vx: false,
+            vf_reset: true,
+            clip_sprites: true,
+            display_wait: true,
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators.
+    pub fn chip48() -> Quirks
+    {
+        Quirks{
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_uses_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+        }
+    }
+
+    /// SUPER-CHIP 1.1, the successor of CHIP-48.
+    pub fn superchip() -> Quirks
+    {
+        Quirks::chip48()
+    }
+
+    /// XO-CHIP as implemented by Octo, which keeps the original shift and load/store
+    /// behavior but drops clipping, the vF reset and the display wait.
+    pub fn xochip() -> Quirks
+    {
+        Quirks{
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            ..Quirks::default()
+        }
+    }
+
+    /// The behavior programs written for `variant` usually expect.
+    pub fn for_variant(variant: Variant) -> Quirks
+    {
+        match variant
+        {
+            Variant::Chip8 => Quirks::default(),
+            Variant::SuperChip => Quirks::superchip(),
+            Variant::XoChip => Quirks::xochip(),
+        }
+    }
+
+    /// The quirks as bit field in declaration order, for save states.
+    pub(crate) fn to_bits(self) -> u8
+    {
+        [
+            self.shift_uses_vy,
+            self.load_store_increments_i,
+            self.jump_uses_vx,
+            self.vf_reset,
+            self.clip_sprites,
+            self.display_wait,
+        ].iter().enumerate().fold(0, |bits, (bit, on)| bits | (*on as u8) << bit)
+    }
+
+    /// The inverse of [`to_bits`](Quirks::to_bits). Unknown bits are ignored.
+    pub(crate) fn from_bits(bits: u8) -> Quirks
+    {
+        let on = |bit: u8| bits & 1 << bit != 0;
+        Quirks{
+            shift_uses_vy: on(0),
+            load_store_increments_i: on(1),
+            jump_uses_vx: on(2),
+            vf_reset: on(3),
+            clip_sprites: on(4),
+            display_wait: on(5),
+        }
+    }
+
+    /// Turns the quirk called `name` on or off. The names are the ones accepted by the
+    /// command line: `shift-vy`, `load-store-i`, `jump-vx`, `vf-reset`, `clip` and `display-wait`.
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<(), String>
+    {
+        let quirk = match name
+        {
+            "shift-vy" => &mut self.shift_uses_vy,
+            "load-store-i" => &mut self.load_store_increments_i,
+            "jump-vx" => &mut self.jump_uses_vx,
+            "vf-reset" => &mut self.vf_reset,
+            "clip" => &mut self.clip_sprites,
+            "display-wait" => &mut self.display_wait,
+            _ => return Err(format!("unknown quirk {:?}", name)),
+        };
+        *quirk = enabled;
+        Ok(())
+    }
+}
+
+impl FromStr for Quirks
+{
+    type Err = String;
+
+    /// Looks up a preset by name: `default`, `vip`, `chip48`, `schip` or `xochip`.
+    fn from_str(name: &str) -> Result<Quirks, String>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "default" => Ok(Quirks::default()),
+            "vip" | "cosmac-vip" => Ok(Quirks::cosmac_vip()),
+            "chip48" | "chip-48" => Ok(Quirks::chip48()),
+            "schip" | "superchip" | "super-chip" => Ok(Quirks::superchip()),
+            "xochip" | "xo-chip" => Ok(Quirks::xochip()),
+            _ => Err(format!("unknown quirks preset {:?}, expected default, vip, chip48, schip or xochip", name)),
+        }
+    }
+}
+
+impl fmt::Display for Quirks
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let flags = [
+            ("shift-vy", self.shift_uses_vy),
+            ("load-store-i", self.load_store_increments_i),
+            ("jump-vx", self.jump_uses_vx),
+            ("vf-reset", self.vf_reset),
+            ("clip", self.clip_sprites),
+            ("display-wait", self.display_wait),
+        ];
+        let enabled : Vec<&str> = flags.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect();
+        if enabled.is_empty()
+        {
+            write!(f, "none")
+        }
+        else
+        {
+            write!(f, "{}", enabled.join(","))
+        }
+    }
+}
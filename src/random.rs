@@ -0,0 +1,128 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// The algorithms a [`Random`] can use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RandomKind
+{
+    /// xorshift64*, a fast generator with a 64 bit state and good statistical quality.
+    #[default]
+    Xorshift,
+    /// A model of the COSMAC VIP interpreter's routine, which kept a 16 bit seed and stirred
+    /// it with bytes of its own code in the first pages of memory. Here those pages hold the
+    /// fonts, so like on the VIP the sequence is short and depends on what is in memory.
+    CosmacVip,
+}
+
+impl FromStr for RandomKind
+{
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<RandomKind, String>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "xorshift" => Ok(RandomKind::Xorshift),
+            "vip" | "cosmac-vip" => Ok(RandomKind::CosmacVip),
+            _ => Err(format!("unknown random number generator {:?}, expected xorshift or vip", name)),
+        }
+    }
+}
+
+impl fmt::Display for RandomKind
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            RandomKind::Xorshift => write!(f, "xorshift"),
+            RandomKind::CosmacVip => write!(f, "vip"),
+        }
+    }
+}
+
+/// The source of the random bytes of 0xCXNN. The same kind and seed always produce the
+/// same sequence, so runs of games that use random numbers can be reproduced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Random
+{
+    kind : RandomKind,
+    state : u64,
+}
+
+impl Random
+{
+    /// Creates a generator that starts from `seed`.
+    pub fn new(kind: RandomKind, seed: u64) -> Random
+    {
+        let state = match kind
+        {
+            // Spread the seed over all bits with splitmix64; xorshift must not start at 0.
+            RandomKind::Xorshift =>
+            {
+                let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                z ^= z >> 31;
+                if z == 0 {1} else {z}
+            },
+            RandomKind::CosmacVip => seed & 0xFFFF,
+        };
+        Random{ kind, state }
+    }
+
+    /// Creates a generator with a seed from the operating system, different on every run.
+    pub fn from_entropy(kind: RandomKind) -> Random
+    {
+        Random::new(kind, rand::random())
+    }
+
+    pub fn kind(&self) -> RandomKind
+    {
+        self.kind
+    }
+
+    /// The next random byte. `memory` is the machine's memory, which the COSMAC VIP
+    /// generator reads from.
+    pub fn next_byte(&mut self, memory: &[u8]) -> u8
+    {
+        match self.kind
+        {
+            RandomKind::Xorshift =>
+            {
+                self.state ^= self.state >> 12;
+                self.state ^= self.state << 25;
+                self.state ^= self.state >> 27;
+                (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+            },
+            RandomKind::CosmacVip =>
+            {
+                // Step the low byte through the first page, add the byte found there to the
+                // high byte and rotate it, which becomes the random number.
+                let [low, high] = (self.state as u16).to_le_bytes();
+                let low = low.wrapping_add(1);
+                let high = high.wrapping_add(memory[low as usize]).rotate_right(1) ^ low;
+                self.state = u16::from_le_bytes([low, high]) as u64;
+                high
+            },
+        }
+    }
+
+    /// The internal state, for save states.
+    pub(crate) fn state(&self) -> u64
+    {
+        self.state
+    }
+
+    /// Restores a generator from its kind and internal state.
+    pub(crate) fn from_state(kind: RandomKind, state: u64) -> Random
+    {
+        let state = match kind
+        {
+            RandomKind::Xorshift if state == 0 => 1,
+            RandomKind::Xorshift => state,
+            RandomKind::CosmacVip => state & 0xFFFF,
+        };
+        Random{ kind, state }
+    }
+}
@@ -0,0 +1,311 @@
+//! Rewinding: a bounded history of the machine state, one snapshot per frame.
+//!
+//! [`Rewind::capture`] records a [save state](Chip::save_state) after every frame. Only the
+//! newest snapshot is kept whole; each older one is stored as the difference to its successor,
+//! XORed and run-length encoded, so frames that change little cost only a few bytes. When the
+//! history outgrows its memory budget the oldest frames are dropped.
+//!
+//! ```
+//! use chip_8::rewind::Rewind;
+//! use chip_8::Chip;
+//!
+//! let mut chip = Chip::new();
+//! // 0x200: ADD V0, 1, 0x202: JP 0x200
+//! chip.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+//!
+//! let mut rewind = Rewind::new(1 << 20);
+//! for _ in 0..3
+//! {
+//!     chip.emulate_cycle().unwrap();
+//!     chip.emulate_cycle().unwrap();
+//!     rewind.capture(&chip);
+//! }
+//! assert_eq!(chip.registers()[0], 3);
+//! assert!(rewind.step_back(&mut chip));
+//! assert_eq!(chip.registers()[0], 2);
+//! ```
+
+use std::collections::VecDeque;
+
+use crate::chip::Chip;
+
+/// The default memory budget of the CLI, in bytes.
+pub const DEFAULT_BUDGET : usize = 16 << 20;
+
+/// A history of snapshots that can be stepped backwards.
+pub struct Rewind
+{
+    budget : usize,
+    /// The snapshot of the most recent frame, in full.
+    latest : Option<Vec<u8>>,
+    /// Older snapshots, oldest first, each encoded against the one after it.
+    deltas : VecDeque<Vec<u8>>,
+    used : usize,
+}
+
+impl Rewind
+{
+    /// Creates an empty history that uses at most about `budget` bytes.
+    pub fn new(budget: usize) -> Rewind
+    {
+        Rewind{
+            budget,
+            latest: None,
+            deltas: VecDeque::new(),
+            used: 0,
+        }
+    }
+
+    /// Records the state of `chip`, usually at the end of a frame.
+    pub fn capture(&mut self, chip: &Chip)
+    {
+        let snapshot = chip.save_state();
+        self.used += snapshot.len();
+        if let Some(previous) = self.latest.replace(snapshot)
+        {
+            self.used -= previous.len();
+            let delta = encode_delta(self.latest.as_ref().unwrap(), &previous);
+            self.used += delta.len();
+            self.deltas.push_back(delta);
+        }
+
+        while self.used > self.budget
+        {
+            match self.deltas.pop_front()
+            {
+                Some(delta) => self.used -= delta.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Restores `chip` to the frame before the latest one and forgets the latest one.
+    /// Returns false, leaving `chip` alone, if there is no earlier frame.
+    pub fn step_back(&mut self, chip: &mut Chip) -> bool
+    {
+        let (latest, delta) = match (self.latest.as_mut(), self.deltas.pop_back())
+        {
+            (Some(latest), Some(delta)) => (latest, delta),
+            _ => return false,
+        };
+        self.used -= latest.len() + delta.len();
+        apply_delta(latest, &delta);
+        self.used += latest.len();
+        chip.load_state(latest).expect("rewind snapshots are valid save states");
+        true
+    }
+
+    /// Number of frames [`step_back`](Rewind::step_back) can go back.
+    pub fn len(&self) -> usize
+    {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.deltas.is_empty()
+    }
+
+    /// Bytes currently used by the snapshots.
+    pub fn memory_used(&self) -> usize
+    {
+        self.used
+    }
+
+    /// Forgets all frames.
+    pub fn clear(&mut self)
+    {
+        self.latest = None;
+        self.deltas.clear();
+        self.used = 0;
+    }
+}
+
+/// Encodes `target` relative to `base`: the length of `target` followed by runs of
+/// unchanged bytes and runs of bytes XORed with `base`, all lengths as LEB128.
+fn encode_delta(base: &[u8], target: &[u8]) -> Vec<u8>
+{
+    let xor = |at: usize| target[at] ^ base.get(at).copied().unwrap_or(0);
+    let mut delta = Vec::new();
+    write_length(&mut delta, target.len());
+
+    let mut position = 0;
+    while position < target.len()
+    {
+        let start = position;
+        while position < target.len() && xor(position) == 0
+        {
+            position += 1;
+        }
+        write_length(&mut delta, position - start);
+
+        let start = position;
+        while position < target.len() && xor(position) != 0
+        {
+            position += 1;
+        }
+        write_length(&mut delta, position - start);
+        delta.extend((start..position).map(xor));
+    }
+    delta
+}
+
+/// Turns `base` into the target `delta` was encoded for.
+fn apply_delta(base: &mut Vec<u8>, delta: &[u8])
+{
+    let mut input = delta;
+    let length = read_length(&mut input);
+    base.resize(length, 0);
+
+    let mut position = 0;
+    while position < length
+    {
+        position += read_length(&mut input);
+        let changed = read_length(&mut input);
+        for (byte, xor) in base[position..position + changed].iter_mut().zip(&input[..changed])
+        {
+            *byte ^= xor;
+        }
+        input = &input[changed..];
+        position += changed;
+    }
+}
+
+/// Appends `length` in LEB128.
+pub(crate) fn write_length(out: &mut Vec<u8>, mut length: usize)
+{
+    while length >= 0x80
+    {
+        out.push(length as u8 | 0x80);
+        length >>= 7;
+    }
+    out.push(length as u8);
+}
+
+/// Reads a LEB128 length from the front of `input`.
+pub(crate) fn read_length(input: &mut &[u8]) -> usize
+{
+    let mut length = 0;
+    let mut shift = 0;
+    while let Some((byte, rest)) = input.split_first()
+    {
+        *input = rest;
+        length |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0
+        {
+            break;
+        }
+        shift += 7;
+    }
+    length
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn round_trip(base: &[u8], target: &[u8]) -> Vec<u8>
+    {
+        let delta = encode_delta(base, target);
+        let mut restored = base.to_vec();
+        apply_delta(&mut restored, &delta);
+        assert_eq!(restored, target);
+        delta
+    }
+
+    #[test]
+    fn identical_buffers_encode_to_a_single_run()
+    {
+        let state : Vec<u8> = (0..=255).cycle().take(1000).collect();
+        assert_eq!(round_trip(&state, &state), [0xE8, 0x07, 0xE8, 0x07, 0x00]);
+        assert_eq!(round_trip(&[0; 300], &[0; 300]), [0xAC, 0x02, 0xAC, 0x02, 0x00]);
+    }
+
+    #[test]
+    fn completely_different_buffers_store_every_byte()
+    {
+        let base = [0x55; 200];
+        let target = [0xAA; 200];
+        let delta = round_trip(&base, &target);
+        assert_eq!(delta[..5], [0xC8, 0x01, 0x00, 0xC8, 0x01]);
+        assert!(delta[5..].iter().all(|byte| *byte == 0xFF));
+        assert_eq!(delta.len(), 205);
+    }
+
+    #[test]
+    fn scattered_changes_round_trip()
+    {
+        let base : Vec<u8> = (0..500).map(|i| (i * 7) as u8).collect();
+        let mut target = base.clone();
+        for i in [0, 1, 2, 100, 101, 250, 499]
+        {
+            target[i] = !target[i];
+        }
+        let delta = round_trip(&base, &target);
+        assert!(delta.len() < 30);
+    }
+
+    #[test]
+    fn lengths_may_differ()
+    {
+        round_trip(&[1, 2, 3], &[1, 2, 3, 4, 5]);
+        round_trip(&[1, 2, 3, 4, 5], &[1, 9]);
+        round_trip(&[1, 2, 3], &[]);
+        round_trip(&[], &[0, 0, 7]);
+    }
+
+    #[test]
+    fn lengths_use_leb128()
+    {
+        for (length, encoded) in [
+            (0, vec![0x00]),
+            (127, vec![0x7F]),
+            (128, vec![0x80, 0x01]),
+            (16383, vec![0xFF, 0x7F]),
+            (16384, vec![0x80, 0x80, 0x01]),
+        ]
+        {
+            let mut out = Vec::new();
+            write_length(&mut out, length);
+            assert_eq!(out, encoded);
+            let mut input = &out[..];
+            assert_eq!(read_length(&mut input), length);
+            assert!(input.is_empty());
+        }
+
+        let mut out = Vec::new();
+        write_length(&mut out, usize::MAX);
+        write_length(&mut out, 5);
+        let mut input = &out[..];
+        assert_eq!(read_length(&mut input), usize::MAX);
+        assert_eq!(read_length(&mut input), 5);
+    }
+
+    #[test]
+    fn the_budget_drops_the_oldest_frames()
+    {
+        let mut chip = Chip::new();
+        // 0x200: ADD V0, 1, 0x202: JP 0x200
+        chip.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        let state_size = chip.save_state().len();
+        let mut rewind = Rewind::new(state_size + 100);
+        for _ in 0..50
+        {
+            chip.emulate_cycle().unwrap();
+            chip.emulate_cycle().unwrap();
+            rewind.capture(&chip);
+        }
+        assert!(rewind.memory_used() <= state_size + 100);
+        assert!(rewind.len() > 1 && rewind.len() < 49);
+
+        let frames = rewind.len();
+        for back in 1..=frames
+        {
+            assert!(rewind.step_back(&mut chip));
+            assert_eq!(chip.registers()[0] as usize, 50 - back);
+        }
+        assert!(!rewind.step_back(&mut chip));
+        assert!(rewind.is_empty());
+    }
+}
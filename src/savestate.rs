@@ -0,0 +1,268 @@
+//! The container format of save states, see [`Chip::save_state`](crate::Chip::save_state).
+//!
+//! A save state is a header, a list of chunks and a checksum, all integers big endian:
+//!
+//! ```text
+//! "C8SS"            magic
+//! u16               version of the writer
+//! u16               oldest reader version that can load the state
+//! chunks            4 byte tag, u32 length, data
+//! u32               CRC-32 of everything before it
+//! ```
+//!
+//! Readers skip chunks they do not know and ignore bytes at the end of a chunk beyond the
+//! fields they know, so newer versions can add chunks and fields without breaking older
+//! readers. Only a change older readers cannot cope with raises the minimum version.
+//!
+//! [Movies](crate::movie) use the same container with their own magic and version.
+
+/// The save state version this crate writes and reads.
+pub const SAVE_STATE_VERSION : u16 = 1;
+/// The oldest reader version that can load the save states this crate writes. It stays
+/// behind [`SAVE_STATE_VERSION`] unless a change breaks older readers.
+pub const MIN_READER_VERSION : u16 = 1;
+
+const MAGIC : &[u8; 4] = b"C8SS";
+const HEADER_SIZE : usize = 8;
+const CHECKSUM_SIZE : usize = 4;
+
+/// Builds a save state, or another file in the same container, chunk by chunk.
+pub(crate) struct StateWriter
+{
+    bytes : Vec<u8>,
+}
+
+impl StateWriter
+{
+    pub fn new() -> StateWriter
+    {
+        StateWriter::with_format(MAGIC, SAVE_STATE_VERSION, MIN_READER_VERSION)
+    }
+
+    /// Starts a file with its own `magic`, written as `version`, that readers of at least
+    /// `min_reader_version` can load.
+    pub fn with_format(magic: &[u8; 4], version: u16, min_reader_version: u16) -> StateWriter
+    {
+        let mut bytes = magic.to_vec();
+        bytes.extend_from_slice(&version.to_be_bytes());
+        bytes.extend_from_slice(&min_reader_version.to_be_bytes());
+        StateWriter{ bytes }
+    }
+
+    /// Appends the chunk `tag` with the fields written by `build`.
+    pub fn chunk(&mut self, tag: &[u8; 4], build: impl FnOnce(&mut ChunkWriter))
+    {
+        let mut chunk = ChunkWriter{ data: Vec::new() };
+        build(&mut chunk);
+        self.bytes.extend_from_slice(tag);
+        self.bytes.extend_from_slice(&(chunk.data.len() as u32).to_be_bytes());
+        self.bytes.extend_from_slice(&chunk.data);
+    }
+
+    /// Appends the checksum and returns the finished save state.
+    pub fn finish(mut self) -> Vec<u8>
+    {
+        let checksum = crc32(&self.bytes);
+        self.bytes.extend_from_slice(&checksum.to_be_bytes());
+        self.bytes
+    }
+}
+
+/// The fields of one chunk.
+pub(crate) struct ChunkWriter
+{
+    data : Vec<u8>,
+}
+
+impl ChunkWriter
+{
+    pub fn u8(&mut self, value: u8)
+    {
+        self.data.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool)
+    {
+        self.data.push(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16)
+    {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32)
+    {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64)
+    {
+        self.data.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8])
+    {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// A byte string preceded by its length as u32.
+    pub fn blob(&mut self, bytes: &[u8])
+    {
+        self.data.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+        self.data.extend_from_slice(bytes);
+    }
+}
+
+/// A checked save state, split into its chunks.
+pub(crate) struct StateReader<'a>
+{
+    chunks : Vec<([u8; 4], &'a [u8])>,
+}
+
+impl<'a> StateReader<'a>
+{
+    /// Checks magic, version and checksum of a save state and splits it into chunks.
+    pub fn parse(bytes: &'a [u8]) -> Result<StateReader<'a>, String>
+    {
+        StateReader::parse_format(bytes, MAGIC, SAVE_STATE_VERSION, "save state")
+    }
+
+    /// Like [`parse`](StateReader::parse) for a file with its own `magic` and `reader_version`,
+    /// called `what` in errors.
+    pub fn parse_format(bytes: &'a [u8], magic: &[u8; 4], reader_version: u16, what: &str) -> Result<StateReader<'a>, String>
+    {
+        if bytes.len() < HEADER_SIZE + CHECKSUM_SIZE || &bytes[..4] != magic
+        {
+            return Err(format!("not a {}", what));
+        }
+        let (body, checksum) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+        if crc32(body).to_be_bytes() != checksum
+        {
+            return Err("checksum mismatch, the file is damaged".to_string());
+        }
+
+        let version = u16::from_be_bytes([body[4], body[5]]);
+        let min_version = u16::from_be_bytes([body[6], body[7]]);
+        if min_version > reader_version
+        {
+            return Err(format!("version {} needs a reader for version {} or newer, this is version {}",
+                version, min_version, reader_version));
+        }
+
+        let mut chunks = Vec::new();
+        let mut rest = &body[HEADER_SIZE..];
+        while !rest.is_empty()
+        {
+            if rest.len() < 8
+            {
+                return Err("truncated chunk header".to_string());
+            }
+            let tag = [rest[0], rest[1], rest[2], rest[3]];
+            let length = u32::from_be_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+            let data = rest[8..].get(..length).ok_or("truncated chunk")?;
+            chunks.push((tag, data));
+            rest = &rest[8 + length..];
+        }
+        Ok(StateReader{ chunks })
+    }
+
+    /// The first chunk called `tag`, if there is one.
+    pub fn chunk(&self, tag: &[u8; 4]) -> Option<ChunkReader<'a>>
+    {
+        self.chunks.iter()
+            .find(|(chunk_tag, _)| chunk_tag == tag)
+            .map(|(tag, data)| ChunkReader{ tag: *tag, data, position: 0 })
+    }
+
+    /// Like [`chunk`](StateReader::chunk), but a missing chunk is an error.
+    pub fn require(&self, tag: &[u8; 4]) -> Result<ChunkReader<'a>, String>
+    {
+        self.chunk(tag).ok_or_else(|| format!("missing chunk {}", String::from_utf8_lossy(tag)))
+    }
+}
+
+/// Reads the fields of a chunk in the order they were written.
+pub(crate) struct ChunkReader<'a>
+{
+    tag : [u8; 4],
+    data : &'a [u8],
+    position : usize,
+}
+
+impl<'a> ChunkReader<'a>
+{
+    pub fn bytes(&mut self, length: usize) -> Result<&'a [u8], String>
+    {
+        let bytes = self.data.get(self.position..self.position + length)
+            .ok_or_else(|| format!("chunk {} is too short", String::from_utf8_lossy(&self.tag)))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String>
+    {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, String>
+    {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String>
+    {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String>
+    {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String>
+    {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    /// Whether every byte of the chunk was read.
+    pub fn is_empty(&self) -> bool
+    {
+        self.position >= self.data.len()
+    }
+
+    /// Reads into `target`, which determines the number of bytes.
+    pub fn fill(&mut self, target: &mut [u8]) -> Result<(), String>
+    {
+        target.copy_from_slice(self.bytes(target.len())?);
+        Ok(())
+    }
+
+    /// A byte string written with [`ChunkWriter::blob`].
+    pub fn blob(&mut self) -> Result<&'a [u8], String>
+    {
+        let length = self.bytes(4)?;
+        let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+        self.bytes(length)
+    }
+}
+
+/// The CRC-32 used by zip and PNG.
+pub fn crc32(bytes: &[u8]) -> u32
+{
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes
+    {
+        crc ^= *byte as u32;
+        for _ in 0..8
+        {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,219 @@
+//! Frame pacing: runs a fixed number of instructions per 60 Hz frame and ticks the
+//! delay and sound timers once at the end of every frame.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use crate::chip::Chip;
+use crate::error::Chip8Error;
+
+/// Rate at which the delay and sound timers count down, in Hz.
+pub const TIMER_FREQUENCY : u32 = 60;
+
+/// A source of monotonic time, measured from an arbitrary starting point.
+pub trait Clock
+{
+    fn now(&self) -> Duration;
+}
+
+impl<C: Clock + ?Sized> Clock for &C
+{
+    fn now(&self) -> Duration
+    {
+        (**self).now()
+    }
+}
+
+/// The host's monotonic clock.
+pub struct SystemClock
+{
+    start : Instant,
+}
+
+impl Default for SystemClock
+{
+    fn default() -> SystemClock
+    {
+        SystemClock::new()
+    }
+}
+
+impl SystemClock
+{
+    pub fn new() -> SystemClock
+    {
+        SystemClock{ start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock
+{
+    fn now(&self) -> Duration
+    {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to, so frame timing can be driven deterministically.
+#[derive(Debug, Default)]
+pub struct ManualClock
+{
+    now : Cell<Duration>,
+}
+
+impl ManualClock
+{
+    pub fn new() -> ManualClock
+    {
+        ManualClock::default()
+    }
+
+    pub fn advance(&self, duration: Duration)
+    {
+        self.now.set(self.now.get() + duration);
+    }
+
+    pub fn set(&self, now: Duration)
+    {
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock
+{
+    fn now(&self) -> Duration
+    {
+        self.now.get()
+    }
+}
+
+/// How many frames the scheduler runs back to back to catch up before it gives up and
+/// drops the missed time instead.
+const MAX_CATCH_UP_FRAMES : u32 = 4;
+
+/// Divides execution into 60 Hz frames of `instructions_per_frame` instructions.
+pub struct Scheduler<C: Clock>
+{
+    clock : C,
+    instructions_per_frame : u32,
+    /// Instructions executed in the current frame so far.
+    frame_cycles : u32,
+    frames : u64,
+    cycles : u64,
+    /// Clock time at which frame 0 was due. Moved forward when missed frames are dropped.
+    start : Duration,
+}
+
+impl<C: Clock> Scheduler<C>
+{
+    pub fn new(clock: C, instructions_per_frame: u32) -> Scheduler<C>
+    {
+        let start = clock.now();
+        Scheduler{
+            clock,
+            instructions_per_frame: instructions_per_frame.max(1),
+            frame_cycles: 0,
+            frames: 0,
+            cycles: 0,
+            start,
+        }
+    }
+
+    pub fn instructions_per_frame(&self) -> u32
+    {
+        self.instructions_per_frame
+    }
+
+    pub fn set_instructions_per_frame(&mut self, instructions_per_frame: u32)
+    {
+        self.instructions_per_frame = instructions_per_frame.max(1);
+    }
+
+    /// Number of completed frames.
+    pub fn frames(&self) -> u64
+    {
+        self.frames
+    }
+
+    /// Number of executed instructions.
+    pub fn cycles(&self) -> u64
+    {
+        self.cycles
+    }
+
+    pub fn clock(&self) -> &C
+    {
+        &self.clock
+    }
+
+    /// Executes a single instruction. Completing the last instruction of a frame ticks the
+    /// timers. Returns whether a frame was completed.
+    pub fn step(&mut self, chip: &mut Chip) -> Result<bool, Chip8Error>
+    {
+        chip.emulate_cycle()?;
+        self.cycles += 1;
+        self.frame_cycles += 1;
+
+        if self.frame_cycles >= self.instructions_per_frame
+        {
+            self.frame_cycles = 0;
+            self.frames += 1;
+            chip.tick_timers();
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Executes the rest of the current frame and ticks the timers, regardless of the clock.
+    pub fn run_frame(&mut self, chip: &mut Chip) -> Result<(), Chip8Error>
+    {
+        while !self.step(chip)? {}
+        Ok(())
+    }
+
+    /// Lets the time of one frame pass without executing anything, for instance while the
+    /// frontend rewinds. The frame counter stays as it is and the next frame starts fresh.
+    pub fn skip_frame(&mut self)
+    {
+        self.start += frame_offset(self.frames + 1) - frame_offset(self.frames);
+        self.frame_cycles = 0;
+    }
+
+    /// The number of frames whose start time has passed according to the clock.
+    ///
+    /// If the emulator fell behind by more than a few frames, for instance because the host
+    /// was suspended, the missed time is dropped instead of being made up in a burst.
+    pub fn due_frames(&mut self) -> u32
+    {
+        let now = self.clock.now();
+        let mut due = 0;
+        while due <= MAX_CATCH_UP_FRAMES && self.frame_due_time(self.frames + due as u64) <= now
+        {
+            due += 1;
+        }
+
+        if due > MAX_CATCH_UP_FRAMES
+        {
+            due = MAX_CATCH_UP_FRAMES;
+            self.start = now - frame_offset(self.frames);
+        }
+        due
+    }
+
+    /// Time left until the next frame is due. Zero if it already is.
+    pub fn time_until_next_frame(&self) -> Duration
+    {
+        self.frame_due_time(self.frames).saturating_sub(self.clock.now())
+    }
+
+    fn frame_due_time(&self, frame: u64) -> Duration
+    {
+        self.start + frame_offset(frame)
+    }
+}
+
+/// Time from the first frame to the start of frame `frame`.
+fn frame_offset(frame: u64) -> Duration
+{
+    Duration::from_secs(frame) / TIMER_FREQUENCY
+}
@@ -0,0 +1,150 @@
+//! Screenshots: the display as an image file, scaled up by an integer factor and colored
+//! with a [`Palette`].
+//!
+//! PNG keeps the colors exactly. The netpbm formats need no library to read: PGM stores the
+//! brightness of every color and PBM only whether a pixel is dark or light, so that with the
+//! default palette lit pixels come out white on black like on screen.
+//!
+//! ```
+//! use chip_8::screenshot::{self, ImageFormat};
+//! use chip_8::{Chip, Palette};
+//!
+//! let chip = Chip::new();
+//! let image = screenshot::capture(&chip, ImageFormat::Pgm, 2, &Palette::default());
+//! assert!(image.starts_with(b"P5\n128 64\n255\n"));
+//! assert_eq!(image.len(), 14 + 128 * 64);
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::chip::Chip;
+use crate::palette::{Palette, Rgb};
+
+/// The image formats screenshots can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat
+{
+    /// 24 bit color PNG.
+    Png,
+    /// Binary netpbm bitmap (P4), one bit per pixel.
+    Pbm,
+    /// Binary netpbm graymap (P5), 8 bits per pixel.
+    Pgm,
+}
+
+impl ImageFormat
+{
+    /// The format a file name asks for by its extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat>
+    {
+        let extension = path.as_ref().extension()?.to_str()?;
+        extension.parse().ok()
+    }
+
+    pub fn extension(&self) -> &'static str
+    {
+        match self
+        {
+            ImageFormat::Png => "png",
+            ImageFormat::Pbm => "pbm",
+            ImageFormat::Pgm => "pgm",
+        }
+    }
+}
+
+impl FromStr for ImageFormat
+{
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<ImageFormat, String>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "png" => Ok(ImageFormat::Png),
+            "pbm" => Ok(ImageFormat::Pbm),
+            "pgm" => Ok(ImageFormat::Pgm),
+            _ => Err(format!("unknown image format {:?}, expected png, pbm or pgm", name)),
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str(self.extension())
+    }
+}
+
+/// Encodes the current display of `chip`, every pixel as a `scale` by `scale` square.
+pub fn capture(chip: &Chip, format: ImageFormat, scale: usize, palette: &Palette) -> Vec<u8>
+{
+    encode(chip.texture(), chip.screen_width(), format, scale, palette)
+}
+
+/// Writes the current display of `chip` to `path`, in the format its extension names.
+pub fn save<P: AsRef<Path>>(chip: &Chip, path: P, scale: usize, palette: &Palette) -> io::Result<()>
+{
+    let path = path.as_ref();
+    let format = ImageFormat::from_path(path).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+        "unknown image format, expected a file name ending in .png, .pbm or .pgm"))?;
+    fs::write(path, capture(chip, format, scale, palette))
+}
+
+/// Encodes a framebuffer like [`Chip::texture`], `width` pixels wide.
+pub fn encode(texture: &[u8], width: usize, format: ImageFormat, scale: usize, palette: &Palette) -> Vec<u8>
+{
+    let scale = scale.max(1);
+    let height = texture.len() / width;
+    let (image_width, image_height) = (width * scale, height * scale);
+    // The image row by row, each pixel repeated `scale` times.
+    let rows = texture.chunks(width)
+        .flat_map(|row| std::iter::repeat_n(row, scale))
+        .map(|row| row.iter().flat_map(move |pixel| std::iter::repeat_n(palette.color(*pixel), scale)));
+
+    match format
+    {
+        ImageFormat::Png =>
+        {
+            let data : Vec<u8> = rows.flatten().flat_map(|Rgb(r, g, b)| [r, g, b]).collect();
+            let mut bytes = Vec::new();
+            let mut encoder = png::Encoder::new(&mut bytes, image_width as u32, image_height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            // Writing to memory cannot fail.
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&data).unwrap();
+            writer.finish().unwrap();
+            bytes
+        },
+        ImageFormat::Pbm =>
+        {
+            let mut bytes = format!("P4\n{} {}\n", image_width, image_height).into_bytes();
+            for row in rows
+            {
+                // 1 is black. Rows are padded to whole bytes.
+                let bits : Vec<bool> = row.map(|color| luminance(color) < 128).collect();
+                bytes.extend(bits.chunks(8).map(|byte| {
+                    byte.iter().enumerate().fold(0u8, |acc, (i, dark)| acc | (*dark as u8) << (7 - i))
+                }));
+            }
+            bytes
+        },
+        ImageFormat::Pgm =>
+        {
+            let mut bytes = format!("P5\n{} {}\n255\n", image_width, image_height).into_bytes();
+            bytes.extend(rows.flatten().map(luminance));
+            bytes
+        },
+    }
+}
+
+/// The brightness of a color, from 0 to 255, weighted like ITU-R BT.601.
+fn luminance(Rgb(r, g, b): Rgb) -> u8
+{
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
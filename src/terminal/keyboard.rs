@@ -0,0 +1,218 @@
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chip::Chip;
+
+/// A key as reported by the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostKey
+{
+    Char(char),
+    /// A letter typed while holding control, e.g. `Ctrl('c')`.
+    Ctrl(char),
+    Escape,
+    Enter,
+    Backspace,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Splits raw terminal input into keys. Escape sequences for the arrow keys are recognised,
+/// other escape sequences are dropped and a lone escape byte is reported as [`HostKey::Escape`].
+/// Terminals send a key typed with Alt as an escape followed by the key; those are dropped
+/// as well, so that Alt combinations neither quit nor press a keypad key.
+pub fn decode_keys(bytes: &[u8]) -> Vec<HostKey>
+{
+    let mut keys = Vec::new();
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next()
+    {
+        let key = match c
+        {
+            '\x1b' => match chars.peek()
+            {
+                Some('[') | Some('O') =>
+                {
+                    chars.next();
+                    // Parameters run until the final byte of the sequence.
+                    let mut last = None;
+                    for c in chars.by_ref()
+                    {
+                        if ('\x40'..='\x7e').contains(&c)
+                        {
+                            last = Some(c);
+                            break;
+                        }
+                    }
+                    match last
+                    {
+                        Some('A') => HostKey::Up,
+                        Some('B') => HostKey::Down,
+                        Some('C') => HostKey::Right,
+                        Some('D') => HostKey::Left,
+                        _ => continue,
+                    }
+                },
+                Some('\x1b') | None => HostKey::Escape,
+                Some(_) =>
+                {
+                    chars.next();
+                    continue;
+                },
+            },
+            '\r' | '\n' => HostKey::Enter,
+            '\x7f' | '\x08' => HostKey::Backspace,
+            '\x01'..='\x1a' => HostKey::Ctrl((c as u8 - 1 + b'a') as char),
+            c if c.is_control() => continue,
+            c => HostKey::Char(c),
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// Puts the terminal attached to stdin into raw mode for as long as the value lives.
+///
+/// In raw mode keys are delivered as soon as they are typed, without echo, and control keys
+/// such as Ctrl-C are passed through as input instead of raising signals.
+pub struct RawMode
+{
+    #[cfg(unix)]
+    original : libc::termios,
+}
+
+impl RawMode
+{
+    #[cfg(unix)]
+    pub fn enable() -> io::Result<RawMode>
+    {
+        // SAFETY: tcgetattr/tcsetattr only read and write the termios struct we pass in.
+        unsafe
+        {
+            let mut original : libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(RawMode{ original })
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn enable() -> io::Result<RawMode>
+    {
+        Err(io::Error::new(io::ErrorKind::Other, "raw terminal input is only supported on unix"))
+    }
+}
+
+impl Drop for RawMode
+{
+    fn drop(&mut self)
+    {
+        #[cfg(unix)]
+        // SAFETY: restores the attributes read in `enable`.
+        unsafe
+        {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Reads keys from stdin on a background thread so the emulator can poll them without blocking.
+pub struct KeyboardReader
+{
+    receiver : Receiver<Vec<HostKey>>,
+}
+
+impl KeyboardReader
+{
+    pub fn spawn() -> KeyboardReader
+    {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move ||
+        {
+            let mut stdin = io::stdin();
+            let mut buffer = [0u8; 64];
+            while let Ok(count) = stdin.read(&mut buffer)
+            {
+                if count == 0 || sender.send(decode_keys(&buffer[..count])).is_err()
+                {
+                    break;
+                }
+            }
+        });
+        KeyboardReader{ receiver }
+    }
+
+    /// Returns all keys typed since the last call.
+    pub fn poll(&self) -> Vec<HostKey>
+    {
+        let mut keys = Vec::new();
+        loop
+        {
+            match self.receiver.try_recv()
+            {
+                Ok(batch) => keys.extend(batch),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return keys,
+            }
+        }
+    }
+}
+
+/// Turns typed keys into press and release events for the hex keypad.
+///
+/// Terminals only report that a key was typed, never that it was let go. Each typed key
+/// is therefore held down for `hold` and released afterwards unless the terminal's
+/// key repeat types it again in the meantime.
+pub struct KeyHold
+{
+    hold : Duration,
+    release_at : [Option<Instant>; 16],
+}
+
+impl KeyHold
+{
+    pub fn new(hold: Duration) -> KeyHold
+    {
+        KeyHold{
+            hold,
+            release_at: [None; 16],
+        }
+    }
+
+    /// Presses `key` on `chip` and (re)starts its hold time.
+    pub fn press(&mut self, chip: &mut Chip, key: u8, now: Instant)
+    {
+        chip.press_key(key);
+        self.release_at[(key & 0xF) as usize] = Some(now + self.hold);
+    }
+
+    /// Releases every key whose hold time ran out.
+    pub fn update(&mut self, chip: &mut Chip, now: Instant)
+    {
+        for (key, release_at) in self.release_at.iter_mut().enumerate()
+        {
+            if release_at.is_some_and(|deadline| now >= deadline)
+            {
+                chip.release_key(key as u8);
+                *release_at = None;
+            }
+        }
+    }
+}
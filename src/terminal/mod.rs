@@ -0,0 +1,7 @@
+//! Frontend pieces for running the emulator inside a text terminal.
+
+mod keyboard;
+mod renderer;
+
+pub use keyboard::{decode_keys, HostKey, KeyHold, KeyboardReader, RawMode};
+pub use renderer::{Glyphs, TerminalRenderer};
@@ -0,0 +1,176 @@
+use std::io::{self, Write};
+
+use crate::chip::{Chip, SCREEN_HEIGHT};
+use crate::palette::{Palette, Rgb};
+
+/// How framebuffer pixels are packed into terminal character cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Glyphs
+{
+    /// One cell per 1x2 pixels using the upper half block. Both pixels keep their own color.
+    HalfBlock,
+    /// One cell per 2x4 pixels using braille patterns. Gives the smallest output but
+    /// only lit pixels are drawn, in the foreground color.
+    Braille,
+}
+
+impl Glyphs
+{
+    fn cell_size(self) -> (usize, usize)
+    {
+        match self
+        {
+            Glyphs::HalfBlock => (1, 2),
+            Glyphs::Braille => (2, 4),
+        }
+    }
+}
+
+/// Draws the framebuffer of a [`Chip`] to an ANSI terminal.
+///
+/// The renderer remembers the last frame it drew and only rewrites the terminal lines
+/// whose pixels changed since then. A change of resolution redraws the whole screen.
+pub struct TerminalRenderer
+{
+    glyphs : Glyphs,
+    palette : Palette,
+    last_frame : Option<Vec<u8>>,
+    /// Height in pixels of the last drawn frame.
+    last_height : usize,
+}
+
+impl TerminalRenderer
+{
+    pub fn new(glyphs: Glyphs, palette: Palette) -> TerminalRenderer
+    {
+        TerminalRenderer{
+            glyphs,
+            palette,
+            last_frame: None,
+            last_height: SCREEN_HEIGHT as usize,
+        }
+    }
+
+    /// Clears the terminal and hides the cursor. Call once before the first [`render`](Self::render).
+    pub fn begin<W: Write>(&mut self, out: &mut W) -> io::Result<()>
+    {
+        self.last_frame = None;
+        write!(out, "\x1b[2J\x1b[?25l")?;
+        out.flush()
+    }
+
+    /// Restores the terminal colors and cursor and moves below the drawn screen.
+    pub fn end<W: Write>(&mut self, out: &mut W) -> io::Result<()>
+    {
+        let (_, cell_height) = self.glyphs.cell_size();
+        let rows = self.last_height.div_ceil(cell_height);
+        write!(out, "\x1b[0m\x1b[{};1H\x1b[2K\x1b[?25h", rows + 1)?;
+        out.flush()
+    }
+
+    /// Shows `text` on the line below the drawn screen, replacing the previous status.
+    pub fn status<W: Write>(&mut self, text: &str, out: &mut W) -> io::Result<()>
+    {
+        let (_, cell_height) = self.glyphs.cell_size();
+        let rows = self.last_height.div_ceil(cell_height);
+        write!(out, "\x1b[0m\x1b[{};1H\x1b[2K{}", rows + 1, text)?;
+        out.flush()
+    }
+
+    /// Forgets the last drawn frame so the next [`render`](Self::render) redraws everything.
+    pub fn invalidate(&mut self)
+    {
+        self.last_frame = None;
+    }
+
+    /// Draws the framebuffer of `chip`. Returns whether anything was written.
+    pub fn render<W: Write>(&mut self, chip: &Chip, out: &mut W) -> io::Result<bool>
+    {
+        let texture = chip.texture();
+        let (cell_width, cell_height) = self.glyphs.cell_size();
+        let width = chip.screen_width();
+        let line_pixels = width * cell_height;
+
+        if self.last_frame.as_ref().is_some_and(|last| last.len() != texture.len())
+        {
+            write!(out, "\x1b[0m\x1b[2J")?;
+            self.last_frame = None;
+        }
+        self.last_height = chip.screen_height();
+
+        let mut drawn = false;
+        for (row, line) in texture.chunks(line_pixels).enumerate()
+        {
+            let unchanged = self.last_frame.as_ref()
+                .is_some_and(|last| last[row * line_pixels..][..line.len()] == *line);
+            if unchanged
+            {
+                continue;
+            }
+
+            write!(out, "\x1b[{};1H", row + 1)?;
+            match self.glyphs
+            {
+                Glyphs::HalfBlock => self.write_half_block_line(line, width, out)?,
+                Glyphs::Braille => self.write_braille_line(line, width, cell_width, out)?,
+            }
+            write!(out, "\x1b[0m")?;
+            drawn = true;
+        }
+
+        if drawn
+        {
+            out.flush()?;
+        }
+        self.last_frame = Some(texture.to_vec());
+        Ok(drawn)
+    }
+
+    fn write_half_block_line<W: Write>(&self, line: &[u8], width: usize, out: &mut W) -> io::Result<()>
+    {
+        let mut current : Option<(Rgb, Rgb)> = None;
+        for x in 0..width
+        {
+            let top = self.palette.color(line[x]);
+            let bottom = self.palette.color(line.get(x + width).copied().unwrap_or(0));
+            if current != Some((top, bottom))
+            {
+                write!(out, "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                    top.0, top.1, top.2, bottom.0, bottom.1, bottom.2)?;
+                current = Some((top, bottom));
+            }
+            write!(out, "\u{2580}")?;
+        }
+        Ok(())
+    }
+
+    fn write_braille_line<W: Write>(&self, line: &[u8], width: usize, cell_width: usize, out: &mut W) -> io::Result<()>
+    {
+        // Bit of each dot in a braille pattern, indexed by [row][column].
+        const DOTS : [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+        let foreground = self.palette.foreground;
+        let background = self.palette.background;
+        write!(out, "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+            foreground.0, foreground.1, foreground.2, background.0, background.1, background.2)?;
+
+        let rows = line.len() / width;
+        for cell in 0..width / cell_width
+        {
+            let mut pattern : u32 = 0;
+            for (dy, dots) in DOTS.iter().enumerate().take(rows)
+            {
+                for (dx, dot) in dots.iter().enumerate()
+                {
+                    if line[dy * width + cell * cell_width + dx] != 0
+                    {
+                        pattern |= dot;
+                    }
+                }
+            }
+            let glyph = std::char::from_u32(0x2800 + pattern).unwrap();
+            write!(out, "{}", glyph)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+//! Instruction traces: a log of every instruction a [`Chip`] executes.
+//!
+//! A [`Tracer`] is handed to the machine with [`Chip::set_tracer`] and writes one
+//! [`TraceRecord`] per executed instruction: its number counted from the start of the
+//! trace, the address, the opcode, the disassembly and the registers it changed. The text
+//! format has one line per instruction and does not change between releases, so traces of
+//! two builds can be compared with `diff`:
+//!
+//! ```text
+//!          0 0200  6A02      LD VA, 0x02          VA=02
+//!          1 0202  A22A      LD I, 0x22A          I=022A
+//!          2 0204  DAB6      DRW VA, VB, 6        VF=01
+//!          3 0206  F000BEEF  LD I, LONG 0xBEEF    I=BEEF
+//! ```
+//!
+//! The binary format stores the same information in a few bytes per instruction, for long
+//! runs. [`read_binary`] turns it back into records:
+//!
+//! ```
+//! use chip_8::trace::{self, TraceFormat, Tracer};
+//! use chip_8::Chip;
+//!
+//! let path = std::env::temp_dir().join("chip_8_trace_example.bin");
+//! let mut chip = Chip::new();
+//! // 0x200: LD V0, 5, 0x202: ADD V0, V0
+//! chip.load_rom_bytes(&[0x60, 0x05, 0x80, 0x04]).unwrap();
+//! chip.set_tracer(Some(Tracer::create(&path, TraceFormat::Binary).unwrap()));
+//! chip.emulate_cycle().unwrap();
+//! chip.emulate_cycle().unwrap();
+//! chip.take_tracer().unwrap().finish().unwrap();
+//!
+//! let records = trace::read_binary(&std::fs::read(&path).unwrap()).unwrap();
+//! assert_eq!(records[1].to_string(), "         1 0202  8004      ADD V0, V0           V0=0A");
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::chip::Chip;
+use crate::instruction::Instruction;
+use crate::rewind::{read_length, write_length};
+use crate::variant::Variant;
+
+/// The binary trace version this crate writes and reads.
+pub const TRACE_VERSION : u16 = 1;
+
+const MAGIC : &[u8; 4] = b"C8TR";
+
+/// The formats a [`Tracer`] can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat
+{
+    /// One line per instruction, see the [module documentation](self).
+    Text,
+    /// A compact encoding read by [`read_binary`].
+    Binary,
+}
+
+impl FromStr for TraceFormat
+{
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<TraceFormat, String>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "text" => Ok(TraceFormat::Text),
+            "binary" => Ok(TraceFormat::Binary),
+            _ => Err(format!("unknown trace format {:?}, expected text or binary", name)),
+        }
+    }
+}
+
+/// One executed instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord
+{
+    /// Number of instructions executed before this one since tracing started.
+    pub index : u64,
+    pub address : u16,
+    pub opcode : u16,
+    /// The instruction, decoded for the variant the machine had.
+    pub instruction : Option<Instruction>,
+    /// The registers the instruction changed, as register number and new value.
+    pub registers : Vec<(u8, u8)>,
+    /// The new value of the index register, if the instruction changed it.
+    pub index_register : Option<u16>,
+}
+
+impl fmt::Display for TraceRecord
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let mut hex = format!("{:04X}", self.opcode);
+        if let Some(Instruction::LongIndex{ nnnn }) = self.instruction
+        {
+            write!(hex, "{:04X}", nnnn)?;
+        }
+        let disassembly = match &self.instruction
+        {
+            Some(instruction) => instruction.to_string(),
+            None => format!("DW 0x{:04X}", self.opcode),
+        };
+        let mut changes = String::new();
+        for (register, value) in &self.registers
+        {
+            write!(changes, " V{:X}={:02X}", register, value)?;
+        }
+        if let Some(index) = self.index_register
+        {
+            write!(changes, " I={:04X}", index)?;
+        }
+        let line = format!("{:>10} {:04X}  {:<8}  {:<20}{}", self.index, self.address, hex, disassembly, changes);
+        f.write_str(line.trim_end())
+    }
+}
+
+/// Writes the instructions a machine executes, see the [module documentation](self).
+///
+/// Writing stops at the first error, which [`finish`](Tracer::finish) reports.
+pub struct Tracer
+{
+    out : Box<dyn Write>,
+    format : TraceFormat,
+    range : RangeInclusive<u16>,
+    limit : Option<u64>,
+    /// Instructions executed since tracing started, traced or not.
+    executed : u64,
+    /// Index of the last written record, for the gaps of the binary format.
+    last_written : Option<u64>,
+    written : u64,
+    header_written : bool,
+    error : Option<io::Error>,
+}
+
+impl Tracer
+{
+    /// Traces every instruction into `out`.
+    pub fn new<W: Write + 'static>(out: W, format: TraceFormat) -> Tracer
+    {
+        Tracer{
+            out: Box::new(out),
+            format,
+            range: 0..=u16::MAX,
+            limit: None,
+            executed: 0,
+            last_written: None,
+            written: 0,
+            header_written: false,
+            error: None,
+        }
+    }
+
+    /// Traces into a new file at `path`.
+    pub fn create<P: AsRef<Path>>(path: P, format: TraceFormat) -> io::Result<Tracer>
+    {
+        Ok(Tracer::new(BufWriter::new(File::create(path)?), format))
+    }
+
+    /// Only traces instructions at addresses in `range`. They still count towards the index.
+    pub fn set_range(&mut self, range: RangeInclusive<u16>)
+    {
+        self.range = range;
+    }
+
+    /// Stops tracing after `limit` records.
+    pub fn set_limit(&mut self, limit: Option<u64>)
+    {
+        self.limit = limit;
+    }
+
+    /// Number of records written so far.
+    pub fn written(&self) -> u64
+    {
+        self.written
+    }
+
+    /// Flushes the trace and reports the first error that stopped it, if any.
+    pub fn finish(&mut self) -> io::Result<()>
+    {
+        match self.error.take()
+        {
+            Some(error) => Err(error),
+            None => self.out.flush(),
+        }
+    }
+
+    /// Notes the instruction `chip` just executed at `address`, given the registers and the
+    /// index register from before it.
+    pub(crate) fn record(&mut self, chip: &Chip, address: u16, registers: &[u8; 16], index_register: u16)
+    {
+        let index = self.executed;
+        self.executed += 1;
+        if !self.range.contains(&address) || self.limit.is_some_and(|limit| self.written >= limit) || self.error.is_some()
+        {
+            return;
+        }
+
+        // The opcode is taken from the chip, since the instruction may have overwritten itself.
+        let opcode = chip.current_opcode();
+        let next_address = address.wrapping_add(2) as usize;
+        let next = chip.memory().get(next_address..next_address + 2).map_or(0, |pair| (pair[0] as u16) << 8 | pair[1] as u16);
+        let record = TraceRecord{
+            index,
+            address,
+            opcode,
+            instruction: Instruction::decode(opcode, next, chip.variant()),
+            registers: (0..16u8)
+                .filter(|register| registers[*register as usize] != chip.registers()[*register as usize])
+                .map(|register| (register, chip.registers()[register as usize]))
+                .collect(),
+            index_register: Some(chip.index_register()).filter(|index| *index != index_register),
+        };
+
+        let result = match self.format
+        {
+            TraceFormat::Text => writeln!(self.out, "{}", record),
+            TraceFormat::Binary => self.write_binary(&record, chip.variant()),
+        };
+        match result
+        {
+            Ok(()) => self.written += 1,
+            Err(error) => self.error = Some(error),
+        }
+        self.last_written = Some(index);
+    }
+
+    /// Writes a record as: the number of instructions skipped since the previous record
+    /// (LEB128), address, opcode, the second word of long instructions, a mask of the changed
+    /// registers with bit 16 for the index register, and their new values. Multi-byte values
+    /// are big-endian. The first record is preceded by the magic, the version and the name of
+    /// the variant.
+    fn write_binary(&mut self, record: &TraceRecord, variant: Variant) -> io::Result<()>
+    {
+        let mut bytes = Vec::new();
+        if !self.header_written
+        {
+            bytes.extend_from_slice(MAGIC);
+            bytes.extend_from_slice(&TRACE_VERSION.to_be_bytes());
+            let name = variant.to_string();
+            bytes.push(name.len() as u8);
+            bytes.extend_from_slice(name.as_bytes());
+            self.header_written = true;
+        }
+
+        let gap = record.index - self.last_written.map_or(0, |last| last + 1);
+        write_length(&mut bytes, gap as usize);
+        bytes.extend_from_slice(&record.address.to_be_bytes());
+        bytes.extend_from_slice(&record.opcode.to_be_bytes());
+        if let Some(Instruction::LongIndex{ nnnn }) = record.instruction
+        {
+            bytes.extend_from_slice(&nnnn.to_be_bytes());
+        }
+
+        let mut mask : u32 = record.registers.iter().fold(0, |mask, (register, _)| mask | 1 << register);
+        if record.index_register.is_some()
+        {
+            mask |= 1 << 16;
+        }
+        bytes.extend_from_slice(&mask.to_be_bytes()[1..]);
+        bytes.extend(record.registers.iter().map(|(_, value)| value));
+        if let Some(index) = record.index_register
+        {
+            bytes.extend_from_slice(&index.to_be_bytes());
+        }
+        self.out.write_all(&bytes)
+    }
+}
+
+/// Reads a trace in the binary format.
+pub fn read_binary(bytes: &[u8]) -> Result<Vec<TraceRecord>, String>
+{
+    if bytes.is_empty()
+    {
+        return Ok(Vec::new());
+    }
+    let mut input = Input{ bytes };
+    if input.take(4).ok() != Some(&MAGIC[..])
+    {
+        return Err("not a binary trace".to_string());
+    }
+    let version = input.word()?;
+    if version > TRACE_VERSION
+    {
+        return Err(format!("the trace has version {}, but at most version {} is supported", version, TRACE_VERSION));
+    }
+    let name_length = input.take(1)?[0] as usize;
+    let variant : Variant = std::str::from_utf8(input.take(name_length)?).ok()
+        .and_then(|name| name.parse().ok())
+        .ok_or("the trace has an unknown variant")?;
+
+    let mut records = Vec::new();
+    let mut index = 0;
+    while !input.bytes.is_empty()
+    {
+        index += read_length(&mut input.bytes) as u64;
+        let address = input.word()?;
+        let opcode = input.word()?;
+        // Only a long instruction decodes differently once its second word is known.
+        let next = match Instruction::decode(opcode, 0, variant)
+        {
+            Some(Instruction::LongIndex{ .. }) => input.word()?,
+            _ => 0,
+        };
+        let mask = input.take(3)?;
+        let mask = u32::from_be_bytes([0, mask[0], mask[1], mask[2]]);
+        let mut registers = Vec::new();
+        for register in (0..16u8).filter(|register| mask & 1 << register != 0)
+        {
+            registers.push((register, input.take(1)?[0]));
+        }
+        let index_register = if mask & 1 << 16 != 0 {Some(input.word()?)} else {None};
+
+        records.push(TraceRecord{
+            index,
+            address,
+            opcode,
+            instruction: Instruction::decode(opcode, next, variant),
+            registers,
+            index_register,
+        });
+        index += 1;
+    }
+    Ok(records)
+}
+
+/// The unread rest of a binary trace.
+struct Input<'a>
+{
+    bytes : &'a [u8],
+}
+
+impl<'a> Input<'a>
+{
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String>
+    {
+        if self.bytes.len() < length
+        {
+            return Err("the trace is cut off".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn word(&mut self) -> Result<u16, String>
+    {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chip::{MEMORY_SIZE, XO_MEMORY_SIZE};
+
+/// The instruction set a [`Chip`](crate::Chip) understands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Variant
+{
+    /// The original CHIP-8 instruction set with the 64x32 display.
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1: adds the 128x64 high resolution mode, scrolling, 16x16 sprites,
+    /// the large font and the RPL user flags.
+    SuperChip,
+    /// XO-CHIP: SUPER-CHIP plus 64 KiB of memory, a second bit-plane, audio patterns and
+    /// a handful of convenience instructions.
+    XoChip,
+}
+
+impl Variant
+{
+    /// Whether the SUPER-CHIP instructions are available.
+    pub fn has_superchip(self) -> bool
+    {
+        self != Variant::Chip8
+    }
+
+    /// Whether the XO-CHIP instructions are available.
+    pub fn has_xochip(self) -> bool
+    {
+        self == Variant::XoChip
+    }
+
+    /// Number of RPL user flags saved and restored by 0xFX75 and 0xFX85.
+    pub fn rpl_flag_count(self) -> usize
+    {
+        match self
+        {
+            Variant::Chip8 => 0,
+            Variant::SuperChip => 8,
+            Variant::XoChip => 16,
+        }
+    }
+
+    /// Size of main memory in bytes.
+    pub fn memory_size(self) -> usize
+    {
+        match self
+        {
+            Variant::Chip8 | Variant::SuperChip => MEMORY_SIZE,
+            Variant::XoChip => XO_MEMORY_SIZE,
+        }
+    }
+}
+
+impl FromStr for Variant
+{
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Variant, String>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "chip8" | "chip-8" => Ok(Variant::Chip8),
+            "schip" | "superchip" | "super-chip" => Ok(Variant::SuperChip),
+            "xochip" | "xo-chip" => Ok(Variant::XoChip),
+            _ => Err(format!("unknown variant {:?}, expected chip8, schip or xochip", name)),
+        }
+    }
+}
+
+impl fmt::Display for Variant
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            Variant::Chip8 => write!(f, "chip8"),
+            Variant::SuperChip => write!(f, "schip"),
+            Variant::XoChip => write!(f, "xochip"),
+        }
+    }
+}
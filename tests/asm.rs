@@ -0,0 +1,141 @@
+use std::fs;
+
+use chip_8::asm::{AsmError, Assembler};
+use chip_8::Variant;
+
+fn assemble(source: &str) -> Result<Vec<u8>, AsmError>
+{
+    Assembler::new(Variant::XoChip).assemble_source("test.asm", source).map(|assembly| assembly.bytes)
+}
+
+fn error(source: &str) -> (usize, String)
+{
+    let error = assemble(source).unwrap_err();
+    (error.line, error.message)
+}
+
+#[test]
+fn labels_resolve_forwards_and_backwards()
+{
+    let bytes = assemble("\
+start:  jp end
+loop:   jp loop
+end:    call start
+").unwrap();
+    assert_eq!(bytes, [0x12, 0x04, 0x12, 0x02, 0x22, 0x00]);
+}
+
+#[test]
+fn constants_and_expressions()
+{
+    let bytes = assemble("\
+digit   equ 7
+twice   equ digit * 2
+        ld v0, digit
+        ld v1, (twice + 1) | 0x40
+        ld va, 'A' + $ - $200
+        ld vb, 2 + 3 * 4
+").unwrap();
+    assert_eq!(bytes, [0x60, 0x07, 0x61, 0x4F, 0x6A, 0x45, 0x6B, 0x0E]);
+}
+
+#[test]
+fn expressions_do_not_overflow()
+{
+    assert_eq!(assemble("db -(0 - 0x7FFFFFFFFFFFFFFF - 1) & 0xFF").unwrap(), [0x00]);
+    assert_eq!(error("db (0 - 0x7FFFFFFFFFFFFFFF - 1) / -1").1,
+        "-9223372036854775808 / -1 overflows");
+    assert_eq!(error("db 1 / 0").1, "division by zero");
+}
+
+#[test]
+fn equ_expects_a_name()
+{
+    assert_eq!(error("cls\nequ 5"), (2, "equ expects a name".to_string()));
+}
+
+#[test]
+fn org_fills_the_gap_with_zeros()
+{
+    let assembly = Assembler::new(Variant::Chip8).assemble_source("test.asm", "\
+        cls
+        org 0x206
+here:   jp here
+").unwrap();
+    assert_eq!(assembly.bytes, [0x00, 0xE0, 0x00, 0x00, 0x00, 0x00, 0x12, 0x06]);
+    assert_eq!(assembly.symbols["here"], 0x206);
+}
+
+#[test]
+fn org_must_stay_inside_memory()
+{
+    assert_eq!(error("org 0x10000").1, "org 0x10000 is beyond the end of memory at 0x10000");
+    assert_eq!(assemble("org 0xFFFF\ndb 1").unwrap().len(), 0xFE00);
+    assert_eq!(error("org 0xFFFF\ndw 1").0, 2);
+    assert_eq!(error("jp 0x300\norg 0x200").1, "org 0x200 is below the current address 0x202");
+}
+
+#[test]
+fn data_directives()
+{
+    let bytes = assemble("\
+table:  dw table, 0x1234
+        db 0b10100101, -1, \"hi\"
+").unwrap();
+    assert_eq!(bytes, [0x02, 0x00, 0x12, 0x34, 0xA5, 0xFF, b'h', b'i']);
+    assert_eq!(error("db 256").1, "256 does not fit into 1 byte(s)");
+    assert_eq!(error("dw \"text\"").1, "data must be numbers or, for db, strings");
+}
+
+#[test]
+fn include_assembles_another_file_in_place()
+{
+    let directory = std::env::temp_dir().join(format!("chip_8_asm_{}", std::process::id()));
+    fs::create_dir_all(directory.join("lib")).unwrap();
+    fs::write(directory.join("main.asm"), "cls\ninclude \"lib/sprite.asm\"\njp sprite\n").unwrap();
+    fs::write(directory.join("lib/sprite.asm"), "sprite: db 0xF0\n        db 0x90\n").unwrap();
+    fs::write(directory.join("loop.asm"), "include \"loop.asm\"\n").unwrap();
+
+    let assembler = Assembler::new(Variant::Chip8);
+    let assembly = assembler.assemble_file(directory.join("main.asm")).unwrap();
+    let looped = assembler.assemble_file(directory.join("loop.asm")).unwrap_err();
+    let missing = assembler.assemble_source("test.asm", "include \"missing.asm\"").unwrap_err();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(assembly.bytes, [0x00, 0xE0, 0xF0, 0x90, 0x12, 0x02]);
+    assert!(assembly.listing[1].file.ends_with("sprite.asm"));
+    assert!(looped.message.ends_with("includes itself"));
+    assert!(missing.message.starts_with("cannot include missing.asm"));
+}
+
+#[test]
+fn errors_name_the_line()
+{
+    assert_eq!(error("cls\nfoo v0"), (2, "unknown instruction FOO".to_string()));
+    assert_eq!(error("cls\n\njp nowhere"), (3, "undefined symbol nowhere".to_string()));
+    assert_eq!(error("a: cls\na: cls").1, "a is defined twice");
+    assert_eq!(error("ld v0, 0x100").0, 1);
+    // Long loads need XO-CHIP.
+    assert!(Assembler::new(Variant::Chip8).assemble_source("test.asm", "ld i, long 0x1234").is_err());
+
+    let error = Assembler::new(Variant::Chip8).assemble_source("game.asm", "\n\nbad").unwrap_err();
+    assert_eq!(error.to_string(), "game.asm:3: unknown instruction BAD");
+}
+
+#[test]
+fn listing_shows_addresses_bytes_and_symbols()
+{
+    let assembly = Assembler::new(Variant::Chip8).assemble_source("test.asm", "\
+start:  cls             ; clear
+        db 1, 2, 3, 4, 5
+").unwrap();
+    assert_eq!(assembly.format_listing(), "\
+; test.asm
+0200  00E0          1  start:  cls             ; clear
+0202  01020304      2          db 1, 2, 3, 4, 5
+0206  05
+
+symbols:
+    start                    0x0200
+");
+}
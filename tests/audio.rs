@@ -0,0 +1,90 @@
+mod common;
+
+use std::io::Cursor;
+
+use chip_8::audio::{AudioSink, ToneGenerator, WavSink};
+
+/// A machine whose sound timer runs for the next frames.
+fn beeping() -> chip_8::Chip
+{
+    // 0x200: LD V0, 5, 0x202: LD ST, V0
+    let mut chip = common::chip(&[0x6005, 0xF018]);
+    chip.emulate_cycle().unwrap();
+    chip.emulate_cycle().unwrap();
+    chip.tick_timers();
+    chip
+}
+
+#[test]
+fn square_wave_has_the_set_period_and_amplitude()
+{
+    // A 375 Hz tone at 6000 Hz lasts 16 samples per period, 100 samples per frame.
+    let mut generator = ToneGenerator::new(6000);
+    generator.set_frequency(375.0);
+    generator.set_volume(0.5);
+    let mut samples = Vec::new();
+    generator.generate_frame(&beeping(), &mut samples);
+
+    assert_eq!(samples.len(), 100);
+    for (index, sample) in samples.iter().enumerate()
+    {
+        let expected = if index % 16 < 8 {0.5} else {-0.5};
+        assert_eq!(*sample, expected, "sample {}", index);
+    }
+}
+
+#[test]
+fn silent_frames_keep_the_sample_count()
+{
+    let chip = common::chip(&[0x1200]);
+    let mut generator = ToneGenerator::new(1000);
+    let mut samples = Vec::new();
+    let mut counts = Vec::new();
+    for _ in 0..3
+    {
+        let before = samples.len();
+        generator.generate_frame(&chip, &mut samples);
+        counts.push(samples.len() - before);
+    }
+    // 1000 / 60 samples per frame, with the remainder carried over.
+    assert_eq!(counts, [16, 17, 17]);
+    assert!(samples.iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn wav_files_have_a_complete_header()
+{
+    let mut bytes = Vec::new();
+    {
+        let mut sink = WavSink::new(Cursor::new(&mut bytes), 8000).unwrap();
+        sink.write(&[0.0, 1.0, -1.0]).unwrap();
+        sink.write(&[0.5; 7]).unwrap();
+        sink.finish().unwrap();
+    }
+    let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+    let u32_at = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+
+    assert_eq!(bytes.len(), 44 + 20);
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(u32_at(4), 36 + 20);
+    assert_eq!(&bytes[8..16], b"WAVEfmt ");
+    assert_eq!(u32_at(16), 16);
+    assert_eq!(u16_at(20), 1);
+    assert_eq!(u16_at(22), 1);
+    assert_eq!(u32_at(24), 8000);
+    assert_eq!(u32_at(28), 16000);
+    assert_eq!(u16_at(32), 2);
+    assert_eq!(u16_at(34), 16);
+    assert_eq!(&bytes[36..40], b"data");
+    assert_eq!(u32_at(40), 20);
+
+    let samples : Vec<i16> = bytes[44..].chunks(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect();
+    assert_eq!(samples[..3], [0, i16::MAX, -i16::MAX]);
+    assert!(samples[3..].iter().all(|sample| *sample == i16::MAX / 2));
+}
+
+#[test]
+fn wav_sample_rates_are_bounded()
+{
+    assert!(WavSink::new(Cursor::new(Vec::new()), u32::MAX).is_err());
+}
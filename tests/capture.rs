@@ -0,0 +1,148 @@
+mod common;
+
+use chip_8::capture::{FrameSink, GifSink, RawSink, Y4mSink};
+use chip_8::{Palette, Variant};
+
+use common::chip_with;
+
+/// Decodes a GIF into its frames' delays and first pixels.
+fn frames(gif: &[u8]) -> Vec<(u16, Vec<u8>)>
+{
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(gif).unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap()
+    {
+        frames.push((frame.delay, frame.buffer.to_vec()));
+    }
+    frames
+}
+
+#[test]
+fn gif_merges_repeated_frames()
+{
+    // Toggles the 0 of the font on and off once.
+    let mut chip = chip_with(Variant::Chip8, &[0xD005, 0xD005, 0x1204]);
+    let mut sink = GifSink::new(Vec::new(), &chip, 1, Palette::default()).unwrap();
+    for _ in 0..30
+    {
+        sink.frame(&chip).unwrap();
+    }
+    chip.emulate_cycle().unwrap();
+    for _ in 0..60
+    {
+        sink.frame(&chip).unwrap();
+    }
+    chip.emulate_cycle().unwrap();
+    sink.frame(&chip).unwrap();
+    sink.frame(&chip).unwrap();
+    sink.frame(&chip).unwrap();
+    let gif = sink.into_inner().unwrap();
+
+    let frames = frames(&gif);
+    let delays : Vec<u16> = frames.iter().map(|(delay, _)| *delay).collect();
+    assert_eq!(delays, [50, 100, 5]);
+    assert_eq!(frames[0].1[0], 0);
+    assert_eq!(frames[1].1[..4], [1, 1, 1, 1]);
+    assert_eq!(frames[1].1.len(), 64 * 32);
+}
+
+#[test]
+fn gif_drops_frames_shorter_than_the_minimum_delay()
+{
+    // Flickers the 0 every instruction.
+    let mut chip = chip_with(Variant::Chip8, &[0xD005, 0x1200]);
+    let mut sink = GifSink::new(Vec::new(), &chip, 1, Palette::default()).unwrap();
+    for _ in 0..60
+    {
+        sink.frame(&chip).unwrap();
+        chip.emulate_cycle().unwrap();
+        chip.emulate_cycle().unwrap();
+    }
+    let gif = sink.into_inner().unwrap();
+
+    let delays : Vec<u16> = frames(&gif).iter().map(|(delay, _)| *delay).collect();
+    assert!(delays.iter().all(|delay| *delay >= 2));
+    assert_eq!(delays.iter().map(|delay| *delay as u32).sum::<u32>(), 100);
+}
+
+#[test]
+fn gif_finishes_when_dropped()
+{
+    let mut file = std::env::temp_dir();
+    file.push(format!("chip_8_capture_{}.gif", std::process::id()));
+    {
+        let chip = chip_with(Variant::Chip8, &[0x1200]);
+        let mut sink = GifSink::create(&file, &chip, 2, Palette::default()).unwrap();
+        sink.frame(&chip).unwrap();
+    }
+    let gif = std::fs::read(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+
+    assert_eq!(gif.last(), Some(&0x3B));
+    let frames = frames(&gif);
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].1.len(), 128 * 64);
+}
+
+#[test]
+fn raw_frames_are_rgb()
+{
+    let mut chip = chip_with(Variant::Chip8, &[0xD005]);
+    chip.emulate_cycle().unwrap();
+    let mut sink = RawSink::new(Vec::new(), &chip, 2, Palette::default());
+    assert_eq!((sink.width(), sink.height()), (128, 64));
+    sink.frame(&chip).unwrap();
+    sink.frame(&chip).unwrap();
+    let video = sink.into_inner();
+
+    assert_eq!(video.len(), 2 * 128 * 64 * 3);
+    assert_eq!(video[..24], [0xFF; 24]);
+    assert_eq!(video[24..27], [0; 3]);
+    // The second image row repeats the first.
+    assert_eq!(video[128 * 3..128 * 3 + 24], [0xFF; 24]);
+}
+
+#[test]
+fn gifs_larger_than_the_format_are_rejected()
+{
+    let chip = chip_with(Variant::SuperChip, &[]);
+    let error = GifSink::new(Vec::new(), &chip, 512, Palette::default()).err().unwrap();
+    assert_eq!(error.to_string(), "a GIF cannot be 65536x32768 pixels");
+    assert!(GifSink::new(Vec::new(), &chip, 511, Palette::default()).is_ok());
+}
+
+#[test]
+fn superchip_low_resolution_is_scaled_up()
+{
+    let mut chip = chip_with(Variant::SuperChip, &[0xD005]);
+    chip.emulate_cycle().unwrap();
+    let mut sink = RawSink::new(Vec::new(), &chip, 1, Palette::default());
+    assert_eq!((sink.width(), sink.height()), (128, 64));
+    sink.frame(&chip).unwrap();
+    let video = sink.into_inner();
+    assert_eq!(video[..24], [0xFF; 24]);
+    assert_eq!(video[24..27], [0; 3]);
+}
+
+#[test]
+fn y4m_frames_have_three_planes()
+{
+    let mut chip = chip_with(Variant::Chip8, &[0xD005]);
+    chip.emulate_cycle().unwrap();
+    let mut sink = Y4mSink::new(Vec::new(), &chip, 1, Palette::default()).unwrap();
+    sink.frame(&chip).unwrap();
+    sink.frame(&chip).unwrap();
+    let video = sink.into_inner();
+
+    let header = b"YUV4MPEG2 W64 H32 F60:1 Ip A1:1 C444\n";
+    assert_eq!(&video[..header.len()], header);
+    let frame = &video[header.len()..];
+    assert_eq!(frame.len(), 2 * (6 + 3 * 64 * 32));
+    assert_eq!(&frame[..6], b"FRAME\n");
+    // White and black in studio range, with neutral color.
+    assert_eq!(frame[6..11], [235, 235, 235, 235, 16]);
+    assert_eq!(frame[6 + 64 * 32], 128);
+    assert_eq!(frame[6 + 2 * 64 * 32], 128);
+}
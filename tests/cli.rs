@@ -0,0 +1,69 @@
+//! The command line frontend, run as a separate process.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A ROM that counts V0 up in a loop: 0x200: ADD V0, 1, 0x202: JP 0x200.
+fn rom(name: &str) -> PathBuf
+{
+    let path = std::env::temp_dir().join(format!("chip_8_cli_{}_{}.ch8", std::process::id(), name));
+    fs::write(&path, [0x70, 0x01, 0x12, 0x00]).unwrap();
+    path
+}
+
+/// Runs the `run` command on a fresh ROM and returns its output, or its error message.
+fn run(name: &str, options: &[&str]) -> Result<String, String>
+{
+    let rom = rom(name);
+    let output = Command::new(env!("CARGO_BIN_EXE_chip_8")).arg(&rom).args(options).output().unwrap();
+    fs::remove_file(&rom).unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    match output.status.success()
+    {
+        true => Ok(stdout),
+        false => Err(stderr.lines().next().unwrap_or("").to_string()),
+    }
+}
+
+#[test]
+fn runs_headlessly_up_to_the_cycle_limit()
+{
+    let output = run("limit", &["--cycles", "5", "--dump-registers"]).unwrap();
+    assert!(output.starts_with("V0=03 "));
+    assert!(output.contains("PC=0202"));
+}
+
+#[test]
+fn rejects_unknown_options_and_extra_arguments()
+{
+    assert_eq!(run("unknown", &["--fast"]).unwrap_err(), "error: unknown option: --fast");
+    assert_eq!(run("extra", &["second.ch8"]).unwrap_err(), "error: unexpected argument: second.ch8");
+    assert_eq!(run("missing", &["--cycles"]).unwrap_err(), "error: --cycles expects a value");
+    assert_eq!(run("invalid", &["--cycles", "many"]).unwrap_err(), "error: invalid value for --cycles: many");
+}
+
+#[test]
+fn options_with_values_are_given_once()
+{
+    assert_eq!(run("twice", &["--cycles", "5", "--cycles", "6"]).unwrap_err(), "error: --cycles is given more than once");
+    assert_eq!(run("seed", &["--seed", "1", "--cycles", "1", "--seed", "2"]).unwrap_err(),
+        "error: --seed is given more than once");
+    // Flags without a value and repeatable options may appear again.
+    assert!(run("flags", &["--cycles", "1", "--dump-screen", "--dump-screen"]).is_ok());
+    assert!(run("quirks", &["--cycles", "1", "--quirk", "clip", "--quirk", "vf-reset=off"]).is_ok());
+}
+
+#[test]
+fn rejects_values_out_of_bounds()
+{
+    assert_eq!(run("ipf", &["--ipf", "0"]).unwrap_err(), "error: the instruction rate must be at least 1 per frame");
+    assert_eq!(run("ips", &["--ips", "4294967295"]).unwrap_err(), "error: invalid value for --ips: 4294967295");
+    assert_eq!(run("ipf_wide", &["--ipf", "4294967296"]).unwrap_err(), "error: invalid value for --ipf: 4294967296");
+    assert_eq!(run("rate", &["--sample-rate", "999"]).unwrap_err(), "error: the sample rate must be from 1000 to 192000 Hz");
+    assert_eq!(run("scale", &["--scale", "65"]).unwrap_err(), "error: the scale must be from 1 to 64");
+    assert_eq!(run("range", &["--trace-range", "0x300-0x200"]).unwrap_err(),
+        "error: invalid value for --trace-range: 0x300-0x200, expected <start>-<end>");
+    assert!(run("range_ok", &["--cycles", "1", "--trace-range", "0x200-0x2FF"]).is_ok());
+}
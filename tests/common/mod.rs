@@ -0,0 +1,52 @@
+//! Fixtures shared by the integration tests. Every test crate uses only some of them.
+#![allow(dead_code)]
+
+use chip_8::{Chip, Variant, PROGRAM_START};
+
+/// A CHIP-8 machine with `program`, a list of opcodes, loaded at 0x200.
+pub fn chip(program: &[u16]) -> Chip
+{
+    chip_with(Variant::default(), program)
+}
+
+/// Like [`chip`] for another variant.
+pub fn chip_with(variant: Variant, program: &[u16]) -> Chip
+{
+    let mut chip = Chip::new();
+    chip.set_variant(variant);
+    let bytes : Vec<u8> = program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect();
+    chip.load_rom_bytes(&bytes).unwrap();
+    chip
+}
+
+/// Runs `chip` until the program counter reaches `end` or leaves the program.
+pub fn run_until(chip: &mut Chip, end: u16)
+{
+    for _ in 0..1000
+    {
+        if chip.program_counter() >= end || chip.program_counter() < PROGRAM_START || chip.is_halted()
+        {
+            return;
+        }
+        chip.emulate_cycle().unwrap();
+    }
+    panic!("program did not finish");
+}
+
+pub fn step(chip: &mut Chip, count: usize)
+{
+    for _ in 0..count
+    {
+        chip.emulate_cycle().unwrap();
+    }
+}
+
+/// The coordinates of every lit pixel, row by row.
+pub fn lit_pixels(chip: &Chip) -> Vec<(usize, usize)>
+{
+    let width = chip.screen_width();
+    chip.texture().iter().enumerate()
+        .filter(|(_, pixel)| **pixel != 0)
+        .map(|(index, _)| (index % width, index / width))
+        .collect()
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use chip_8::debugger::{Condition, Debugger, StopReason, WatchKind, Watchpoint};
+use chip_8::{AccessKind, Chip};
+
+fn value(chip: &Chip, expression: &str) -> i64
+{
+    expression.parse::<Condition>().unwrap().evaluate(chip)
+}
+
+fn parse_error(expression: &str) -> String
+{
+    expression.parse::<Condition>().unwrap_err()
+}
+
+/// A machine with V3 = 0x10, I = 0x204 and 0xAB at 0x206.
+fn machine() -> Chip
+{
+    let mut chip = Chip::new();
+    // LD V3, 0x10, LD I, 0x204
+    chip.load_rom_bytes(&[0x63, 0x10, 0xA2, 0x04, 0x00, 0x00, 0xAB]).unwrap();
+    chip.emulate_cycle().unwrap();
+    chip.emulate_cycle().unwrap();
+    chip
+}
+
+/// A program with a subroutine at 0x206 that calls another one at 0x20C.
+fn nested_calls() -> Debugger
+{
+    let chip = common::chip(&[
+        0x2206, // 0x200: CALL 0x206
+        0x7101, // 0x202: ADD V1, 1
+        0x1204, // 0x204: JP 0x204
+        0x7001, // 0x206: ADD V0, 1
+        0x220C, // 0x208: CALL 0x20C
+        0x00EE, // 0x20A: RET
+        0x7010, // 0x20C: ADD V0, 0x10
+        0x00EE, // 0x20E: RET
+    ]);
+    Debugger::new(chip, 12)
+}
+
+#[test]
+fn next_steps_over_calls()
+{
+    let mut debugger = nested_calls();
+    assert_eq!(debugger.step_over(1000).unwrap(), StopReason::Stepped);
+    assert_eq!(debugger.chip().program_counter(), 0x202);
+    assert_eq!(debugger.chip().stack_pointer(), 0);
+    assert_eq!(debugger.chip().registers()[0], 0x11);
+
+    // Other instructions are single steps.
+    assert_eq!(debugger.step_over(1000).unwrap(), StopReason::Stepped);
+    assert_eq!(debugger.chip().program_counter(), 0x204);
+    assert_eq!(debugger.chip().registers()[1], 1);
+}
+
+#[test]
+fn next_stops_at_breakpoints_in_the_callee()
+{
+    let mut debugger = nested_calls();
+    debugger.add_breakpoint(0x20C);
+    assert_eq!(debugger.step_over(1000).unwrap(), StopReason::Breakpoint(0x20C));
+    assert_eq!(debugger.chip().stack_pointer(), 2);
+    assert_eq!(debugger.chip().registers()[0], 1);
+}
+
+#[test]
+fn finish_returns_one_level_at_a_time()
+{
+    let mut debugger = nested_calls();
+    for _ in 0..3
+    {
+        debugger.step().unwrap();
+    }
+    assert_eq!(debugger.chip().program_counter(), 0x20C);
+
+    assert_eq!(debugger.run_until_return(1000).unwrap(), StopReason::Returned);
+    assert_eq!(debugger.chip().program_counter(), 0x20A);
+    assert_eq!(debugger.chip().stack_pointer(), 1);
+    assert_eq!(debugger.chip().registers()[0], 0x11);
+
+    assert_eq!(debugger.run_until_return(1000).unwrap(), StopReason::Returned);
+    assert_eq!(debugger.chip().program_counter(), 0x202);
+    assert_eq!(debugger.chip().stack_pointer(), 0);
+}
+
+#[test]
+fn operators_follow_c_precedence()
+{
+    let chip = Chip::new();
+    assert_eq!(value(&chip, "2 + 3 * 4"), 14);
+    assert_eq!(value(&chip, "(2 + 3) * 4"), 20);
+    assert_eq!(value(&chip, "1 << 2 + 1"), 8);
+    assert_eq!(value(&chip, "6 & 3 == 3"), 0);
+    assert_eq!(value(&chip, "1 | 2 ^ 3 & 1"), 3);
+    assert_eq!(value(&chip, "-2 * -3 - ~0 % 7"), 7);
+    assert_eq!(value(&chip, "!0 + !5"), 1);
+    assert_eq!(value(&chip, "0 || 2 && 3 < 4"), 1);
+    assert_eq!(value(&chip, "10 / 0 + 10 % 0"), 0);
+}
+
+#[test]
+fn numbers_are_written_like_in_the_assembler()
+{
+    let chip = Chip::new();
+    for number in ["512", "0x200", "0X200", "$200", "#200", "0b1000000000"]
+    {
+        assert_eq!(value(&chip, number), 512, "{}", number);
+    }
+    assert_eq!(value(&chip, "'A'"), 65);
+    assert_eq!(parse_error("0x"), "invalid number \"0x\"");
+    assert_eq!(parse_error("12ab"), "invalid number \"12ab\"");
+    assert_eq!(parse_error("$"), "unexpected character '$'");
+}
+
+#[test]
+fn registers_are_case_insensitive()
+{
+    let chip = machine();
+    assert_eq!(value(&chip, "v3"), 0x10);
+    assert_eq!(value(&chip, "V3 + vF"), 0x10);
+    assert_eq!(value(&chip, "i"), 0x204);
+    assert_eq!(value(&chip, "PC"), 0x204);
+    assert_eq!(value(&chip, "sp + dt + St"), 0);
+    assert_eq!(parse_error("VG"), "unknown name \"VG\"");
+    assert_eq!(parse_error("v10"), "unknown name \"v10\"");
+}
+
+#[test]
+fn brackets_read_memory()
+{
+    let chip = machine();
+    assert_eq!(value(&chip, "[I + 2]"), 0xAB);
+    assert_eq!(value(&chip, "[0x200]"), 0x63);
+    assert_eq!(value(&chip, "[[0x201] + 0x1F6]"), 0xAB);
+    // Outside of memory reads as zero.
+    assert_eq!(value(&chip, "[-1] + [0x10000]"), 0);
+    assert_eq!(parse_error("[I"), "missing ]");
+}
+
+#[test]
+fn conditions_hold_when_not_zero()
+{
+    let chip = machine();
+    let condition : Condition = "  v3 == 16 && [i + 2] > 0xA0  ".parse().unwrap();
+    assert!(condition.is_true(&chip));
+    assert_eq!(condition.to_string(), "v3 == 16 && [i + 2] > 0xA0");
+    assert!(!"v3 != 0x10".parse::<Condition>().unwrap().is_true(&chip));
+    assert_eq!(parse_error("v3 == "), "incomplete expression");
+    assert_eq!(parse_error("v3 v4"), "unexpected v4 in \"v3 v4\"");
+}
+
+#[test]
+fn conditions_stop_execution()
+{
+    let mut chip = Chip::new();
+    // ADD V0, 1, JP 0x200
+    chip.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+    let mut debugger = Debugger::new(chip, 12);
+    let index = debugger.add_condition("v0 == 5".parse().unwrap());
+    assert_eq!(debugger.run(1000).unwrap(), StopReason::Condition(index));
+    assert_eq!(debugger.chip().registers()[0], 5);
+
+    debugger.remove_condition(index);
+    debugger.add_conditional_breakpoint(0x202, "v0 >= 8".parse().unwrap());
+    assert_eq!(debugger.run(1000).unwrap(), StopReason::Breakpoint(0x202));
+    assert_eq!(debugger.chip().registers()[0], 8);
+}
+
+#[test]
+fn watchpoints_trigger_on_matching_accesses()
+{
+    let mut chip = Chip::new();
+    // LD I, 0x300, LD V0, [I], LD V0, 7, LD [I], V0, JP 0x208
+    chip.load_rom_bytes(&[0xA3, 0x00, 0xF0, 0x65, 0x60, 0x07, 0xF0, 0x55, 0x12, 0x08]).unwrap();
+    let mut debugger = Debugger::new(chip, 12);
+    debugger.add_watchpoint(Watchpoint{ start: 0x300, length: 1, kind: WatchKind::Write });
+    debugger.add_watchpoint(Watchpoint{ start: 0x301, length: 4, kind: WatchKind::Access });
+
+    match debugger.run(1000).unwrap()
+    {
+        StopReason::Watchpoint{ instruction, access } =>
+        {
+            assert_eq!(instruction, 0x206);
+            assert_eq!((access.kind, access.start, access.length), (AccessKind::Write, 0x300, 1));
+        },
+        reason => panic!("stopped for {:?}", reason),
+    }
+    assert_eq!(debugger.chip().memory()[0x300], 7);
+
+    // The read of 0x300 alone did not trigger the watchpoint on 0x301.
+    assert!(debugger.remove_watchpoint(0x300));
+    assert_eq!(debugger.run(100).unwrap(), StopReason::Limit);
+}
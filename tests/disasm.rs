@@ -0,0 +1,76 @@
+use chip_8::disasm::{self, Line};
+use chip_8::{Chip, Instruction, Variant};
+
+fn listing(bytes: &[u8], variant: Variant) -> String
+{
+    disasm::format_listing(&disasm::disassemble(bytes, 0x200, variant))
+}
+
+#[test]
+fn jump_and_call_targets_get_labels()
+{
+    // CLS, CALL 0x208, JP 0x202, JP 0x300, RET
+    let bytes = [0x00, 0xE0, 0x22, 0x08, 0x12, 0x02, 0x13, 0x00, 0x00, 0xEE];
+    assert_eq!(listing(&bytes, Variant::Chip8), "\
+0200  00E0        CLS
+L0202:
+0202  2208        CALL 0x208
+0204  1202        JP 0x202
+0206  1300        JP 0x300
+L0208:
+0208  00EE        RET
+");
+}
+
+#[test]
+fn targets_inside_an_instruction_get_no_label()
+{
+    // JP 0x201 points into the middle of the jump itself.
+    assert_eq!(listing(&[0x12, 0x01], Variant::Chip8), "0200  1201        JP 0x201\n");
+}
+
+#[test]
+fn words_that_do_not_decode_are_data()
+{
+    // LD V0, 1, a sprite row pair, a trailing byte.
+    let bytes = [0x60, 0x01, 0xFF, 0xFF, 0x81];
+    let lines = disasm::disassemble(&bytes, 0x200, Variant::Chip8);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1], Line{ address: 0x202, bytes: vec![0xFF, 0xFF], instruction: None });
+    assert_eq!(listing(&bytes, Variant::Chip8), "\
+0200  6001        LD V0, 0x01
+0202  FFFF        DW 0xFFFF
+0204  81          DB 0x81
+");
+}
+
+#[test]
+fn decoding_follows_the_variant()
+{
+    let bytes = [0x00, 0xFF, 0xF0, 0x00, 0x12, 0x34];
+    let chip8 = disasm::disassemble(&bytes, 0x200, Variant::Chip8);
+    assert_eq!(chip8.iter().map(|line| line.instruction.is_some()).collect::<Vec<_>>(), [false, false, true]);
+
+    let xochip = disasm::disassemble(&bytes, 0x200, Variant::XoChip);
+    assert_eq!(xochip.len(), 2);
+    assert_eq!(xochip[1].instruction, Some(Instruction::LongIndex{ nnnn: 0x1234 }));
+    assert_eq!(xochip[1].to_string(), "0202  F0001234    LD I, LONG 0x1234");
+}
+
+#[test]
+fn long_loads_cut_off_by_the_range_are_data()
+{
+    let lines = disasm::disassemble(&[0xF0, 0x00], 0x200, Variant::XoChip);
+    assert_eq!(lines, [Line{ address: 0x200, bytes: vec![0xF0, 0x00], instruction: None }]);
+}
+
+#[test]
+fn memory_ranges_stop_at_the_end_of_memory()
+{
+    let mut chip = Chip::new();
+    chip.load_rom_bytes(&[0x00, 0xE0]).unwrap();
+    let lines = disasm::disassemble_memory(&chip, 0x200, 2);
+    assert_eq!(lines[0].instruction, Some(Instruction::ClearScreen));
+    assert_eq!(disasm::disassemble_memory(&chip, 0xFFC, 100).len(), 2);
+    assert!(disasm::disassemble_memory(&chip, 0x1000, 2).is_empty());
+}
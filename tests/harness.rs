@@ -0,0 +1,142 @@
+mod common;
+
+use chip_8::harness::{Harness, KeyEvent, KeyScript, Screen};
+use chip_8::{Chip, Palette, Rgb, Variant};
+
+use common::chip;
+
+/// Draws the font digit of the key pressed with FX0A at (0, 0) and waits for the next one.
+fn key_echo() -> Chip
+{
+    // 0x200: LD V0, K, 0x202: CLS, 0x204: LD F, V0, 0x206: DRW V1, V1, 5, 0x208: JP 0x200
+    chip(&[0xF00A, 0x00E0, 0xF029, 0xD115, 0x1200])
+}
+
+fn text_screen(rows: &[&str], width: usize, height: usize) -> Screen
+{
+    let mut text = String::new();
+    for y in 0..height
+    {
+        let row = rows.get(y).copied().unwrap_or("");
+        text += &format!("{:.<width$}\n", row, width = width);
+    }
+    text.parse().unwrap()
+}
+
+#[test]
+fn runs_the_requested_number_of_frames()
+{
+    let mut harness = Harness::new(key_echo(), 10);
+    let outcome = harness.run(30).unwrap();
+    assert_eq!((outcome.frames, outcome.cycles, outcome.halted), (30, 300, false));
+
+    // Running further continues from there.
+    let outcome = harness.run(40).unwrap();
+    assert_eq!(outcome.frames, 40);
+}
+
+#[test]
+fn stops_when_the_program_exits()
+{
+    let mut chip = chip(&[0x6001, 0x00FD]);
+    chip.set_variant(Variant::SuperChip);
+    let mut harness = Harness::new(chip, 10);
+    let outcome = harness.run(100).unwrap();
+    assert_eq!((outcome.frames, outcome.cycles, outcome.halted), (0, 2, true));
+}
+
+#[test]
+fn reports_faults()
+{
+    let mut harness = Harness::new(chip(&[0x00EE]), 10);
+    assert!(harness.run(1).is_err());
+}
+
+#[test]
+fn presses_scripted_keys()
+{
+    let script : KeyScript = "\
+        # the first key
+        2 press 7
+        10 down a   # held for a while
+        20 up a
+    ".parse().unwrap();
+    assert_eq!(script.events().len(), 4);
+    assert_eq!(script.events()[1], KeyEvent{ frame: 3, key: 7, pressed: false });
+
+    let mut harness = Harness::new(key_echo(), 10);
+    harness.set_script(script);
+    harness.run(5).unwrap();
+    assert_eq!(harness.chip().registers()[0], 7);
+    harness.run(30).unwrap();
+    assert_eq!(harness.chip().registers()[0], 0xA);
+
+    let expected = text_screen(&["####", "#..#", "####", "#..#", "#..#"], 64, 32);
+    assert_eq!(harness.screen().diff(&expected), None);
+}
+
+#[test]
+fn rejects_malformed_scripts()
+{
+    assert!("x press 1".parse::<KeyScript>().is_err());
+    assert!("1 press g".parse::<KeyScript>().is_err());
+    assert!("1 hold 1".parse::<KeyScript>().is_err());
+    assert!("1 up 1 2".parse::<KeyScript>().is_err());
+    assert_eq!("\n18446744073709551615 press 1".parse::<KeyScript>().unwrap_err(), "line 2: frame out of range");
+    assert_eq!("18446744073709551614 press 1".parse::<KeyScript>().unwrap().events()[1].frame, u64::MAX);
+}
+
+#[test]
+fn text_screens_round_trip()
+{
+    let screen = text_screen(&["#+*.", ".#"], 8, 3);
+    assert_eq!((screen.width(), screen.height()), (8, 3));
+    assert_eq!(screen.pixels()[0..4], [1, 2, 3, 0]);
+    assert_eq!(screen.to_string().parse::<Screen>().unwrap(), screen);
+
+    assert!("##\n#\n".parse::<Screen>().is_err());
+    assert!("#x\n".parse::<Screen>().is_err());
+}
+
+#[test]
+fn png_screens_round_trip_and_scale()
+{
+    let mut harness = Harness::new(key_echo(), 10);
+    let mut script = KeyScript::new();
+    script.press(1, 3, 2);
+    harness.set_script(script);
+    harness.run(10).unwrap();
+    let screen = harness.screen();
+    assert!(screen.pixels().contains(&1));
+
+    let palette = Palette::default();
+    let png = screen.to_png(&palette);
+    assert_eq!(Screen::from_png(&png, 64, 32, &palette).unwrap(), screen);
+
+    // Any integer scale is accepted, so the same image also reads as a 32x16 screen.
+    let doubled = Screen::from_png(&png, 32, 16, &palette).unwrap();
+    assert_eq!((doubled.width(), doubled.height()), (32, 16));
+    assert!(Screen::from_png(&png, 48, 24, &palette).is_err());
+
+    let other = Palette{ foreground: Rgb(0x00, 0xFF, 0x00), ..palette };
+    assert!(Screen::from_png(&png, 64, 32, &other).is_err());
+}
+
+#[test]
+fn diffs_show_the_differing_rows()
+{
+    let actual = text_screen(&["", ".##", "#"], 4, 3);
+    let expected = text_screen(&["", ".#.#", "#"], 4, 3);
+    let diff = actual.diff(&expected).unwrap();
+    assert_eq!(diff.pixels(), [(2, 1), (3, 1)]);
+    assert_eq!(diff.to_string(), "\
+2 of 12 pixels differ, the first at (2, 1)
+row 1:
+  expected .#.#
+  actual   .##.
+             ^^
+");
+
+    let small = text_screen(&[], 2, 2);
+    assert_eq!(actual.diff(&small).unwrap().to_string(), "the screen is 4x3 but the reference is 2x2\n");
+}
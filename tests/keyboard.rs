@@ -0,0 +1,27 @@
+use chip_8::terminal::{decode_keys, HostKey};
+
+#[test]
+fn plain_and_control_keys()
+{
+    assert_eq!(decode_keys(b"a1\r\x7f\x03"), [
+        HostKey::Char('a'), HostKey::Char('1'), HostKey::Enter, HostKey::Backspace, HostKey::Ctrl('c'),
+    ]);
+    assert_eq!(decode_keys("é".as_bytes()), [HostKey::Char('é')]);
+}
+
+#[test]
+fn arrow_sequences()
+{
+    assert_eq!(decode_keys(b"\x1b[A\x1b[B\x1bOC\x1b[1;5D"), [HostKey::Up, HostKey::Down, HostKey::Right, HostKey::Left]);
+    // Other sequences, such as F5, are dropped.
+    assert_eq!(decode_keys(b"\x1b[15~x"), [HostKey::Char('x')]);
+}
+
+#[test]
+fn only_a_lone_escape_is_escape()
+{
+    assert_eq!(decode_keys(b"\x1b"), [HostKey::Escape]);
+    assert_eq!(decode_keys(b"\x1b\x1b"), [HostKey::Escape, HostKey::Escape]);
+    // Alt+Q and Alt+1 neither quit nor type.
+    assert_eq!(decode_keys(b"\x1bq\x1b1w"), [HostKey::Char('w')]);
+}
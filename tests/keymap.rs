@@ -0,0 +1,108 @@
+use chip_8::keymap::{Keymap, KeymapConfig, KeymapError, Preset};
+use chip_8::terminal::HostKey;
+
+fn config(text: &str) -> KeymapConfig
+{
+    text.parse().unwrap()
+}
+
+fn error(text: &str) -> KeymapError
+{
+    text.parse::<KeymapConfig>().unwrap_err()
+}
+
+#[test]
+fn presets_cover_the_keypad()
+{
+    for preset in [Preset::Qwerty, Preset::Azerty, Preset::Numpad]
+    {
+        let keymap = Keymap::preset(preset);
+        let mut keys : Vec<u8> = "1234qwerasdfzxcvazwx789/456*-0.+ ".chars()
+            .filter_map(|c| keymap.get(HostKey::Char(c)))
+            .chain(keymap.get(HostKey::Enter))
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys, (0..16).collect::<Vec<u8>>(), "{:?}", preset);
+    }
+    let qwerty = Keymap::default();
+    assert_eq!(qwerty.get(HostKey::Char('x')), Some(0x0));
+    assert_eq!(qwerty.get(HostKey::Char('V')), Some(0xF));
+    assert_eq!(Keymap::preset(Preset::Azerty).get(HostKey::Char('é')), Some(0x2));
+}
+
+#[test]
+fn config_lines_adjust_the_base_keymap()
+{
+    let config = config("\
+# a comment
+preset = azerty     # trailing comment
+space = 5
+UP = 0x4
+q = none
+");
+    let keymap = config.keymap_for(Keymap::default(), None);
+    assert_eq!(keymap.get(HostKey::Char('a')), Some(0x4));
+    assert_eq!(keymap.get(HostKey::Char(' ')), Some(0x5));
+    assert_eq!(keymap.get(HostKey::Up), Some(0x4));
+    assert_eq!(keymap.get(HostKey::Char('q')), None);
+}
+
+#[test]
+fn rom_sections_override_the_global_lines()
+{
+    let config = config("\
+space = 5
+[tetris.ch8]
+space = 6
+left = 4
+[pong.ch8]
+preset = numpad
+");
+    let tetris = config.keymap_for(Keymap::default(), Some("tetris.ch8"));
+    assert_eq!(tetris.get(HostKey::Char(' ')), Some(0x6));
+    assert_eq!(tetris.get(HostKey::Left), Some(0x4));
+
+    let other = config.keymap_for(Keymap::default(), Some("other.ch8"));
+    assert_eq!(other.get(HostKey::Char(' ')), Some(0x5));
+    assert_eq!(other.get(HostKey::Left), None);
+
+    // A preset in a section replaces everything before it.
+    let pong = config.keymap_for(Keymap::default(), Some("pong.ch8"));
+    assert_eq!(pong.get(HostKey::Char(' ')), None);
+    assert_eq!(pong.get(HostKey::Char('7')), Some(0x1));
+}
+
+#[test]
+fn duplicate_keys_keep_the_last_binding()
+{
+    let config = config("\
+x = 1
+x = 2
+X = 3
+[game.ch8]
+left = 4
+[game.ch8]
+left = 5
+");
+    assert_eq!(config.keymap_for(Keymap::empty(), None).get(HostKey::Char('x')), Some(0x3));
+    assert_eq!(config.keymap_for(Keymap::empty(), Some("game.ch8")).get(HostKey::Left), Some(0x5));
+    // Several host keys may share a keypad key.
+    let shared = config.keymap_for(Keymap::default(), None);
+    assert_eq!(shared.get(HostKey::Char('x')), Some(0x3));
+    assert_eq!(shared.get(HostKey::Char('3')), Some(0x3));
+}
+
+#[test]
+fn bad_lines_are_reported_with_their_number()
+{
+    assert_eq!(error("x = 1\n\ny = G"), KeymapError{ line: 3, message: "invalid keypad key \"G\", expected 0-F or none".to_string() });
+    assert_eq!(error("x = 0x10").message, "invalid keypad key \"0x10\", expected 0-F or none");
+    assert_eq!(error("pgup = 1").message, "unknown host key \"pgup\"");
+    assert_eq!(error("Backspace = 1").message, "backspace is reserved by the frontend and cannot be bound");
+    assert_eq!(error("x 1").message, "expected `key = value`, found \"x 1\"");
+    assert_eq!(error("x =").message, "expected `key = value`, found \"x =\"");
+    assert_eq!(error("[game.ch8").message, "unterminated section header");
+    assert_eq!(error("\n# only a comment\npreset = dvorak").line, 3);
+    assert_eq!(error("a = b = c").to_string(), "line 1: invalid keypad key \"b = c\", expected 0-F or none");
+}
@@ -0,0 +1,62 @@
+use chip_8::movie::Movie;
+use chip_8::savestate::crc32;
+use chip_8::{Chip, Chip8Error};
+
+fn movie(frames: Vec<u16>) -> Movie
+{
+    Movie{
+        instructions_per_frame: 10,
+        start_state: Chip::new().save_state(),
+        frames,
+        cycles: 0,
+        final_hash: 0,
+        error: None,
+    }
+}
+
+/// The movie with the data of its KEYS chunk, the last one, replaced by `keys`.
+fn with_keys(movie: &Movie, keys: &[u8]) -> Vec<u8>
+{
+    let mut bytes = movie.to_bytes();
+    let tag = bytes.windows(4).rposition(|window| window == b"KEYS").unwrap();
+    bytes.truncate(tag + 4);
+    bytes.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(keys);
+    let checksum = crc32(&bytes);
+    bytes.extend_from_slice(&checksum.to_be_bytes());
+    bytes
+}
+
+fn load_error(bytes: &[u8]) -> String
+{
+    match Movie::from_bytes(bytes)
+    {
+        Err(Chip8Error::InvalidMovie{ reason }) => reason,
+        Err(error) => panic!("unexpected error {}", error),
+        Ok(_) => panic!("the movie loaded"),
+    }
+}
+
+#[test]
+fn movies_round_trip()
+{
+    let movie = movie(vec![0, 0, 0, 0x20, 0x20, 0, 0x8001]);
+    assert_eq!(Movie::from_bytes(&movie.to_bytes()).unwrap(), movie);
+}
+
+#[test]
+fn truncated_keypad_runs_are_rejected()
+{
+    let movie = movie(vec![0x20; 3]);
+    assert_eq!(Movie::from_bytes(&with_keys(&movie, &[0, 0, 0, 3, 0, 0x20])).unwrap().frames, [0x20; 3]);
+    assert_eq!(load_error(&with_keys(&movie, &[0, 0, 0, 3, 0, 0x20, 0, 0])), "chunk KEYS is too short");
+    assert_eq!(load_error(&with_keys(&movie, &[0, 0, 0, 3, 0])), "chunk KEYS is too short");
+}
+
+#[test]
+fn overlong_movies_are_rejected()
+{
+    let movie = movie(Vec::new());
+    let keys = [0xFF, 0xFF, 0xFF, 0xFF, 0, 0x20, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0x20];
+    assert_eq!(load_error(&with_keys(&movie, &keys)), "the movie is longer than 5184000 frames");
+}
@@ -0,0 +1,441 @@
+//! Every CHIP-8 instruction against its specified behavior, one or more tests per opcode.
+//! The quirks and the SUPER-CHIP and XO-CHIP instructions have their own files.
+//!
+//! Programs are given as lists of opcodes, loaded at 0x200 and run until the program counter
+//! leaves them. The machine runs CHIP-8 with all quirks off.
+
+mod common;
+
+use chip_8::{Chip, Chip8Error, Random, RandomKind, PROGRAM_START};
+use common::{lit_pixels, run_until, step};
+
+fn run(program: &[u16]) -> Chip
+{
+    let mut chip = common::chip(program);
+    run_until(&mut chip, PROGRAM_START + 2 * program.len() as u16);
+    chip
+}
+
+// 0x0NNN
+
+#[test]
+fn op_00e0_clears_the_screen()
+{
+    // Draw the 0 of the font, then clear.
+    let chip = run(&[0xA000, 0xD005, 0x00E0]);
+    assert!(lit_pixels(&chip).is_empty());
+}
+
+#[test]
+fn op_00ee_returns_from_a_subroutine()
+{
+    // 0x200: CALL 0x206, 0x202: LD V1, 1, 0x204: JP 0x20A, 0x206: LD V0, 1, 0x208: RET
+    let mut chip = common::chip(&[0x2206, 0x6101, 0x120A, 0x6001, 0x00EE]);
+    step(&mut chip, 3);
+    assert_eq!(chip.program_counter(), 0x202);
+    assert_eq!(chip.stack_pointer(), 0);
+    step(&mut chip, 2);
+    assert_eq!(chip.registers()[0..2], [1, 1]);
+    assert_eq!(chip.program_counter(), 0x20A);
+}
+
+#[test]
+fn op_00ee_with_an_empty_stack_fails()
+{
+    let mut chip = common::chip(&[0x00EE]);
+    assert!(matches!(chip.emulate_cycle(), Err(Chip8Error::StackUnderflow{ address: 0x200 })));
+    assert_eq!(chip.program_counter(), 0x200);
+}
+
+#[test]
+fn op_0nnn_is_not_supported()
+{
+    let mut chip = common::chip(&[0x0123]);
+    assert!(matches!(chip.emulate_cycle(), Err(Chip8Error::UnknownOpcode{ opcode: 0x0123, address: 0x200 })));
+    assert_eq!(chip.program_counter(), 0x200);
+}
+
+// 0x1NNN - 0x2NNN
+
+#[test]
+fn op_1nnn_jumps()
+{
+    let mut chip = common::chip(&[0x1ABC]);
+    step(&mut chip, 1);
+    assert_eq!(chip.program_counter(), 0xABC);
+}
+
+#[test]
+fn op_2nnn_calls_a_subroutine()
+{
+    let mut chip = common::chip(&[0x0000, 0x2ABC]);
+    chip.load_rom_bytes(&[0x12, 0x02, 0x2A, 0xBC]).unwrap();
+    step(&mut chip, 2);
+    assert_eq!(chip.program_counter(), 0xABC);
+    assert_eq!(chip.stack(), [0x204]);
+    assert_eq!(chip.stack_pointer(), 1);
+}
+
+#[test]
+fn op_2nnn_fails_beyond_16_levels()
+{
+    // CALL 0x200 calls itself forever.
+    let mut chip = common::chip(&[0x2200]);
+    step(&mut chip, 16);
+    assert!(matches!(chip.emulate_cycle(), Err(Chip8Error::StackOverflow{ address: 0x200 })));
+    assert_eq!(chip.stack_pointer(), 16);
+}
+
+// 0x3XNN - 0x5XY0, 0x9XY0
+
+#[test]
+fn op_3xnn_skips_if_equal()
+{
+    let chip = run(&[0x6042, 0x3042, 0x6101, 0x3043, 0x6201]);
+    assert_eq!(chip.registers()[1..3], [0, 1]);
+}
+
+#[test]
+fn op_4xnn_skips_if_not_equal()
+{
+    let chip = run(&[0x6042, 0x4043, 0x6101, 0x4042, 0x6201]);
+    assert_eq!(chip.registers()[1..3], [0, 1]);
+}
+
+#[test]
+fn op_5xy0_skips_if_registers_equal()
+{
+    let chip = run(&[0x6007, 0x6107, 0x5010, 0x6201, 0x5020, 0x6301]);
+    assert_eq!(chip.registers()[2..4], [0, 1]);
+}
+
+#[test]
+fn op_9xy0_skips_if_registers_not_equal()
+{
+    let chip = run(&[0x6007, 0x6107, 0x9010, 0x6201, 0x9020, 0x6301]);
+    assert_eq!(chip.registers()[2..4], [1, 0]);
+}
+
+#[test]
+fn op_5xy1_is_not_supported()
+{
+    let mut chip = common::chip(&[0x5011]);
+    assert!(matches!(chip.emulate_cycle(), Err(Chip8Error::UnknownOpcode{ .. })));
+}
+
+// 0x6XNN - 0x7XNN
+
+#[test]
+fn op_6xnn_loads_a_constant()
+{
+    let chip = run(&[0x6A42]);
+    assert_eq!(chip.registers()[0xA], 0x42);
+}
+
+#[test]
+fn op_7xnn_adds_without_carry_flag()
+{
+    let chip = run(&[0x6F55, 0x61FF, 0x7102, 0x7203]);
+    assert_eq!(chip.registers()[1..3], [0x01, 0x03]);
+    assert_eq!(chip.registers()[0xF], 0x55);
+}
+
+// 0x8XYN
+
+#[test]
+fn op_8xy0_copies()
+{
+    let chip = run(&[0x6142, 0x8010]);
+    assert_eq!(chip.registers()[0], 0x42);
+}
+
+#[test]
+fn op_8xy1_8xy2_8xy3_logic()
+{
+    let chip = run(&[0x60F0, 0x613C, 0x6F55, 0x8011]);
+    assert_eq!(chip.registers()[0], 0xFC);
+    assert_eq!(chip.registers()[0xF], 0x55);
+
+    let chip = run(&[0x60F0, 0x613C, 0x8012]);
+    assert_eq!(chip.registers()[0], 0x30);
+    let chip = run(&[0x60F0, 0x613C, 0x8013]);
+    assert_eq!(chip.registers()[0], 0xCC);
+}
+
+#[test]
+fn op_8xy4_adds_with_carry()
+{
+    let chip = run(&[0x60F0, 0x6120, 0x8014]);
+    assert_eq!(chip.registers()[0], 0x10);
+    assert_eq!(chip.registers()[0xF], 1);
+
+    let chip = run(&[0x6010, 0x6120, 0x6F55, 0x8014]);
+    assert_eq!(chip.registers()[0], 0x30);
+    assert_eq!(chip.registers()[0xF], 0);
+}
+
+#[test]
+fn op_8xy4_flag_wins_over_the_result_in_vf()
+{
+    let chip = run(&[0x6FFF, 0x6102, 0x8F14]);
+    assert_eq!(chip.registers()[0xF], 1);
+}
+
+#[test]
+fn op_8xy5_subtracts_with_not_borrow()
+{
+    let chip = run(&[0x6005, 0x6103, 0x8015]);
+    assert_eq!(chip.registers()[0], 2);
+    assert_eq!(chip.registers()[0xF], 1);
+
+    let chip = run(&[0x6003, 0x6105, 0x8015]);
+    assert_eq!(chip.registers()[0], 0xFE);
+    assert_eq!(chip.registers()[0xF], 0);
+
+    let chip = run(&[0x6005, 0x6105, 0x8015]);
+    assert_eq!(chip.registers()[0], 0);
+    assert_eq!(chip.registers()[0xF], 1);
+}
+
+#[test]
+fn op_8xy7_subtracts_reversed_with_not_borrow()
+{
+    let chip = run(&[0x6003, 0x6105, 0x8017]);
+    assert_eq!(chip.registers()[0], 2);
+    assert_eq!(chip.registers()[0xF], 1);
+
+    let chip = run(&[0x6005, 0x6103, 0x8017]);
+    assert_eq!(chip.registers()[0], 0xFE);
+    assert_eq!(chip.registers()[0xF], 0);
+}
+
+#[test]
+fn op_8xy6_shifts_right()
+{
+    let chip = run(&[0x6005, 0x61F0, 0x8016]);
+    assert_eq!(chip.registers()[0], 0x02);
+    assert_eq!(chip.registers()[0xF], 1);
+
+    let chip = run(&[0x6004, 0x8016]);
+    assert_eq!(chip.registers()[0], 0x02);
+    assert_eq!(chip.registers()[0xF], 0);
+}
+
+#[test]
+fn op_8xye_shifts_left()
+{
+    let chip = run(&[0x6081, 0x801E]);
+    assert_eq!(chip.registers()[0], 0x02);
+    assert_eq!(chip.registers()[0xF], 1);
+
+    let chip = run(&[0x6041, 0x6F55, 0x801E]);
+    assert_eq!(chip.registers()[0], 0x82);
+    assert_eq!(chip.registers()[0xF], 0);
+}
+
+#[test]
+fn op_8xy8_is_not_supported()
+{
+    let mut chip = common::chip(&[0x8018]);
+    assert!(matches!(chip.emulate_cycle(), Err(Chip8Error::UnknownOpcode{ .. })));
+}
+
+// 0xANNN - 0xCXNN
+
+#[test]
+fn op_annn_sets_the_index()
+{
+    let chip = run(&[0xA123]);
+    assert_eq!(chip.index_register(), 0x123);
+}
+
+#[test]
+fn op_bnnn_jumps_with_offset()
+{
+    let mut chip = common::chip(&[0x6010, 0x6220, 0xB300]);
+    step(&mut chip, 3);
+    assert_eq!(chip.program_counter(), 0x310);
+}
+
+#[test]
+fn op_cxnn_masks_a_random_byte()
+{
+    let mut chip = common::chip(&[0xC00F, 0xC100, 0x6FFF, 0xC2FF]);
+    chip.set_random(Random::new(RandomKind::Xorshift, 7));
+    run_until(&mut chip, 0x208);
+    assert!(chip.registers()[0] <= 0x0F);
+    assert_eq!(chip.registers()[1], 0);
+    assert_eq!(chip.registers()[0xF], 0xFF);
+}
+
+#[test]
+fn op_cxnn_is_reproducible_with_a_seed()
+{
+    for kind in [RandomKind::Xorshift, RandomKind::CosmacVip]
+    {
+        let program : Vec<u16> = (0..16).map(|x| 0xC0FF | x << 8).collect();
+        let mut first = common::chip(&program);
+        let mut second = common::chip(&program);
+        first.set_random(Random::new(kind, 1234));
+        second.set_random(Random::new(kind, 1234));
+        step(&mut first, 16);
+        step(&mut second, 16);
+        assert_eq!(first.registers(), second.registers());
+        assert!(first.registers().iter().any(|value| *value != first.registers()[0]));
+    }
+}
+
+// 0xDXYN
+
+#[test]
+fn op_dxyn_draws_a_sprite()
+{
+    // The 1 of the font: 20 60 20 20 70.
+    let chip = run(&[0x6001, 0xF029, 0x6102, 0x6203, 0xD125]);
+    let expected = [(4, 3), (3, 4), (4, 4), (4, 5), (4, 6), (3, 7), (4, 7), (5, 7)];
+    assert_eq!(lit_pixels(&chip), expected);
+    assert_eq!(chip.registers()[0xF], 0);
+}
+
+#[test]
+fn op_dxyn_takes_the_height_from_the_low_nibble()
+{
+    // Draws one row of the 0 of the font even though Y is 2.
+    let chip = run(&[0xA000, 0xD121]);
+    assert_eq!(lit_pixels(&chip), [(0, 0), (1, 0), (2, 0), (3, 0)]);
+}
+
+#[test]
+fn op_dxyn_xors_and_reports_collisions()
+{
+    let chip = run(&[0xA000, 0xD005, 0xD005]);
+    assert!(lit_pixels(&chip).is_empty());
+    assert_eq!(chip.registers()[0xF], 1);
+
+    // Overlapping by one column only still collides.
+    let chip = run(&[0xA000, 0xD005, 0x6003, 0x6F00, 0xD005]);
+    assert_eq!(chip.registers()[0xF], 1);
+}
+
+#[test]
+fn op_dxyn_wraps_the_start_and_the_edges()
+{
+    // x = 66 starts at 2 and y = 34 at 2.
+    let chip = run(&[0xA000, 0x6042, 0x6122, 0xD011]);
+    assert_eq!(lit_pixels(&chip), [(2, 2), (3, 2), (4, 2), (5, 2)]);
+
+    // Starting at x = 62 the sprite wraps around to the left edge.
+    let chip = run(&[0xA000, 0x603E, 0xD011]);
+    assert_eq!(lit_pixels(&chip), [(0, 0), (1, 0), (62, 0), (63, 0)]);
+}
+
+// 0xEX9E - 0xEXA1
+
+#[test]
+fn op_ex9e_exa1_skip_on_keys()
+{
+    let program = [0x6005, 0xE09E, 0x6101, 0xE0A1, 0x6201];
+    let chip = run(&program);
+    assert_eq!(chip.registers()[1..3], [1, 0]);
+
+    let mut chip = common::chip(&program);
+    chip.press_key(5);
+    run_until(&mut chip, 0x20A);
+    assert_eq!(chip.registers()[1..3], [0, 1]);
+}
+
+// 0xFXNN
+
+#[test]
+fn op_fx07_fx15_delay_timer()
+{
+    let mut chip = common::chip(&[0x6030, 0xF015, 0xF107]);
+    step(&mut chip, 2);
+    chip.tick_timers();
+    step(&mut chip, 1);
+    assert_eq!(chip.delay_timer(), 0x2F);
+    assert_eq!(chip.registers()[1], 0x2F);
+}
+
+#[test]
+fn op_fx18_sound_timer()
+{
+    let mut chip = common::chip(&[0x6002, 0xF018]);
+    step(&mut chip, 2);
+    assert_eq!(chip.sound_timer(), 2);
+    chip.tick_timers();
+    assert!(chip.is_sound_active());
+    chip.tick_timers();
+    chip.tick_timers();
+    assert_eq!(chip.sound_timer(), 0);
+    assert!(!chip.is_sound_active());
+}
+
+#[test]
+fn op_fx0a_waits_for_press_and_release()
+{
+    let mut chip = common::chip(&[0xF30A]);
+    // A key that is already down does not count.
+    chip.press_key(1);
+    step(&mut chip, 3);
+    assert!(chip.is_waiting_for_key());
+    assert_eq!(chip.program_counter(), 0x200);
+
+    chip.press_key(7);
+    step(&mut chip, 3);
+    assert_eq!(chip.program_counter(), 0x200);
+    chip.release_key(7);
+    step(&mut chip, 1);
+    assert_eq!(chip.registers()[3], 7);
+    assert_eq!(chip.program_counter(), 0x202);
+}
+
+#[test]
+fn op_fx1e_adds_vx_to_the_index()
+{
+    let chip = run(&[0xA300, 0x6110, 0x6F55, 0xF11E]);
+    assert_eq!(chip.index_register(), 0x310);
+    assert_eq!(chip.registers()[0xF], 0x55);
+}
+
+#[test]
+fn op_fx29_points_at_the_font()
+{
+    let chip = run(&[0x600A, 0xF029]);
+    assert_eq!(chip.index_register(), 50);
+    assert_eq!(chip.memory()[50..55], [0xF0, 0x90, 0xF0, 0x90, 0x90]);
+
+    // Only the low nibble counts.
+    let chip = run(&[0x601A, 0xF029]);
+    assert_eq!(chip.index_register(), 50);
+}
+
+#[test]
+fn op_fx33_stores_bcd()
+{
+    let chip = run(&[0x607B, 0xA300, 0xF033]);
+    assert_eq!(chip.memory()[0x300..0x303], [1, 2, 3]);
+    assert_eq!(chip.index_register(), 0x300);
+
+    let chip = run(&[0x6009, 0xA300, 0xF033]);
+    assert_eq!(chip.memory()[0x300..0x303], [0, 0, 9]);
+}
+
+#[test]
+fn op_fx55_fx65_store_and_load()
+{
+    let chip = run(&[0x6011, 0x6122, 0x6233, 0xA300, 0xF155, 0xA300, 0xF265]);
+    assert_eq!(chip.memory()[0x300..0x303], [0x11, 0x22, 0]);
+    assert_eq!(chip.registers()[0..3], [0x11, 0x22, 0]);
+    assert_eq!(chip.index_register(), 0x300);
+}
+
+#[test]
+fn op_fx55_past_the_end_of_memory_fails()
+{
+    let mut chip = common::chip(&[0xAFFF, 0xF155]);
+    step(&mut chip, 1);
+    assert!(matches!(chip.emulate_cycle(), Err(Chip8Error::MemoryOutOfBounds{ address: 0x202, .. })));
+    assert_eq!(chip.program_counter(), 0x202);
+}
+
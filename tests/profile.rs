@@ -0,0 +1,110 @@
+mod common;
+
+use chip_8::profile::{HotSpot, Profiler, SubroutineProfile};
+use chip_8::{Chip, Chip8Error};
+
+fn chip(program: &[u16]) -> Chip
+{
+    let mut chip = common::chip(program);
+    chip.set_profiler(Some(Profiler::new()));
+    chip
+}
+
+fn run(chip: &mut Chip, cycles: usize)
+{
+    for _ in 0..cycles
+    {
+        chip.emulate_cycle().unwrap();
+    }
+}
+
+/// The main loop calls 0x208, which calls 0x20E, and then calls 0x20E directly.
+const NESTED : [u16; 9] = [0x2208, 0x220E, 0x1200, 0x0000, 0x7001, 0x220E, 0x00EE, 0x7101, 0x00EE];
+
+#[test]
+fn hot_spots_are_sorted_by_count()
+{
+    let mut chip = chip(&NESTED);
+    run(&mut chip, 100);
+    let profiler = chip.profiler().unwrap();
+    assert_eq!(profiler.total(), 100);
+    assert_eq!(profiler.hot_spots()[..3], [
+        HotSpot{ address: 0x20E, count: 20 },
+        HotSpot{ address: 0x210, count: 20 },
+        HotSpot{ address: 0x200, count: 10 },
+    ]);
+    assert_eq!(profiler.hot_spots().len(), 8);
+    assert_eq!(profiler.count(0x206), 0);
+}
+
+#[test]
+fn subroutines_get_their_own_and_their_callees_instructions()
+{
+    let mut chip = chip(&NESTED);
+    run(&mut chip, 100);
+    assert_eq!(chip.profiler().unwrap().subroutines(), [
+        SubroutineProfile{ entry: 0x208, calls: 10, self_count: 30, total_count: 50 },
+        SubroutineProfile{ entry: 0x20E, calls: 20, self_count: 40, total_count: 40 },
+    ]);
+}
+
+#[test]
+fn folded_stacks_follow_the_calls()
+{
+    let mut chip = chip(&NESTED);
+    run(&mut chip, 100);
+    assert_eq!(chip.profiler().unwrap().folded(), "\
+main 30
+main;sub_0208 30
+main;sub_0208;sub_020E 20
+main;sub_020E 20
+");
+}
+
+#[test]
+fn recursion_is_counted_once_in_the_total()
+{
+    // 0x206 counts V0 down from 3 to zero, calling itself for every step.
+    let mut chip = chip(&[0x6003, 0x2206, 0x1204, 0x3000, 0x120C, 0x00EE, 0x70FF, 0x2206, 0x00EE]);
+    run(&mut chip, 19);
+    let profiler = chip.profiler().unwrap();
+    assert_eq!(profiler.subroutines(), [SubroutineProfile{ entry: 0x206, calls: 4, self_count: 17, total_count: 17 }]);
+    assert_eq!(profiler.folded().lines().last(), Some("main;sub_0206;sub_0206;sub_0206;sub_0206 2"));
+}
+
+#[test]
+fn faulting_instructions_are_not_counted()
+{
+    let mut chip = chip(&[0x00EE]);
+    assert!(matches!(chip.emulate_cycle(), Err(Chip8Error::StackUnderflow{ .. })));
+    assert_eq!(chip.profiler().unwrap().total(), 0);
+    assert_eq!(chip.profiler().unwrap().count(0x200), 0);
+}
+
+#[test]
+fn loading_a_state_returns_to_the_top_level()
+{
+    let mut chip = chip(&NESTED);
+    run(&mut chip, 4);
+    let state = chip.save_state();
+    run(&mut chip, 1);
+    chip.load_state(&state).unwrap();
+    // The return of the subroutine that was active in the state goes nowhere.
+    run(&mut chip, 2);
+    let profiler = chip.profiler().unwrap();
+    assert_eq!(profiler.total(), 7);
+    assert!(profiler.folded().starts_with("main 3\n"));
+}
+
+#[test]
+fn report_lists_hot_spots_and_subroutines()
+{
+    let mut chip = chip(&NESTED);
+    run(&mut chip, 100);
+    let report = chip.profiler().unwrap().report(&chip, 2);
+    let lines : Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], "100 instructions at 8 addresses");
+    assert_eq!(lines[4], "020E              20   20.0%  ADD V1, 0x01");
+    assert_eq!(lines[6], "and 6 more addresses");
+    assert_eq!(lines[11], "sub_0208            10            30            50   50.0%");
+}
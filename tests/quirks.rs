@@ -0,0 +1,108 @@
+//! Every quirk against the behavior it changes. The plain behavior is covered by the
+//! tests of the instructions.
+
+mod common;
+
+use chip_8::{Chip, Quirks, PROGRAM_START};
+use common::{lit_pixels, run_until, step};
+
+fn machine(quirks: Quirks, program: &[u16]) -> Chip
+{
+    let mut chip = common::chip(program);
+    chip.set_quirks(quirks);
+    chip
+}
+
+fn run_with(quirks: Quirks, program: &[u16]) -> Chip
+{
+    let mut chip = machine(quirks, program);
+    run_until(&mut chip, PROGRAM_START + 2 * program.len() as u16);
+    chip
+}
+
+#[test]
+fn presets_by_name()
+{
+    assert_eq!("default".parse::<Quirks>().unwrap(), Quirks::default());
+    assert_eq!("VIP".parse::<Quirks>().unwrap(), Quirks::cosmac_vip());
+    assert_eq!("chip-48".parse::<Quirks>().unwrap(), Quirks::chip48());
+    assert_eq!("schip".parse::<Quirks>().unwrap(), Quirks::superchip());
+    assert!("vip2".parse::<Quirks>().is_err());
+
+    assert_eq!(Quirks::default().to_string(), "none");
+    assert_eq!(Quirks::cosmac_vip().to_string(), "shift-vy,load-store-i,vf-reset,clip,display-wait");
+}
+
+#[test]
+fn single_quirks_by_name()
+{
+    let mut quirks = Quirks::cosmac_vip();
+    quirks.set("clip", false).unwrap();
+    quirks.set("jump-vx", true).unwrap();
+    assert!(!quirks.clip_sprites);
+    assert!(quirks.jump_uses_vx);
+    assert_eq!(quirks.set("wrap", true).unwrap_err(), "unknown quirk \"wrap\"");
+}
+
+#[test]
+fn op_8xy1_resets_vf_with_the_quirk()
+{
+    let quirks = Quirks{ vf_reset: true, ..Quirks::default() };
+    for logic in [0x8011, 0x8012, 0x8013]
+    {
+        let chip = run_with(quirks, &[0x6F55, logic]);
+        assert_eq!(chip.registers()[0xF], 0);
+    }
+}
+
+#[test]
+fn op_8xy6_8xye_shift_vy_with_the_quirk()
+{
+    let quirks = Quirks{ shift_uses_vy: true, ..Quirks::default() };
+    let chip = run_with(quirks, &[0x60FF, 0x6103, 0x8016]);
+    assert_eq!(chip.registers()[0..2], [0x01, 0x03]);
+    assert_eq!(chip.registers()[0xF], 1);
+
+    let chip = run_with(quirks, &[0x60FF, 0x6181, 0x801E]);
+    assert_eq!(chip.registers()[0..2], [0x02, 0x81]);
+    assert_eq!(chip.registers()[0xF], 1);
+}
+
+#[test]
+fn op_bxnn_jumps_with_vx_with_the_quirk()
+{
+    let quirks = Quirks{ jump_uses_vx: true, ..Quirks::default() };
+    let mut chip = machine(quirks, &[0x6010, 0x6320, 0xB300]);
+    step(&mut chip, 3);
+    assert_eq!(chip.program_counter(), 0x320);
+}
+
+#[test]
+fn op_dxyn_clips_with_the_quirk()
+{
+    let quirks = Quirks{ clip_sprites: true, ..Quirks::default() };
+    let chip = run_with(quirks, &[0xA000, 0x603E, 0x611E, 0xD015]);
+    assert_eq!(lit_pixels(&chip), [(62, 30), (63, 30), (62, 31)]);
+}
+
+#[test]
+fn op_dxyn_waits_for_the_display_with_the_quirk()
+{
+    let quirks = Quirks{ display_wait: true, ..Quirks::default() };
+    let mut chip = machine(quirks, &[0xA000, 0xD005, 0x6001]);
+    step(&mut chip, 3);
+    assert_eq!(chip.registers()[0], 0);
+    chip.tick_timers();
+    step(&mut chip, 1);
+    assert_eq!(chip.registers()[0], 1);
+}
+
+#[test]
+fn op_fx55_fx65_advance_the_index_with_the_quirk()
+{
+    let quirks = Quirks{ load_store_increments_i: true, ..Quirks::default() };
+    let chip = run_with(quirks, &[0xA300, 0xF255]);
+    assert_eq!(chip.index_register(), 0x303);
+    let chip = run_with(quirks, &[0xA300, 0xF065]);
+    assert_eq!(chip.index_register(), 0x301);
+}
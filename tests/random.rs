@@ -0,0 +1,38 @@
+use chip_8::{Chip, Random, RandomKind};
+
+#[test]
+fn vip_sequence_follows_the_first_page_of_memory()
+{
+    // The sequence reads the font at 0x000: 0xF0, 0x90, 0x90, 0x90, ...
+    let chip = Chip::new();
+    let mut random = Random::new(RandomKind::CosmacVip, 0);
+    let bytes : Vec<u8> = (0..3).map(|_| random.next_byte(chip.memory())).collect();
+    assert_eq!(bytes, [0x49, 0xEE, 0x3C]);
+
+    // Only the low 16 bits of the seed count.
+    let mut wide = Random::new(RandomKind::CosmacVip, 0x1_0000);
+    assert_eq!(wide.next_byte(chip.memory()), 0x49);
+}
+
+#[test]
+fn cxnn_masks_the_vip_sequence()
+{
+    let mut chip = Chip::new();
+    // RND V0, 0xFF, RND V1, 0x0F, RND V2, 0xF0
+    chip.load_rom_bytes(&[0xC0, 0xFF, 0xC1, 0x0F, 0xC2, 0xF0]).unwrap();
+    chip.set_random(Random::new(RandomKind::CosmacVip, 0));
+    for _ in 0..3
+    {
+        chip.emulate_cycle().unwrap();
+    }
+    assert_eq!(chip.registers()[..3], [0x49, 0x0E, 0x30]);
+}
+
+#[test]
+fn kinds_parse_by_name()
+{
+    assert_eq!("vip".parse::<RandomKind>().unwrap(), RandomKind::CosmacVip);
+    assert_eq!("COSMAC-VIP".parse::<RandomKind>().unwrap(), RandomKind::CosmacVip);
+    assert_eq!("xorshift".parse::<RandomKind>().unwrap(), RandomKind::Xorshift);
+    assert!("mt19937".parse::<RandomKind>().is_err());
+}
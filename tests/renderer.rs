@@ -0,0 +1,71 @@
+use chip_8::terminal::{Glyphs, TerminalRenderer};
+use chip_8::{Chip, Palette, Rgb};
+
+/// A machine that drew the 0 of the font, 4x5 pixels, at the top left. The next
+/// instruction draws it again.
+fn zero() -> Chip
+{
+    let mut chip = Chip::new();
+    // 0x200: DRW V0, V0, 5, 0x202: DRW V0, V0, 5
+    chip.load_rom_bytes(&[0xD0, 0x05, 0xD0, 0x05]).unwrap();
+    chip.emulate_cycle().unwrap();
+    chip
+}
+
+fn render(renderer: &mut TerminalRenderer, chip: &Chip) -> (bool, String)
+{
+    let mut out = Vec::new();
+    let drawn = renderer.render(chip, &mut out).unwrap();
+    (drawn, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn half_blocks_color_the_top_and_bottom_pixel()
+{
+    let palette = Palette{ foreground: Rgb(1, 2, 3), background: Rgb(4, 5, 6), ..Palette::default() };
+    let mut renderer = TerminalRenderer::new(Glyphs::HalfBlock, palette);
+    let (drawn, output) = render(&mut renderer, &zero());
+    assert!(drawn);
+
+    let lines : Vec<&str> = output.split("\x1b[0m").filter(|line| !line.is_empty()).collect();
+    assert_eq!(lines.len(), 16);
+    // The 0 is F0 90 90 90 F0: the first cell row pairs F0 over 90.
+    let lit_over_lit = "\x1b[38;2;1;2;3m\x1b[48;2;1;2;3m";
+    let lit_over_unlit = "\x1b[38;2;1;2;3m\x1b[48;2;4;5;6m";
+    let unlit = "\x1b[38;2;4;5;6m\x1b[48;2;4;5;6m";
+    assert_eq!(lines[0], format!("\x1b[1;1H{}\u{2580}{}\u{2580}\u{2580}{}\u{2580}{}{}",
+        lit_over_lit, lit_over_unlit, lit_over_lit, unlit, "\u{2580}".repeat(60)));
+    assert_eq!(lines[15], format!("\x1b[16;1H{}{}", unlit, "\u{2580}".repeat(64)));
+}
+
+#[test]
+fn only_changed_lines_are_redrawn()
+{
+    let mut chip = zero();
+    let mut renderer = TerminalRenderer::new(Glyphs::HalfBlock, Palette::default());
+    render(&mut renderer, &chip);
+    assert_eq!(render(&mut renderer, &chip), (false, String::new()));
+
+    // Drawing the 0 again erases it from the first three cell rows.
+    chip.emulate_cycle().unwrap();
+    let (drawn, output) = render(&mut renderer, &chip);
+    assert!(drawn);
+    assert_eq!(output.matches(";1H").count(), 3);
+    assert!(output.starts_with("\x1b[1;1H"));
+
+    renderer.invalidate();
+    assert_eq!(render(&mut renderer, &chip).1.matches(";1H").count(), 16);
+}
+
+#[test]
+fn braille_packs_two_by_four_pixels()
+{
+    let mut renderer = TerminalRenderer::new(Glyphs::Braille, Palette::default());
+    let (_, output) = render(&mut renderer, &zero());
+    let lines : Vec<&str> = output.split("\x1b[0m").filter(|line| !line.is_empty()).collect();
+    assert_eq!(lines.len(), 8);
+    // The first four rows of the 0, F0 90 90 90, light the top and the outer columns.
+    let glyphs : Vec<char> = lines[0].chars().filter(|c| ('\u{2800}'..='\u{28FF}').contains(c)).collect();
+    assert_eq!(glyphs.len(), 32);
+    assert_eq!(glyphs[..3], ['\u{284F}', '\u{28B9}', '\u{2800}']);
+}
@@ -0,0 +1,112 @@
+use chip_8::savestate::{crc32, MIN_READER_VERSION, SAVE_STATE_VERSION};
+use chip_8::{Chip, Chip8Error, Variant};
+
+/// A machine in the middle of a program, with some state in every part.
+fn busy_chip() -> Chip
+{
+    let mut chip = Chip::new();
+    chip.set_variant(Variant::SuperChip);
+    // LD V3, 0x42, LD I, 0x300, CALL 0x208, JP 0x206, DRW V0, V0, 5
+    chip.load_rom_bytes(&[0x63, 0x42, 0xA3, 0x00, 0x22, 0x08, 0x12, 0x06, 0xD0, 0x05]).unwrap();
+    for _ in 0..4
+    {
+        chip.emulate_cycle().unwrap();
+    }
+    chip.set_key(0x7, true);
+    chip
+}
+
+/// Replaces the checksum at the end of `state` by one that matches its contents.
+fn reseal(state: &mut Vec<u8>)
+{
+    state.truncate(state.len() - 4);
+    let checksum = crc32(state);
+    state.extend_from_slice(&checksum.to_be_bytes());
+}
+
+fn load_error(state: &[u8]) -> String
+{
+    match Chip::new().load_state(state)
+    {
+        Err(Chip8Error::InvalidSaveState{ reason }) => reason,
+        Err(error) => panic!("unexpected error {}", error),
+        Ok(()) => panic!("the state loaded"),
+    }
+}
+
+#[test]
+fn states_round_trip()
+{
+    let chip = busy_chip();
+    let state = chip.save_state();
+    assert_eq!(&state[..4], b"C8SS");
+    assert_eq!(state[4..8], [0, SAVE_STATE_VERSION as u8, 0, MIN_READER_VERSION as u8]);
+
+    let mut loaded = Chip::new();
+    loaded.load_state(&state).unwrap();
+    assert_eq!(loaded.save_state(), state);
+    assert_eq!(loaded.variant(), Variant::SuperChip);
+    assert_eq!(loaded.registers()[3], 0x42);
+    assert_eq!(loaded.index_register(), 0x300);
+    assert_eq!(loaded.stack_pointer(), 1);
+    assert_eq!(loaded.program_counter(), chip.program_counter());
+    assert_eq!(loaded.texture(), chip.texture());
+}
+
+#[test]
+fn damaged_states_are_rejected()
+{
+    let mut state = busy_chip().save_state();
+    state[20] ^= 0x01;
+    assert_eq!(load_error(&state), "checksum mismatch, the file is damaged");
+    assert_eq!(load_error(&state[..6]), "not a save state");
+    assert_eq!(load_error(b"C8MV\0\x01\0\x01\0\0\0\0"), "not a save state");
+}
+
+#[test]
+fn failed_loads_leave_the_machine_unchanged()
+{
+    let mut chip = busy_chip();
+    let before = chip.save_state();
+    let mut state = before.clone();
+    state[20] ^= 0x01;
+    assert!(chip.load_state(&state).is_err());
+    assert_eq!(chip.save_state(), before);
+}
+
+#[test]
+fn states_needing_a_newer_reader_are_rejected()
+{
+    let mut state = busy_chip().save_state();
+    let newer = SAVE_STATE_VERSION + 1;
+    state[6..8].copy_from_slice(&newer.to_be_bytes());
+    reseal(&mut state);
+    assert_eq!(load_error(&state), format!("version {} needs a reader for version {} or newer, this is version {}",
+        SAVE_STATE_VERSION, newer, SAVE_STATE_VERSION));
+}
+
+#[test]
+fn newer_states_with_unknown_chunks_still_load()
+{
+    let chip = busy_chip();
+    let mut state = chip.save_state();
+    // A newer writer that only added a chunk keeps the minimum reader version.
+    state[4..6].copy_from_slice(&(SAVE_STATE_VERSION + 1).to_be_bytes());
+    let position = state.len() - 4;
+    state.splice(position..position, b"NEW!\0\0\0\x03abc".iter().copied());
+    reseal(&mut state);
+
+    let mut loaded = Chip::new();
+    loaded.load_state(&state).unwrap();
+    assert_eq!(loaded.save_state(), chip.save_state());
+}
+
+#[test]
+fn truncated_chunks_are_rejected()
+{
+    let mut state = busy_chip().save_state();
+    let position = state.len() - 4;
+    state.splice(position..position, b"NEW!\0\0\0\x09abc".iter().copied());
+    reseal(&mut state);
+    assert_eq!(load_error(&state), "truncated chunk");
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::time::Duration;
+
+use chip_8::scheduler::{ManualClock, Scheduler};
+
+/// The time `count` 60 Hz frames take.
+fn frames(count: u64) -> Duration
+{
+    Duration::from_secs(count) / 60
+}
+
+fn millis(ms: u64) -> Duration
+{
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn frames_become_due_at_60_hz()
+{
+    let clock = ManualClock::new();
+    let mut scheduler = Scheduler::new(&clock, 10);
+    let mut chip = common::chip(&[0x1200]);
+    assert_eq!(scheduler.due_frames(), 1);
+    scheduler.run_frame(&mut chip).unwrap();
+    assert_eq!(scheduler.due_frames(), 0);
+    assert_eq!(scheduler.time_until_next_frame(), frames(1));
+
+    clock.advance(millis(10));
+    assert_eq!(scheduler.due_frames(), 0);
+    clock.advance(millis(40));
+    assert_eq!(scheduler.due_frames(), 3);
+    // Asking does not consume the frames.
+    assert_eq!(scheduler.due_frames(), 3);
+    for _ in 0..3
+    {
+        scheduler.run_frame(&mut chip).unwrap();
+    }
+    assert_eq!(scheduler.due_frames(), 0);
+    assert_eq!((scheduler.frames(), scheduler.cycles()), (4, 40));
+}
+
+#[test]
+fn falling_far_behind_drops_the_missed_time()
+{
+    let clock = ManualClock::new();
+    let mut scheduler = Scheduler::new(&clock, 10);
+    let mut chip = common::chip(&[0x1200]);
+    clock.set(Duration::from_secs(5));
+    assert_eq!(scheduler.due_frames(), 4);
+    for _ in 0..4
+    {
+        scheduler.run_frame(&mut chip).unwrap();
+    }
+    // The catch-up frames counted from now, so the next one is a few frames ahead.
+    assert_eq!(scheduler.due_frames(), 0);
+    assert_eq!(scheduler.time_until_next_frame(), frames(4));
+    clock.advance(frames(4) + millis(1));
+    assert_eq!(scheduler.due_frames(), 1);
+}
+
+#[test]
+fn skipped_frames_take_time_but_run_nothing()
+{
+    let clock = ManualClock::new();
+    let mut scheduler = Scheduler::new(&clock, 4);
+    // LD V0, 10, LD DT, V0, ADD V1, 1, JP 0x204
+    let mut chip = common::chip(&[0x600A, 0xF015, 0x7101, 0x1204]);
+    scheduler.step(&mut chip).unwrap();
+    scheduler.step(&mut chip).unwrap();
+    scheduler.skip_frame();
+    assert_eq!(scheduler.due_frames(), 0);
+    assert_eq!(scheduler.frames(), 0);
+    assert_eq!(chip.delay_timer(), 10);
+
+    // The skipped frame's instructions do not count towards the next one.
+    clock.advance(frames(1));
+    assert_eq!(scheduler.due_frames(), 1);
+    for _ in 0..3
+    {
+        assert!(!scheduler.step(&mut chip).unwrap());
+    }
+    assert!(scheduler.step(&mut chip).unwrap());
+    assert_eq!(scheduler.frames(), 1);
+    assert_eq!(chip.delay_timer(), 9);
+}
+
+#[test]
+fn timers_tick_once_per_frame()
+{
+    let mut scheduler = Scheduler::new(ManualClock::new(), 3);
+    // LD V0, 5, LD DT, V0, LD ST, V0, JP 0x206
+    let mut chip = common::chip(&[0x6005, 0xF015, 0xF018, 0x1206]);
+    // Both timers were set during the frame and tick at its end.
+    scheduler.run_frame(&mut chip).unwrap();
+    assert_eq!((chip.delay_timer(), chip.sound_timer()), (4, 4));
+    for _ in 0..10
+    {
+        scheduler.run_frame(&mut chip).unwrap();
+    }
+    assert_eq!((chip.delay_timer(), chip.sound_timer()), (0, 0));
+    assert_eq!(scheduler.cycles(), 33);
+
+    scheduler.set_instructions_per_frame(0);
+    assert_eq!(scheduler.instructions_per_frame(), 1);
+}
@@ -0,0 +1,75 @@
+use chip_8::screenshot::{self, ImageFormat};
+use chip_8::{Chip, Palette, Rgb};
+
+/// A 8x2 framebuffer with one pixel of every value in the first row.
+fn texture() -> Vec<u8>
+{
+    let mut texture = vec![0; 16];
+    texture[..4].copy_from_slice(&[0, 1, 2, 3]);
+    texture
+}
+
+#[test]
+fn formats_follow_the_extension()
+{
+    assert_eq!(ImageFormat::from_path("shot.PNG"), Some(ImageFormat::Png));
+    assert_eq!(ImageFormat::from_path("dir/shot.pbm"), Some(ImageFormat::Pbm));
+    assert_eq!(ImageFormat::from_path("shot.pgm"), Some(ImageFormat::Pgm));
+    assert_eq!(ImageFormat::from_path("shot.bmp"), None);
+    assert_eq!(ImageFormat::from_path("shot"), None);
+}
+
+#[test]
+fn png_uses_the_palette_and_scale()
+{
+    let palette = Palette{ background: Rgb(1, 2, 3), ..Palette::default() };
+    let image = screenshot::encode(&texture(), 8, ImageFormat::Png, 3, &palette);
+
+    let mut reader = png::Decoder::new(image.as_slice()).read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    assert_eq!((info.width, info.height, info.color_type), (24, 6, png::ColorType::Rgb));
+
+    let pixel = |x: usize, y: usize| Rgb(data[(y * 24 + x) * 3], data[(y * 24 + x) * 3 + 1], data[(y * 24 + x) * 3 + 2]);
+    assert_eq!(pixel(0, 0), palette.background);
+    assert_eq!(pixel(3, 2), palette.foreground);
+    assert_eq!(pixel(5, 0), palette.foreground);
+    assert_eq!(pixel(6, 1), palette.plane2);
+    assert_eq!(pixel(11, 2), palette.overlap);
+    assert_eq!(pixel(11, 3), palette.background);
+    assert_eq!(pixel(12, 0), palette.background);
+}
+
+#[test]
+fn pbm_marks_dark_pixels()
+{
+    let image = screenshot::encode(&texture(), 8, ImageFormat::Pbm, 1, &Palette::default());
+    // The black background and the dark overlap color are 1, white and bright orange 0.
+    assert_eq!(image, b"P4\n8 2\n\x9F\xFF");
+
+    // Rows of 10 pixels take two bytes.
+    let image = screenshot::encode(&[1; 10], 5, ImageFormat::Pbm, 2, &Palette::default());
+    assert_eq!(image, b"P4\n10 4\n\x00\x00\x00\x00\x00\x00\x00\x00");
+}
+
+#[test]
+fn pgm_stores_brightness()
+{
+    let image = screenshot::encode(&texture(), 8, ImageFormat::Pgm, 1, &Palette::default());
+    let header = b"P5\n8 2\n255\n";
+    assert_eq!(&image[..header.len()], header);
+    assert_eq!(image[header.len()..header.len() + 5], [0, 255, 136, 50, 0]);
+    assert_eq!(image.len(), header.len() + 16);
+}
+
+#[test]
+fn captures_the_visible_screen()
+{
+    let mut chip = Chip::new();
+    chip.set_variant(chip_8::Variant::SuperChip);
+    // 0x200: HIGH
+    chip.load_rom_bytes(&[0x00, 0xFF]).unwrap();
+    chip.emulate_cycle().unwrap();
+    let image = screenshot::capture(&chip, ImageFormat::Pgm, 1, &Palette::default());
+    assert!(image.starts_with(b"P5\n128 64\n255\n"));
+}
@@ -0,0 +1,103 @@
+//! The SUPER-CHIP 1.1 instructions and display. The machines run with all quirks off.
+
+mod common;
+
+use chip_8::{Chip, Chip8Error, Variant, BIG_FONT_ADDRESS, PROGRAM_START};
+use common::{lit_pixels, run_until, step};
+
+fn machine(program: &[u16]) -> Chip
+{
+    common::chip_with(Variant::SuperChip, program)
+}
+
+fn run(program: &[u16]) -> Chip
+{
+    let mut chip = machine(program);
+    run_until(&mut chip, PROGRAM_START + 2 * program.len() as u16);
+    chip
+}
+
+#[test]
+fn op_dxy0_draws_16x16_on_superchip()
+{
+    let mut chip = machine(&[]);
+    let mut rom = vec![0xA3, 0x00, 0xD0, 0x00];
+    rom.resize(0x100, 0);
+    rom.extend_from_slice(&[0xFF; 32]);
+    chip.load_rom_bytes(&rom).unwrap();
+    step(&mut chip, 2);
+    assert_eq!(lit_pixels(&chip).len(), 256);
+
+    // CHIP-8 draws nothing for a height of 0.
+    let mut chip = common::chip(&[0xA000, 0xD000]);
+    step(&mut chip, 2);
+    assert!(lit_pixels(&chip).is_empty());
+}
+
+#[test]
+fn op_fx30_points_at_the_big_font()
+{
+    let chip = run(&[0x6003, 0xF030]);
+    assert_eq!(chip.index_register(), BIG_FONT_ADDRESS + 30);
+}
+
+#[test]
+fn op_fx75_fx85_rpl_flags()
+{
+    let chip = run(&[0x6011, 0x6122, 0xF175, 0x6000, 0x6100, 0xF185]);
+    assert_eq!(chip.registers()[0..2], [0x11, 0x22]);
+    assert_eq!(chip.rpl_flags()[0..2], [0x11, 0x22]);
+
+    // SUPER-CHIP has 8 flags.
+    let chip = run(&[0x6899, 0xFF75]);
+    assert_eq!(chip.rpl_flags().len(), 8);
+}
+
+#[test]
+fn op_00fe_00ff_switch_resolution()
+{
+    let chip = run(&[0xA000, 0xD005, 0x00FF]);
+    assert!(chip.is_hires());
+    assert_eq!((chip.screen_width(), chip.screen_height()), (128, 64));
+    assert!(lit_pixels(&chip).is_empty());
+
+    let chip = run(&[0x00FF, 0x00FE]);
+    assert!(!chip.is_hires());
+}
+
+#[test]
+fn op_00cn_scrolls_down()
+{
+    let chip = run(&[0xA000, 0xD001, 0x00C3]);
+    assert_eq!(lit_pixels(&chip), [(0, 3), (1, 3), (2, 3), (3, 3)]);
+}
+
+#[test]
+fn op_00fb_00fc_scroll_sideways()
+{
+    let chip = run(&[0xA000, 0xD001, 0x00FB]);
+    assert_eq!(lit_pixels(&chip), [(4, 0), (5, 0), (6, 0), (7, 0)]);
+
+    let chip = run(&[0xA000, 0x6004, 0x6100, 0xD011, 0x00FC]);
+    assert_eq!(lit_pixels(&chip), [(0, 0), (1, 0), (2, 0), (3, 0)]);
+}
+
+#[test]
+fn op_00fd_exits()
+{
+    let mut chip = machine(&[0x00FD, 0x6001]);
+    step(&mut chip, 3);
+    assert!(chip.is_halted());
+    assert_eq!(chip.program_counter(), 0x200);
+    assert_eq!(chip.registers()[0], 0);
+}
+
+#[test]
+fn superchip_opcodes_need_the_variant()
+{
+    for opcode in [0x00C1, 0x00FB, 0x00FC, 0x00FD, 0x00FE, 0x00FF, 0xF030, 0xF075, 0xF085]
+    {
+        let mut chip = common::chip(&[opcode]);
+        assert!(matches!(chip.emulate_cycle(), Err(Chip8Error::UnknownOpcode{ .. })), "{:04X}", opcode);
+    }
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use chip_8::trace::{self, TraceFormat, Tracer};
+use chip_8::{Chip, Variant};
+
+use common::chip_with;
+
+/// A writer whose contents stay readable while the tracer owns it.
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared
+{
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize>
+    {
+        self.0.borrow_mut().write(bytes)
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        Ok(())
+    }
+}
+
+/// Runs `cycles` instructions with a tracer set up by `setup` and returns what it wrote.
+fn traced(chip: &mut Chip, format: TraceFormat, cycles: usize, setup: impl FnOnce(&mut Tracer)) -> Vec<u8>
+{
+    let out = Shared::default();
+    let mut tracer = Tracer::new(out.clone(), format);
+    setup(&mut tracer);
+    chip.set_tracer(Some(tracer));
+    for _ in 0..cycles
+    {
+        chip.emulate_cycle().unwrap();
+    }
+    chip.take_tracer().unwrap().finish().unwrap();
+    let bytes = out.0.borrow().clone();
+    bytes
+}
+
+fn lines(bytes: &[u8]) -> Vec<String>
+{
+    String::from_utf8(bytes.to_vec()).unwrap().lines().map(str::to_string).collect()
+}
+
+#[test]
+fn text_lines_show_the_changed_registers()
+{
+    let mut chip = chip_with(Variant::Chip8, &[0x6A02, 0xA22A, 0x7A01, 0x3A03, 0x1200, 0x1200]);
+    let trace = lines(&traced(&mut chip, TraceFormat::Text, 5, |_| {}));
+    assert_eq!(trace, [
+        "         0 0200  6A02      LD VA, 0x02          VA=02",
+        "         1 0202  A22A      LD I, 0x22A          I=022A",
+        "         2 0204  7A01      ADD VA, 0x01         VA=03",
+        "         3 0206  3A03      SE VA, 0x03",
+        "         4 020A  1200      JP 0x200",
+    ].iter().map(|line| line.to_string()).collect::<Vec<_>>());
+}
+
+#[test]
+fn range_filters_addresses_but_keeps_the_numbering()
+{
+    // A loop of three instructions, of which only the jump is traced.
+    let mut chip = chip_with(Variant::Chip8, &[0x7001, 0x7101, 0x1200]);
+    let trace = lines(&traced(&mut chip, TraceFormat::Text, 9, |tracer| tracer.set_range(0x204..=0x204)));
+    assert_eq!(trace.len(), 3);
+    assert!(trace[0].starts_with("         2 0204  1200"));
+    assert!(trace[2].starts_with("         8 0204  1200"));
+}
+
+#[test]
+fn limit_stops_the_trace()
+{
+    let mut chip = chip_with(Variant::Chip8, &[0x7001, 0x1200]);
+    let out = Shared::default();
+    let mut tracer = Tracer::new(out.clone(), TraceFormat::Text);
+    tracer.set_limit(Some(5));
+    chip.set_tracer(Some(tracer));
+    for _ in 0..20
+    {
+        chip.emulate_cycle().unwrap();
+    }
+    assert_eq!(chip.tracer().unwrap().written(), 5);
+    assert_eq!(lines(&out.0.borrow()).len(), 5);
+    // The machine itself keeps running.
+    assert_eq!(chip.registers()[0], 10);
+}
+
+#[test]
+fn binary_traces_read_back_as_the_text_lines()
+{
+    let program = [0x6A02, 0xF000, 0xBEEF, 0x7001, 0x7001, 0x1206];
+    let text = lines(&traced(&mut chip_with(Variant::XoChip, &program), TraceFormat::Text, 8, |tracer| tracer.set_range(0x200..=0x206)));
+    let binary = traced(&mut chip_with(Variant::XoChip, &program), TraceFormat::Binary, 8, |tracer| tracer.set_range(0x200..=0x206));
+    assert_eq!(&binary[..4], b"C8TR");
+
+    let records = trace::read_binary(&binary).unwrap();
+    assert_eq!(records.iter().map(|record| record.to_string()).collect::<Vec<_>>(), text);
+    assert_eq!(records[1].to_string(), "         1 0202  F000BEEF  LD I, LONG 0xBEEF    I=BEEF");
+    assert_eq!(records.last().unwrap().index, 5);
+}
+
+#[test]
+fn damaged_binary_traces_are_rejected()
+{
+    let binary = traced(&mut chip_with(Variant::Chip8, &[0x6001, 0x1200]), TraceFormat::Binary, 4, |_| {});
+    assert!(trace::read_binary(&binary[..binary.len() - 1]).is_err());
+    assert!(trace::read_binary(b"not a trace").is_err());
+    assert_eq!(trace::read_binary(&[]).unwrap(), []);
+}
+
+#[test]
+fn tracer_survives_loading_a_state()
+{
+    let mut chip = chip_with(Variant::Chip8, &[0x7001, 0x1200]);
+    let state = chip.save_state();
+    let out = Shared::default();
+    chip.set_tracer(Some(Tracer::new(out.clone(), TraceFormat::Text)));
+    chip.emulate_cycle().unwrap();
+    chip.load_state(&state).unwrap();
+    chip.emulate_cycle().unwrap();
+    chip.take_tracer().unwrap().finish().unwrap();
+
+    let trace = lines(&out.0.borrow());
+    assert_eq!(trace.len(), 2);
+    assert!(trace[1].starts_with("         1 0200  7001"));
+}
@@ -0,0 +1,101 @@
+//! The XO-CHIP instructions, bit-planes and 64 KiB of memory. The machines run with all
+//! quirks off.
+
+mod common;
+
+use chip_8::{Chip, Chip8Error, Variant, PROGRAM_START};
+use common::{lit_pixels, run_until, step};
+
+fn machine(program: &[u16]) -> Chip
+{
+    common::chip_with(Variant::XoChip, program)
+}
+
+fn run(program: &[u16]) -> Chip
+{
+    let mut chip = machine(program);
+    run_until(&mut chip, PROGRAM_START + 2 * program.len() as u16);
+    chip
+}
+
+#[test]
+fn op_5xy2_5xy3_save_and_load_ranges()
+{
+    let chip = run(&[0x6111, 0x6222, 0x6333, 0xA300, 0x5132, 0xA300, 0x5433]);
+    assert_eq!(chip.memory()[0x300..0x303], [0x11, 0x22, 0x33]);
+    // Loading from V4 down to V3 reverses the order.
+    assert_eq!(chip.registers()[3..5], [0x22, 0x11]);
+    assert_eq!(chip.index_register(), 0x300);
+
+    let chip = run(&[0xA000, 0x5133]);
+    assert_eq!(chip.registers()[1..4], [0xF0, 0x90, 0x90]);
+}
+
+#[test]
+fn op_f000_loads_a_long_index()
+{
+    let chip = run(&[0xF000, 0xBEEF, 0x6001]);
+    assert_eq!(chip.index_register(), 0xBEEF);
+    assert_eq!(chip.registers()[0], 1);
+}
+
+#[test]
+fn skips_step_over_f000_on_xochip()
+{
+    let chip = run(&[0x3000, 0xF000, 0x1234, 0x6001]);
+    assert_eq!(chip.index_register(), 0);
+    assert_eq!(chip.registers()[0], 1);
+}
+
+#[test]
+fn op_fn01_selects_planes()
+{
+    let chip = run(&[0xF201, 0xA000, 0xD001]);
+    assert_eq!(chip.plane_mask(), 2);
+    assert_eq!(chip.texture()[0..5], [2, 2, 2, 2, 0]);
+
+    // With both planes the second one's row follows the first one's: 0xF0, then 0x90.
+    let chip = run(&[0xF301, 0xA000, 0xD001]);
+    assert_eq!(chip.texture()[0..5], [3, 1, 1, 3, 0]);
+
+    // Clearing only touches the selected planes.
+    let chip = run(&[0xF301, 0xA000, 0xD001, 0xF101, 0x00E0]);
+    assert_eq!(chip.texture()[0..5], [2, 0, 0, 2, 0]);
+}
+
+#[test]
+fn op_00dn_scrolls_up()
+{
+    let chip = run(&[0xA000, 0x6103, 0xD011, 0x00D2]);
+    assert_eq!(lit_pixels(&chip), [(0, 1), (1, 1), (2, 1), (3, 1)]);
+}
+
+#[test]
+fn op_f002_fx3a_audio()
+{
+    let chip = run(&[0xA000, 0xF002, 0x6070, 0xF03A]);
+    assert_eq!(chip.audio_pattern().unwrap()[..5], [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+    assert_eq!(chip.pitch(), 0x70);
+}
+
+#[test]
+fn op_fx0a_waits_at_the_end_of_memory()
+{
+    // ADD V0, 0 all the way up to an FX0A in the last word of memory.
+    let mut program = vec![0x7000; (0xFFFE - 0x200) / 2];
+    program.push(0xF30A);
+    let mut chip = machine(&program);
+    step(&mut chip, program.len() + 2);
+    assert!(chip.is_waiting_for_key());
+    assert_eq!(chip.program_counter(), 0xFFFE);
+}
+
+#[test]
+fn xochip_opcodes_need_the_variant()
+{
+    for opcode in [0x00D1, 0x5012, 0x5013, 0xF000, 0xF101, 0xF002, 0xF03A]
+    {
+        let mut chip = common::chip_with(Variant::SuperChip, &[opcode]);
+        assert!(matches!(chip.emulate_cycle(), Err(Chip8Error::UnknownOpcode{ .. })), "{:04X}", opcode);
+    }
+}